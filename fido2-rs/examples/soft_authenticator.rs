@@ -0,0 +1,78 @@
+//! Example: exercise the in-process software authenticator
+//!
+//! No device is needed, everything runs against [SoftAuthenticator].
+//!
+//! Usage: cargo run --example soft_authenticator --features soft-authenticator
+
+use fido2_rs::assertion::AssertRequest;
+use fido2_rs::credentials::{CoseType, Credential, CredentialRecord, Extensions, Opt};
+use fido2_rs::soft::SoftAuthenticator;
+
+fn main() -> anyhow::Result<()> {
+    let pin = "1234";
+    let authenticator = SoftAuthenticator::new();
+    let dev = authenticator.open()?;
+
+    println!("fido2: {}, pin set: {}", dev.is_fido2(), dev.has_pin());
+    dev.set_pin(pin, None)?;
+    assert!(dev.has_pin());
+
+    // Make a resident credential with a largeBlobKey
+    let mut cred = Credential::new();
+    cred.set_client_data([0u8; 32])?;
+    cred.set_rp("fido2-rs.example", "soft authenticator example")?;
+    cred.set_user([1, 2, 3, 4], "alice", Some("Alice"), None)?;
+    cred.set_cose_type(CoseType::ES256)?;
+    cred.set_rk(Opt::True)?;
+    cred.set_extension(Extensions::LARGEBLOB_KEY)?;
+
+    dev.make_credential(&mut cred, Some(pin))?;
+    cred.verify_self()?;
+    println!("credential id: {:02x?}", cred.id());
+
+    // Restore it from an owned copy and verify the attestation again
+    let record = CredentialRecord::from(&*cred);
+    Credential::try_from(&record)?.verify_self()?;
+
+    // Get an assertion for it
+    let mut request = AssertRequest::new();
+    request.set_rp("fido2-rs.example")?;
+    request.set_client_data([1u8; 32])?;
+    request.set_allow_credential(cred.id())?;
+
+    let assertions = dev.get_assertion(request, Some(pin))?;
+    for assertion in assertions.iter() {
+        println!(
+            "assertion: counter {}, flags {:#04x}",
+            assertion.counter(),
+            assertion.flags()
+        );
+    }
+
+    // Enumerate resident credentials
+    let credman = dev.credman(pin)?;
+    println!("resident credentials: {}", credman.count());
+    for rp in credman.get_rp()? {
+        println!("rp: {:?}", rp.id);
+        for rk in credman.get_rk(rp.id)?.iter() {
+            println!("  user: {:?}", rk.user_name());
+        }
+    }
+    drop(credman);
+
+    // Round trip a largeBlob
+    let payload = b"Hello from the soft authenticator!";
+    dev.largeblob_set(cred.large_blob_key(), payload, pin)?;
+    assert_eq!(dev.largeblob_get(cred.large_blob_key())?, payload);
+    println!("largeBlob round-trip verified!");
+
+    // Deny user presence
+    authenticator.set_user_presence(false);
+    let mut request = AssertRequest::new();
+    request.set_rp("fido2-rs.example")?;
+    request.set_client_data([2u8; 32])?;
+    assert!(dev.get_assertion(request, Some(pin)).is_err());
+    println!("denied assertion rejected");
+
+    Ok(())
+}
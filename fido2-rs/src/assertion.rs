@@ -1,10 +1,12 @@
+use crate::authdata::AuthenticatorData;
 use crate::credentials::{CoseType, Opt};
-use crate::error::{FidoError, Result};
+use crate::error::{Error, FidoError, Result};
 use crate::key::{ES256, ES384, Eddsa, Rsa};
-use crate::utils::check;
+use crate::utils::{check, slice_or_empty};
 use ffi::FIDO_ERR_INVALID_ARGUMENT;
 use openssl::nid::Nid;
 use openssl::pkey::{Id, PKey, Public};
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::marker::PhantomData;
 use std::ptr::NonNull;
@@ -91,25 +93,6 @@ macro_rules! impl_assertion_set {
 
                 Ok(())
             }
-
-            /// Allow a credential in a FIDO2 assertion.
-            ///
-            /// Add id to the list of credentials allowed in assert.
-            ///
-            /// If fails, the existing list of allowed credentials is preserved.
-            pub fn set_allow_credential(&mut self, id: impl AsRef<[u8]>) -> Result<()> {
-                let id = id.as_ref();
-
-                unsafe {
-                    check(ffi::fido_assert_allow_cred(
-                        self.$($f).*.as_ptr(),
-                        id.as_ptr(),
-                        id.len(),
-                    ))?;
-                }
-
-                Ok(())
-            }
         }
     };
 }
@@ -119,6 +102,8 @@ pub struct Assertions {
     pub(crate) ptr: NonNull<ffi::fido_assert_t>,
 }
 
+unsafe impl Send for Assertions {}
+
 /// A single FIDO assertion.
 pub struct Assertion<'a> {
     ptr: NonNull<ffi::fido_assert_t>,
@@ -144,6 +129,25 @@ impl AssertRequest {
         }
     }
 
+    /// Allow a credential in a FIDO2 assertion.
+    ///
+    /// Add id to the list of credentials allowed in assert.
+    ///
+    /// If fails, the existing list of allowed credentials is preserved.
+    pub fn set_allow_credential(&mut self, id: impl AsRef<[u8]>) -> Result<()> {
+        let id = id.as_ref();
+
+        unsafe {
+            check(ffi::fido_assert_allow_cred(
+                self.0.ptr.as_ptr(),
+                id.as_ptr(),
+                id.len(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
     pub fn set_hmac_salt(&mut self, salt: &[u8]) -> Result<()> {
         unsafe {
             check(ffi::fido_assert_set_hmac_salt(
@@ -157,25 +161,165 @@ impl AssertRequest {
     }
 }
 
-/// helper for verify an exist single assertion
-pub struct AssertVerifier(Assertions);
+/// Helper for verifying existing assertions.
+///
+/// A verifier holds one or more assertion statements, made over the same client data hash and
+/// relying party ID, e.g. the assertions returned for the resident credentials of a single request.
+/// Assertions made for different client data need separate verifiers, see
+/// [AssertVerifier::verify_batch].
+///
+/// libfido2 does not keep the credential ID and user handle of a statement it did not obtain from
+/// a device, so they are tracked and checked here, see [AssertVerifier::set_allow_credential] and
+/// [AssertVerifier::set_user_id].
+pub struct AssertVerifier {
+    asserts: Assertions,
+    ids: Vec<Vec<u8>>,
+    user_handles: Vec<Option<Vec<u8>>>,
+    allowed: Vec<Vec<u8>>,
+    user_id: Option<Vec<u8>>,
+}
 
-impl_assertion_set!(AssertVerifier, 0.ptr);
+impl_assertion_set!(AssertVerifier, asserts.ptr);
 
 impl AssertVerifier {
-    /// Return a [AssertVerifier] for verify.
+    /// Return a [AssertVerifier] for verify a single assertion.
     #[allow(clippy::new_without_default)]
     pub fn new() -> AssertVerifier {
         unsafe {
             let assert = ffi::fido_assert_new();
             ffi::fido_assert_set_count(assert, 1);
 
-            AssertVerifier(Assertions {
-                ptr: NonNull::new_unchecked(assert),
-            })
+            AssertVerifier {
+                asserts: Assertions {
+                    ptr: NonNull::new_unchecked(assert),
+                },
+                ids: vec![Vec::new()],
+                user_handles: vec![None],
+                allowed: Vec::new(),
+                user_id: None,
+            }
         }
     }
 
+    /// Return a [AssertVerifier] for verify `count` assertions.
+    pub fn with_count(count: usize) -> Result<AssertVerifier> {
+        let mut verifier = unsafe {
+            let assert = ffi::fido_assert_new();
+
+            AssertVerifier {
+                asserts: Assertions {
+                    ptr: NonNull::new_unchecked(assert),
+                },
+                ids: Vec::new(),
+                user_handles: Vec::new(),
+                allowed: Vec::new(),
+                user_id: None,
+            }
+        };
+        verifier.set_count(count)?;
+
+        Ok(verifier)
+    }
+
+    /// Return a [AssertVerifier] for a WebAuthn `AuthenticatorAssertionResponse`.
+    ///
+    /// `id` is the `rawId` of the `PublicKeyCredential`, `auth_data` the raw authenticator data and
+    /// `user_handle` the user handle of the response, if any.
+    ///
+    /// The client data hash is computed from `client_data_json`, user presence is required, and
+    /// user verification is required if `uv` is [Opt::True].
+    pub fn from_response(
+        rp_id: impl AsRef<str>,
+        id: impl AsRef<[u8]>,
+        auth_data: impl AsRef<[u8]>,
+        client_data_json: impl AsRef<[u8]>,
+        signature: impl AsRef<[u8]>,
+        user_handle: Option<&[u8]>,
+        uv: Opt,
+    ) -> Result<AssertVerifier> {
+        let mut verifier = AssertVerifier::new();
+        verifier.set_rp(rp_id)?;
+        verifier.set_client_data(client_data_json)?;
+        verifier.set_up(Opt::True)?;
+        verifier.set_uv(uv)?;
+        verifier.set_id(0, id)?;
+        verifier.set_auth_data_raw(auth_data)?;
+        verifier.set_signature(signature)?;
+        verifier.set_user_handle(0, user_handle)?;
+
+        Ok(verifier)
+    }
+
+    /// Set the number of assertion statements.
+    ///
+    /// Existing statements below `count` are preserved.
+    pub fn set_count(&mut self, count: usize) -> Result<()> {
+        unsafe {
+            check(ffi::fido_assert_set_count(self.asserts.ptr.as_ptr(), count))?;
+        }
+
+        self.ids.resize(count, Vec::new());
+        self.user_handles.resize(count, None);
+
+        Ok(())
+    }
+
+    /// Return the number of assertion statements.
+    pub fn count(&self) -> usize {
+        self.asserts.count()
+    }
+
+    /// Set the credential ID of the statement `idx`.
+    pub fn set_id(&mut self, idx: usize, id: impl AsRef<[u8]>) -> Result<()> {
+        let slot = self
+            .ids
+            .get_mut(idx)
+            .ok_or(FidoError::new(FIDO_ERR_INVALID_ARGUMENT))?;
+        *slot = id.as_ref().to_vec();
+
+        Ok(())
+    }
+
+    /// Return the credential ID of the statement `idx`.
+    pub fn id(&self, idx: usize) -> Option<&[u8]> {
+        self.ids.get(idx).map(Vec::as_slice)
+    }
+
+    /// Set the user handle of the statement `idx`.
+    pub fn set_user_handle(&mut self, idx: usize, user_handle: Option<&[u8]>) -> Result<()> {
+        let slot = self
+            .user_handles
+            .get_mut(idx)
+            .ok_or(FidoError::new(FIDO_ERR_INVALID_ARGUMENT))?;
+        *slot = user_handle.map(<[u8]>::to_vec);
+
+        Ok(())
+    }
+
+    /// Return the user handle of the statement `idx`.
+    pub fn user_handle(&self, idx: usize) -> Option<&[u8]> {
+        self.user_handles.get(idx)?.as_deref()
+    }
+
+    /// Allow a credential, by ID, in the verified statements.
+    ///
+    /// Once a credential is allowed, [AssertVerifier::verify_at] rejects a statement whose
+    /// credential ID is not one of the allowed credentials, as for the `allowCredentials` of the
+    /// request.
+    pub fn set_allow_credential(&mut self, id: impl AsRef<[u8]>) -> Result<()> {
+        self.allowed.push(id.as_ref().to_vec());
+
+        Ok(())
+    }
+
+    /// Set the user ID of the user account the credentials were created for.
+    ///
+    /// [AssertVerifier::verify_at] then rejects a statement whose user handle is not `user_id`.
+    /// A statement without user handle is accepted, as the user was identified before the request.
+    pub fn set_user_id(&mut self, user_id: impl AsRef<[u8]>) {
+        self.user_id = Some(user_id.as_ref().to_vec());
+    }
+
     /// Set the authenticator data part of the statement.
     ///
     /// A copy of data is made, and no references to the passed data are kept.
@@ -185,12 +329,17 @@ impl AssertVerifier {
     ///
     /// Alternatively, a raw binary blob may be passed to [AssertVerifier::set_auth_data_raw]
     pub fn set_auth_data(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        self.set_auth_data_at(0, data)
+    }
+
+    /// Set the authenticator data part of the statement `idx`, see [AssertVerifier::set_auth_data].
+    pub fn set_auth_data_at(&mut self, idx: usize, data: impl AsRef<[u8]>) -> Result<()> {
         let data = data.as_ref();
 
         unsafe {
             check(ffi::fido_assert_set_authdata(
-                self.0.ptr.as_ptr(),
-                0,
+                self.asserts.ptr.as_ptr(),
+                idx,
                 data.as_ptr(),
                 data.len(),
             ))?;
@@ -201,12 +350,17 @@ impl AssertVerifier {
 
     /// Set the raw binary authenticator data part of the statement.
     pub fn set_auth_data_raw(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        self.set_auth_data_raw_at(0, data)
+    }
+
+    /// Set the raw binary authenticator data part of the statement `idx`.
+    pub fn set_auth_data_raw_at(&mut self, idx: usize, data: impl AsRef<[u8]>) -> Result<()> {
         let data = data.as_ref();
 
         unsafe {
             check(ffi::fido_assert_set_authdata_raw(
-                self.0.ptr.as_ptr(),
-                0,
+                self.asserts.ptr.as_ptr(),
+                idx,
                 data.as_ptr(),
                 data.len(),
             ))?;
@@ -217,12 +371,17 @@ impl AssertVerifier {
 
     /// Set the signature part of the statement.
     pub fn set_signature(&mut self, signature: impl AsRef<[u8]>) -> Result<()> {
+        self.set_signature_at(0, signature)
+    }
+
+    /// Set the signature part of the statement `idx`.
+    pub fn set_signature_at(&mut self, idx: usize, signature: impl AsRef<[u8]>) -> Result<()> {
         let signature = signature.as_ref();
 
         unsafe {
             check(ffi::fido_assert_set_sig(
-                self.0.ptr.as_ptr(),
-                0,
+                self.asserts.ptr.as_ptr(),
+                idx,
                 signature.as_ptr(),
                 signature.len(),
             ))?;
@@ -241,14 +400,32 @@ impl AssertVerifier {
     /// # Return
     /// On verify success, this method return Ok(()), otherwise return Err.
     pub fn verify(&self, public_key: PKey<Public>) -> Result<()> {
+        self.verify_at(0, public_key)
+    }
+
+    /// Verify the statement `idx`, see [AssertVerifier::verify].
+    pub fn verify_at(&self, idx: usize, public_key: PKey<Public>) -> Result<()> {
+        let id = self
+            .ids
+            .get(idx)
+            .ok_or(FidoError::new(FIDO_ERR_INVALID_ARGUMENT))?;
+        if !self.allowed.is_empty() && !self.allowed.contains(id) {
+            return Err(Error::Verification("credential ID not allowed"));
+        }
+        if let (Some(user_id), Some(user_handle)) = (&self.user_id, &self.user_handles[idx])
+            && user_id != user_handle
+        {
+            return Err(Error::Verification("user handle"));
+        }
+
         match public_key.id() {
             Id::ED25519 => {
                 let pk = Eddsa::try_from(public_key)?;
 
                 unsafe {
                     check(ffi::fido_assert_verify(
-                        self.0.ptr.as_ptr(),
-                        0,
+                        self.asserts.ptr.as_ptr(),
+                        idx,
                         CoseType::EDDSA as i32,
                         pk.as_ptr().cast(),
                     ))?;
@@ -259,9 +436,9 @@ impl AssertVerifier {
 
                 unsafe {
                     check(ffi::fido_assert_verify(
-                        self.0.ptr.as_ptr(),
-                        0,
-                        CoseType::EDDSA as i32,
+                        self.asserts.ptr.as_ptr(),
+                        idx,
+                        CoseType::RS256 as i32,
                         pk.as_ptr().cast(),
                     ))?;
                 }
@@ -278,8 +455,8 @@ impl AssertVerifier {
 
                         unsafe {
                             check(ffi::fido_assert_verify(
-                                self.0.ptr.as_ptr(),
-                                0,
+                                self.asserts.ptr.as_ptr(),
+                                idx,
                                 CoseType::ES256 as i32,
                                 pk.as_ptr().cast(),
                             ))?;
@@ -290,8 +467,8 @@ impl AssertVerifier {
 
                         unsafe {
                             check(ffi::fido_assert_verify(
-                                self.0.ptr.as_ptr(),
-                                0,
+                                self.asserts.ptr.as_ptr(),
+                                idx,
                                 CoseType::ES384 as i32,
                                 pk.as_ptr().cast(),
                             ))?;
@@ -309,6 +486,66 @@ impl AssertVerifier {
 
         Ok(())
     }
+
+    /// Verify every response of `responses`, each against its own client data, with the
+    /// credential of its credential ID in `credentials`.
+    ///
+    /// Return one result per response, in order. A response whose credential ID is not in
+    /// `credentials`, or whose user handle is not the user ID of the credential, fails with
+    /// [Error::Verification](crate::error::Error::Verification).
+    ///
+    /// See [AssertVerifier::from_response] for `rp_id` and `uv`.
+    pub fn verify_batch(
+        rp_id: &str,
+        uv: Opt,
+        responses: &[AssertionResponse<'_>],
+        credentials: &HashMap<Vec<u8>, CredentialKey>,
+    ) -> Vec<Result<()>> {
+        responses
+            .iter()
+            .map(|response| {
+                let credential = credentials
+                    .get(response.id)
+                    .ok_or(Error::Verification("unknown credential ID"))?;
+
+                let mut verifier = AssertVerifier::from_response(
+                    rp_id,
+                    response.id,
+                    response.auth_data,
+                    response.client_data_json,
+                    response.signature,
+                    response.user_handle,
+                    uv,
+                )?;
+                verifier.set_user_id(&credential.user_id);
+                verifier.verify(credential.public_key.clone())
+            })
+            .collect()
+    }
+}
+
+/// A registered credential, for [AssertVerifier::verify_batch].
+#[derive(Clone, Debug)]
+pub struct CredentialKey {
+    /// Public key of the credential.
+    pub public_key: PKey<Public>,
+    /// User ID of the user account the credential was created for.
+    pub user_id: Vec<u8>,
+}
+
+/// A WebAuthn `AuthenticatorAssertionResponse`, for [AssertVerifier::verify_batch].
+#[derive(Clone, Copy, Debug)]
+pub struct AssertionResponse<'a> {
+    /// Client data JSON.
+    pub client_data_json: &'a [u8],
+    /// Raw authenticator data.
+    pub auth_data: &'a [u8],
+    /// Signature.
+    pub signature: &'a [u8],
+    /// Credential ID, the `rawId` of the `PublicKeyCredential`.
+    pub id: &'a [u8],
+    /// User handle, if any.
+    pub user_handle: Option<&'a [u8]>,
 }
 
 impl Drop for Assertions {
@@ -357,7 +594,12 @@ impl Assertion<'_> {
         let len = unsafe { ffi::fido_assert_authdata_len(self.ptr.as_ptr(), self.idx) };
         let ptr = unsafe { ffi::fido_assert_authdata_ptr(self.ptr.as_ptr(), self.idx) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
+    }
+
+    /// Parse the authenticator data, see [AuthenticatorData].
+    pub fn authenticator_data(&self) -> Result<AuthenticatorData> {
+        AuthenticatorData::from_cbor(self.auth_data())
     }
 
     /// Return client data hash.
@@ -365,7 +607,7 @@ impl Assertion<'_> {
         let len = unsafe { ffi::fido_assert_clientdata_hash_len(self.ptr.as_ptr()) };
         let ptr = unsafe { ffi::fido_assert_clientdata_hash_ptr(self.ptr.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return the credBlob attribute.
@@ -373,7 +615,7 @@ impl Assertion<'_> {
         let len = unsafe { ffi::fido_assert_blob_len(self.ptr.as_ptr(), self.idx) };
         let ptr = unsafe { ffi::fido_assert_blob_ptr(self.ptr.as_ptr(), self.idx) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return the hmac-secret attribute.
@@ -385,7 +627,7 @@ impl Assertion<'_> {
         let len = unsafe { ffi::fido_assert_hmac_secret_len(self.ptr.as_ptr(), self.idx) };
         let ptr = unsafe { ffi::fido_assert_hmac_secret_ptr(self.ptr.as_ptr(), self.idx) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return largeBlobKey attribute.
@@ -393,7 +635,7 @@ impl Assertion<'_> {
         let len = unsafe { ffi::fido_assert_largeblob_key_len(self.ptr.as_ptr(), self.idx) };
         let ptr = unsafe { ffi::fido_assert_largeblob_key_ptr(self.ptr.as_ptr(), self.idx) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return user ID.
@@ -401,7 +643,7 @@ impl Assertion<'_> {
         let len = unsafe { ffi::fido_assert_user_id_len(self.ptr.as_ptr(), self.idx) };
         let ptr = unsafe { ffi::fido_assert_user_id_ptr(self.ptr.as_ptr(), self.idx) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return signature
@@ -409,7 +651,7 @@ impl Assertion<'_> {
         let len = unsafe { ffi::fido_assert_sig_len(self.ptr.as_ptr(), self.idx) };
         let ptr = unsafe { ffi::fido_assert_sig_ptr(self.ptr.as_ptr(), self.idx) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return credential ID
@@ -417,7 +659,7 @@ impl Assertion<'_> {
         let len = unsafe { ffi::fido_assert_id_len(self.ptr.as_ptr(), self.idx) };
         let ptr = unsafe { ffi::fido_assert_id_ptr(self.ptr.as_ptr(), self.idx) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return signature count.
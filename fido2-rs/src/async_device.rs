@@ -0,0 +1,230 @@
+//! Async wrapper over [Device].
+//!
+//! Operations that wait for the user, like [Device::make_credential] or [Device::get_assertion],
+//! block until the authenticator is touched. [AsyncDevice] runs them on a dedicated thread and
+//! returns futures instead, so they can be awaited without blocking an executor.
+//!
+//! Dropping a pending [DeviceFuture] cancels the request with [DeviceCancel::cancel].
+//!
+//! # Example
+//! ```rust,no_run
+//! use fido2_rs::async_device::AsyncDevice;
+//! use fido2_rs::credentials::{CoseType, Credential};
+//! use fido2_rs::device::Device;
+//!
+//! async fn register() -> anyhow::Result<()> {
+//!     let dev = AsyncDevice::new(Device::open("/dev/hidraw0")?);
+//!
+//!     let mut cred = Credential::new();
+//!     cred.set_client_data(&[1, 2, 3, 4, 5, 6])?;
+//!     cred.set_rp("fido_rs", "fido example")?;
+//!     cred.set_user(&[1, 2, 3, 4, 5, 6], "alice", Some("alice"), None)?;
+//!     cred.set_cose_type(CoseType::ES256)?;
+//!
+//!     // the executor is free while waiting for a touch, and dropping the future
+//!     // (e.g. on a timeout) cancels the request
+//!     let cred = dev.make_credential(cred, None).await?;
+//!
+//!     dbg!(cred.id());
+//!     Ok(())
+//! }
+//! ```
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, mpsc};
+use std::task::{Context, Poll};
+
+use tokio::sync::oneshot;
+use zeroize::Zeroizing;
+
+use crate::assertion::{AssertRequest, Assertions};
+use crate::credentials::Credential;
+use crate::device::{Device, DeviceCancel};
+use crate::error::{FidoError, Result};
+
+type Job = Box<dyn FnOnce(&Device) + Send>;
+
+/// State of a request, only changed under its lock.
+///
+/// The device thread cannot finish a request while its dropped future holds the lock, so the
+/// future cancels its own request, never the next one.
+type JobState = Arc<Mutex<u8>>;
+
+const QUEUED: u8 = 0;
+const RUNNING: u8 = 1;
+const DONE: u8 = 2;
+const DROPPED: u8 = 3;
+
+/// A [Device] driven from a dedicated thread.
+///
+/// The device is moved to the thread, which runs requests one at a time in submission order.
+/// The thread exits when the [AsyncDevice] is dropped.
+pub struct AsyncDevice {
+    jobs: mpsc::Sender<Job>,
+    cancel: DeviceCancel,
+}
+
+impl AsyncDevice {
+    /// Move `device` to a new thread.
+    pub fn new(device: Device) -> AsyncDevice {
+        let cancel = device.cancel_handle();
+        let (jobs, rx) = mpsc::channel::<Job>();
+
+        std::thread::Builder::new()
+            .name("fido2-device".to_string())
+            .spawn(move || {
+                for job in rx {
+                    job(&device);
+                }
+            })
+            .expect("spawn device thread");
+
+        AsyncDevice { jobs, cancel }
+    }
+
+    /// Get a handle of this device for cancel.
+    pub fn cancel_handle(&self) -> DeviceCancel {
+        self.cancel.clone()
+    }
+
+    /// Run `f` with the device on the device thread.
+    ///
+    /// If the returned future is dropped before `f` starts, `f` is skipped.
+    pub fn run<F, T>(&self, f: F) -> DeviceFuture<T>
+    where
+        F: FnOnce(&Device) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let state = JobState::new(Mutex::new(QUEUED));
+
+        let job_state = state.clone();
+        let job: Job = Box::new(move |device| {
+            {
+                let mut state = lock(&job_state);
+                if *state != QUEUED {
+                    return;
+                }
+                *state = RUNNING;
+            }
+
+            let res = f(device);
+            *lock(&job_state) = DONE;
+            let _ = tx.send(res);
+        });
+
+        // the thread only exits once `jobs` is dropped, and a dropped `tx` is reported by `rx`
+        let _ = self.jobs.send(job);
+
+        DeviceFuture {
+            rx,
+            state,
+            cancel: self.cancel.clone(),
+        }
+    }
+
+    /// Generates a new credential on a FIDO2 device, see [Device::make_credential].
+    ///
+    /// The credential is returned once it is filled with the device response.
+    pub fn make_credential(
+        &self,
+        mut credential: Credential,
+        pin: Option<&str>,
+    ) -> DeviceFuture<Credential> {
+        let pin = pin.map(|it| Zeroizing::new(it.to_string()));
+
+        self.run(move |dev| {
+            dev.make_credential(&mut credential, pin.as_deref().map(String::as_str))?;
+            Ok(credential)
+        })
+    }
+
+    /// Obtains an assertion from a FIDO2 device, see [Device::get_assertion].
+    pub fn get_assertion(
+        &self,
+        request: AssertRequest,
+        pin: Option<&str>,
+    ) -> DeviceFuture<Assertions> {
+        let pin = pin.map(|it| Zeroizing::new(it.to_string()));
+
+        self.run(move |dev| dev.get_assertion(request, pin.as_deref().map(String::as_str)))
+    }
+
+    /// Perform a factory reset of the device, see [Device::reset].
+    pub fn reset(&self) -> DeviceFuture<()> {
+        self.run(|dev| dev.reset())
+    }
+
+    /// Read a largeBlob entry from the device, see [Device::largeblob_get].
+    pub fn largeblob_get(&self, key: &[u8]) -> DeviceFuture<Vec<u8>> {
+        let key = Zeroizing::new(key.to_vec());
+
+        self.run(move |dev| dev.largeblob_get(&key))
+    }
+
+    /// Store data as a largeBlob entry on the device, see [Device::largeblob_set].
+    pub fn largeblob_set(&self, key: &[u8], data: &[u8], pin: &str) -> DeviceFuture<()> {
+        let key = Zeroizing::new(key.to_vec());
+        let data = data.to_vec();
+        let pin = Zeroizing::new(pin.to_string());
+
+        self.run(move |dev| dev.largeblob_set(&key, &data, &pin))
+    }
+
+    /// Remove a largeBlob entry from the device, see [Device::largeblob_remove].
+    pub fn largeblob_remove(&self, key: &[u8], pin: &str) -> DeviceFuture<()> {
+        let key = Zeroizing::new(key.to_vec());
+        let pin = Zeroizing::new(pin.to_string());
+
+        self.run(move |dev| dev.largeblob_remove(&key, &pin))
+    }
+
+    /// Read the raw serialized largeBlob CBOR array from the device, see [Device::largeblob_get_array].
+    pub fn largeblob_get_array(&self) -> DeviceFuture<Vec<u8>> {
+        self.run(|dev| dev.largeblob_get_array())
+    }
+
+    /// Replace the entire largeBlob CBOR array on the device, see [Device::largeblob_set_array].
+    pub fn largeblob_set_array(&self, data: &[u8], pin: &str) -> DeviceFuture<()> {
+        let data = data.to_vec();
+        let pin = Zeroizing::new(pin.to_string());
+
+        self.run(move |dev| dev.largeblob_set_array(&data, &pin))
+    }
+}
+
+/// A pending request on an [AsyncDevice].
+///
+/// Dropping it before completion cancels the request on the device.
+#[must_use = "dropping a DeviceFuture cancels the request"]
+pub struct DeviceFuture<T> {
+    rx: oneshot::Receiver<Result<T>>,
+    state: JobState,
+    cancel: DeviceCancel,
+}
+
+impl<T> Future for DeviceFuture<T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx).poll(cx).map(|res| {
+            // the device thread is gone, e.g. it panicked
+            res.unwrap_or_else(|_| Err(FidoError::new(ffi::FIDO_ERR_INTERNAL).into()))
+        })
+    }
+}
+
+impl<T> Drop for DeviceFuture<T> {
+    fn drop(&mut self) {
+        // a queued request is skipped, only a running one needs to be cancelled on the device
+        let mut state = lock(&self.state);
+        if *state == RUNNING {
+            self.cancel.cancel();
+        }
+        *state = DROPPED;
+    }
+}
+
+fn lock(state: &Mutex<u8>) -> MutexGuard<'_, u8> {
+    state.lock().unwrap_or_else(|it| it.into_inner())
+}
@@ -0,0 +1,474 @@
+//! Attestation trust-chain verification.
+//!
+//! [CredentialRef::verify] checks the attestation signature against the attestation certificate,
+//! but not the certificate itself. [AttestationVerifier] additionally validates the certificate
+//! chain up to a set of trusted roots, and the requirements on packed attestation certificates.
+//!
+//! # Example
+//! ```rust,no_run
+//! use fido2_rs::attestation::{AttestationType, AttestationVerifier};
+//! use fido2_rs::credentials::Credential;
+//! use openssl::x509::X509;
+//! use openssl::x509::store::X509StoreBuilder;
+//!
+//! fn enroll(cred: &Credential, vendor_root: &[u8]) -> anyhow::Result<()> {
+//!     let mut store = X509StoreBuilder::new()?;
+//!     store.add_cert(X509::from_pem(vendor_root)?)?;
+//!
+//!     let verifier = AttestationVerifier::new(store.build());
+//!     match verifier.verify(cred)? {
+//!         AttestationType::Basic | AttestationType::AttCA => Ok(()),
+//!         _ => anyhow::bail!("authenticator is not attested by an approved vendor"),
+//!     }
+//! }
+//! ```
+use foreign_types::{ForeignType, ForeignTypeRef};
+use openssl::asn1::{Asn1Object, Asn1OctetStringRef};
+use openssl::nid::Nid;
+use openssl::stack::Stack;
+use openssl::x509::store::X509Store;
+use openssl::x509::{X509, X509Ref, X509StoreContext};
+
+use crate::credentials::{AttestationFormat, CredentialRef};
+use crate::error::{Error, Result};
+
+/// OID of the FIDO AAGUID certificate extension, `id-fido-gen-ce-aaguid`.
+const OID_FIDO_GEN_CE_AAGUID: &str = "1.3.6.1.4.1.45724.1.1.4";
+
+/// Attestation type of a verified credential.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AttestationType {
+    /// Signed by an attestation key shared by a batch of authenticators, and the certificate
+    /// chains to a trusted root.
+    Basic,
+    /// Signed by a key certified by an Attestation CA, as used by TPMs, and the certificate
+    /// chains to a trusted root.
+    AttCA,
+    /// Signed by the credential private key itself, nothing is known about the authenticator.
+    SelfAttestation,
+    /// No attestation statement.
+    None,
+}
+
+/// Verify attestation statements against a store of trusted root certificates.
+pub struct AttestationVerifier {
+    store: X509Store,
+}
+
+impl AttestationVerifier {
+    /// Return a [AttestationVerifier] trusting the root certificates in `store`.
+    pub fn new(store: X509Store) -> AttestationVerifier {
+        AttestationVerifier { store }
+    }
+
+    /// Verify the attestation statement of `cred`.
+    ///
+    /// The attestation signature is verified with [CredentialRef::verify], or
+    /// [CredentialRef::verify_self] if the statement has no certificate. A certificate must chain
+    /// to a root of the store, using the other certificates of the statement as intermediates.
+    ///
+    /// For the packed format, the certificate must also meet the requirements of
+    /// [WebAuthn §8.2.1](https://www.w3.org/TR/webauthn-3/#sctn-packed-attestation-cert-requirements),
+    /// and its AAGUID extension, if present, must match [CredentialRef::attestation_guid].
+    ///
+    /// An untrusted chain or certificate is reported as [Error::Verification].
+    ///
+    /// Basic and AttCA attestation cannot be told apart from the statement, `tpm` statements are
+    /// reported as [AttestationType::AttCA] and other formats as [AttestationType::Basic].
+    pub fn verify(&self, cred: &CredentialRef) -> Result<AttestationType> {
+        let format = cred.attestation_format();
+        if matches!(format, None | Some(AttestationFormat::None)) {
+            return Ok(AttestationType::None);
+        }
+
+        let certificates = cred.certificates();
+        let Some((leaf, intermediates)) = certificates.split_first() else {
+            cred.verify_self()?;
+
+            return Ok(AttestationType::SelfAttestation);
+        };
+
+        cred.verify()?;
+
+        let leaf = X509::from_der(leaf)?;
+        let mut chain = Stack::new()?;
+        for cert in intermediates {
+            chain.push(X509::from_der(cert)?)?;
+        }
+
+        let mut ctx = X509StoreContext::new()?;
+        if !ctx.init(&self.store, &leaf, &chain, |ctx| ctx.verify_cert())? {
+            return Err(Error::Verification("attestation certificate chain"));
+        }
+
+        if format == Some(AttestationFormat::Packed) {
+            check_packed_certificate(&leaf, cred.attestation_guid())?;
+        }
+
+        if format == Some(AttestationFormat::Tpm) {
+            Ok(AttestationType::AttCA)
+        } else {
+            Ok(AttestationType::Basic)
+        }
+    }
+}
+
+/// Check the requirements on a packed attestation certificate.
+fn check_packed_certificate(cert: &X509Ref, aaguid: &[u8]) -> Result<()> {
+    if cert.version() != 2 {
+        return Err(Error::Verification("attestation certificate version"));
+    }
+
+    let subject = cert.subject_name();
+    let entry = |nid| {
+        subject
+            .entries_by_nid(nid)
+            .next()
+            .and_then(|it| std::str::from_utf8(it.data().as_slice()).ok())
+    };
+
+    if entry(Nid::COUNTRYNAME).is_none()
+        || entry(Nid::ORGANIZATIONNAME).is_none()
+        || entry(Nid::COMMONNAME).is_none()
+        || entry(Nid::ORGANIZATIONALUNITNAME) != Some("Authenticator Attestation")
+    {
+        return Err(Error::Verification("attestation certificate subject"));
+    }
+
+    // the extensions as parsed by openssl, EXFLAG_CA is set by BasicConstraints with cA true
+    let flags = unsafe { openssl_sys::X509_get_extension_flags(cert.as_ptr()) };
+    if flags & openssl_sys::EXFLAG_INVALID != 0 {
+        return Err(Error::Verification("attestation certificate extensions"));
+    }
+    if flags & openssl_sys::EXFLAG_CA != 0 {
+        return Err(Error::Verification("attestation certificate is a CA"));
+    }
+
+    if let Some((critical, data)) = extension(cert, OID_FIDO_GEN_CE_AAGUID)? {
+        // the extension value is an OCTET STRING of the 16 bytes AAGUID
+        match data {
+            [0x04, 0x10, cert_aaguid @ ..] if !critical && cert_aaguid == aaguid => {}
+            _ => return Err(Error::Verification("attestation certificate AAGUID")),
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the criticality and value of the extension `oid` of `cert`.
+fn extension<'a>(cert: &'a X509Ref, oid: &str) -> Result<Option<(bool, &'a [u8])>> {
+    let oid = Asn1Object::from_str(oid)?;
+
+    unsafe {
+        let idx = openssl_sys::X509_get_ext_by_OBJ(cert.as_ptr(), oid.as_ptr(), -1);
+        if idx < 0 {
+            return Ok(None);
+        }
+
+        let ext = openssl_sys::X509_get_ext(cert.as_ptr(), idx);
+        let critical = openssl_sys::X509_EXTENSION_get_critical(ext) != 0;
+        let data = Asn1OctetStringRef::from_ptr(openssl_sys::X509_EXTENSION_get_data(ext));
+
+        Ok(Some((critical, data.as_slice())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ciborium::Value;
+    use openssl::asn1::{Asn1Integer, Asn1OctetString, Asn1Time};
+    use openssl::bn::BigNum;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::sha::sha256;
+    use openssl::sign::Signer;
+    use openssl::x509::extension::BasicConstraints;
+    use openssl::x509::store::X509StoreBuilder;
+    use openssl::x509::{X509Builder, X509Extension, X509Name};
+
+    use crate::cose::CoseKey;
+    use crate::credentials::Credential;
+
+    const RP_ID: &str = "example.com";
+    const CLIENT_DATA_HASH: [u8; 32] = [0x44; 32];
+    const AAGUID: [u8; 16] = [0x22; 16];
+    const CREDENTIAL_ID: [u8; 16] = [0x33; 16];
+    const ATTESTATION_OU: &str = "Authenticator Attestation";
+
+    fn key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn name(common_name: &str, unit: &str) -> X509Name {
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_nid(Nid::COUNTRYNAME, "SE").unwrap();
+        name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "fido2-rs")
+            .unwrap();
+        name.append_entry_by_nid(Nid::ORGANIZATIONALUNITNAME, unit)
+            .unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, common_name)
+            .unwrap();
+
+        name.build()
+    }
+
+    /// Return a certificate of `key` for `subject`, signed by `issuer` or self-signed.
+    fn certificate(
+        subject: &X509Name,
+        key: &PKey<Private>,
+        issuer: Option<(&X509, &PKey<Private>)>,
+        extensions: Vec<X509Extension>,
+    ) -> X509 {
+        let mut cert = X509Builder::new().unwrap();
+        cert.set_version(2).unwrap();
+        let serial = Asn1Integer::from_bn(&BigNum::from_u32(1).unwrap()).unwrap();
+        cert.set_serial_number(&serial).unwrap();
+        cert.set_subject_name(subject).unwrap();
+        cert.set_pubkey(key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(365).unwrap())
+            .unwrap();
+        for extension in extensions {
+            cert.append_extension(extension).unwrap();
+        }
+
+        let (issuer_name, issuer_key) = match issuer {
+            Some((cert, key)) => (cert.subject_name(), key),
+            None => (subject.as_ref(), key),
+        };
+        cert.set_issuer_name(issuer_name).unwrap();
+        cert.sign(issuer_key, MessageDigest::sha256()).unwrap();
+
+        cert.build()
+    }
+
+    fn ca() -> X509Extension {
+        BasicConstraints::new().critical().ca().build().unwrap()
+    }
+
+    fn aaguid_extension(aaguid: &[u8; 16], critical: bool) -> X509Extension {
+        let oid = Asn1Object::from_str(OID_FIDO_GEN_CE_AAGUID).unwrap();
+        let mut value = vec![0x04, 0x10];
+        value.extend_from_slice(aaguid);
+        let value = Asn1OctetString::new_from_bytes(&value).unwrap();
+
+        X509Extension::new_from_der(&oid, critical, &value).unwrap()
+    }
+
+    /// A root CA and the store trusting it.
+    struct Root {
+        cert: X509,
+        key: PKey<Private>,
+    }
+
+    impl Root {
+        fn new() -> Root {
+            let key = key();
+            let cert = certificate(&name("root", "CA"), &key, None, vec![ca()]);
+
+            Root { cert, key }
+        }
+
+        fn store(&self) -> X509Store {
+            let mut store = X509StoreBuilder::new().unwrap();
+            store.add_cert(self.cert.clone()).unwrap();
+
+            store.build()
+        }
+
+        /// Return an attestation key and a leaf certificate with `extensions`, signed by this root.
+        fn leaf(&self, unit: &str, extensions: Vec<X509Extension>) -> (X509, PKey<Private>) {
+            let key = key();
+            let cert = certificate(
+                &name("attestation", unit),
+                &key,
+                Some((&self.cert, &self.key)),
+                extensions,
+            );
+
+            (cert, key)
+        }
+    }
+
+    fn auth_data(credential_key: &PKey<Private>) -> Vec<u8> {
+        let public =
+            PKey::public_key_from_der(&credential_key.public_key_to_der().unwrap()).unwrap();
+
+        let mut data = sha256(RP_ID.as_bytes()).to_vec();
+        data.push(0x41); // UP | AT
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&AAGUID);
+        data.extend_from_slice(&(CREDENTIAL_ID.len() as u16).to_be_bytes());
+        data.extend_from_slice(&CREDENTIAL_ID);
+        data.extend_from_slice(&CoseKey::from_pkey(&public).unwrap().to_cbor());
+
+        data
+    }
+
+    /// Return a credential with a packed attestation statement signed by `key`, with the
+    /// certificates `x5c`, or self attestation if there is none.
+    fn packed(key: &PKey<Private>, x5c: &[&X509]) -> Credential {
+        let credential_key = if x5c.is_empty() {
+            key.clone()
+        } else {
+            self::key()
+        };
+        let auth_data = auth_data(&credential_key);
+
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(&auth_data).unwrap();
+        signer.update(&CLIENT_DATA_HASH).unwrap();
+        let sig = signer.sign_to_vec().unwrap();
+
+        let mut att_stmt = vec![
+            (Value::from("alg"), Value::from(-7)),
+            (Value::from("sig"), Value::from(sig)),
+        ];
+        if !x5c.is_empty() {
+            let x5c = x5c.iter().map(|it| Value::from(it.to_der().unwrap()));
+            att_stmt.push((Value::from("x5c"), Value::Array(x5c.collect())));
+        }
+
+        credential("packed", Value::Map(att_stmt), auth_data)
+    }
+
+    fn credential(fmt: &str, att_stmt: Value, auth_data: Vec<u8>) -> Credential {
+        let object = Value::Map(vec![
+            (Value::from("fmt"), Value::from(fmt)),
+            (Value::from("attStmt"), att_stmt),
+            (Value::from("authData"), Value::from(auth_data)),
+        ]);
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&object, &mut data).unwrap();
+
+        let mut cred = Credential::from_attestation_object(&data).unwrap();
+        cred.set_rp(RP_ID, "example").unwrap();
+        cred.set_client_data_hash(CLIENT_DATA_HASH).unwrap();
+
+        cred
+    }
+
+    #[test]
+    fn basic() {
+        let root = Root::new();
+        let verifier = AttestationVerifier::new(root.store());
+
+        let (leaf, key) = root.leaf(ATTESTATION_OU, vec![]);
+        let cred = packed(&key, &[&leaf]);
+        assert_eq!(verifier.verify(&cred).unwrap(), AttestationType::Basic);
+
+        let (leaf, key) = root.leaf(ATTESTATION_OU, vec![aaguid_extension(&AAGUID, false)]);
+        let cred = packed(&key, &[&leaf]);
+        assert_eq!(verifier.verify(&cred).unwrap(), AttestationType::Basic);
+    }
+
+    #[test]
+    fn intermediate() {
+        let root = Root::new();
+        let verifier = AttestationVerifier::new(root.store());
+
+        let intermediate_key = key();
+        let intermediate = certificate(
+            &name("intermediate", "CA"),
+            &intermediate_key,
+            Some((&root.cert, &root.key)),
+            vec![ca()],
+        );
+        let intermediate = Root {
+            cert: intermediate,
+            key: intermediate_key,
+        };
+        let (leaf, key) = intermediate.leaf(ATTESTATION_OU, vec![]);
+        let cred = packed(&key, &[&leaf]);
+
+        // the intermediate is neither in the statement nor in the store
+        assert!(matches!(
+            verifier.verify(&cred),
+            Err(Error::Verification("attestation certificate chain"))
+        ));
+
+        let mut store = X509StoreBuilder::new().unwrap();
+        store.add_cert(root.cert.clone()).unwrap();
+        store.add_cert(intermediate.cert.clone()).unwrap();
+        let verifier = AttestationVerifier::new(store.build());
+        assert_eq!(verifier.verify(&cred).unwrap(), AttestationType::Basic);
+    }
+
+    #[test]
+    fn untrusted_root() {
+        let root = Root::new();
+        let verifier = AttestationVerifier::new(Root::new().store());
+
+        let (leaf, key) = root.leaf(ATTESTATION_OU, vec![]);
+        let cred = packed(&key, &[&leaf]);
+        assert!(matches!(
+            verifier.verify(&cred),
+            Err(Error::Verification("attestation certificate chain"))
+        ));
+    }
+
+    #[test]
+    fn signature() {
+        let root = Root::new();
+        let verifier = AttestationVerifier::new(root.store());
+
+        let (leaf, _) = root.leaf(ATTESTATION_OU, vec![]);
+        let cred = packed(&key(), &[&leaf]);
+        assert!(verifier.verify(&cred).is_err());
+    }
+
+    #[test]
+    fn packed_requirements() {
+        let root = Root::new();
+        let verifier = AttestationVerifier::new(root.store());
+
+        let (leaf, key) = root.leaf("Engineering", vec![]);
+        assert!(matches!(
+            verifier.verify(&packed(&key, &[&leaf])),
+            Err(Error::Verification("attestation certificate subject"))
+        ));
+
+        let (leaf, key) = root.leaf(ATTESTATION_OU, vec![ca()]);
+        assert!(matches!(
+            verifier.verify(&packed(&key, &[&leaf])),
+            Err(Error::Verification("attestation certificate is a CA"))
+        ));
+
+        let (leaf, key) = root.leaf(ATTESTATION_OU, vec![aaguid_extension(&[0x11; 16], false)]);
+        assert!(matches!(
+            verifier.verify(&packed(&key, &[&leaf])),
+            Err(Error::Verification("attestation certificate AAGUID"))
+        ));
+
+        // openssl already rejects the unknown critical extension in the chain
+        let (leaf, key) = root.leaf(ATTESTATION_OU, vec![aaguid_extension(&AAGUID, true)]);
+        assert!(matches!(
+            verifier.verify(&packed(&key, &[&leaf])),
+            Err(Error::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn self_attestation() {
+        let verifier = AttestationVerifier::new(Root::new().store());
+
+        let cred = packed(&key(), &[]);
+        assert_eq!(
+            verifier.verify(&cred).unwrap(),
+            AttestationType::SelfAttestation
+        );
+    }
+
+    #[test]
+    fn none() {
+        let verifier = AttestationVerifier::new(Root::new().store());
+
+        let cred = credential("none", Value::Map(vec![]), auth_data(&key()));
+        assert_eq!(verifier.verify(&cred).unwrap(), AttestationType::None);
+    }
+}
@@ -0,0 +1,329 @@
+//! Authenticator data parsing.
+//!
+//! [AuthenticatorData] is the structure signed by the authenticator in both registration and
+//! assertion, see [WebAuthn §6.1](https://www.w3.org/TR/webauthn-3/#sctn-authenticator-data).
+use bitflags::bitflags;
+use ciborium::Value;
+
+use crate::cose::CoseKey;
+use crate::error::{Error, Result};
+use crate::info::Aaguid;
+
+const MALFORMED: Error = Error::Malformed("authenticator data");
+
+bitflags! {
+    /// Flags of the authenticator data
+    #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+    pub struct AuthDataFlags: u8 {
+        /// User Present
+        const UP = 0x01;
+        /// User Verified
+        const UV = 0x04;
+        /// Backup Eligibility
+        const BE = 0x08;
+        /// Backup State
+        const BS = 0x10;
+        /// Attested credential data included
+        const AT = 0x40;
+        /// Extension data included
+        const ED = 0x80;
+    }
+}
+
+/// Attested credential data, present in the authenticator data of a new credential.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttestedCredentialData {
+    /// AAGUID of the authenticator.
+    pub aaguid: Aaguid,
+    /// Credential ID.
+    pub credential_id: Vec<u8>,
+    /// CBOR-encoded COSE_Key of the credential.
+    pub public_key: Vec<u8>,
+}
+
+impl AttestedCredentialData {
+    /// Parse the public key of the credential.
+    pub fn cose_key(&self) -> Result<CoseKey> {
+        CoseKey::from_cbor(&self.public_key)
+    }
+}
+
+/// Parsed authenticator data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthenticatorData {
+    /// SHA-256 hash of the RP ID the credential is scoped to.
+    pub rp_id_hash: [u8; 32],
+    /// Flags.
+    pub flags: AuthDataFlags,
+    /// Signature counter.
+    pub sign_count: u32,
+    /// Attested credential data, if [AuthDataFlags::AT] is set.
+    pub attested_credential: Option<AttestedCredentialData>,
+    /// CBOR map of extension outputs, if [AuthDataFlags::ED] is set.
+    pub extensions: Option<Value>,
+}
+
+impl AuthenticatorData {
+    /// Parse authenticator data wrapped in a CBOR byte string.
+    ///
+    /// This is the form returned by [CredentialRef::auth_data](crate::credentials::CredentialRef::auth_data)
+    /// and [Assertion::auth_data](crate::assertion::Assertion::auth_data).
+    pub fn from_cbor(data: &[u8]) -> Result<AuthenticatorData> {
+        let raw = unwrap_cbor(data).ok_or(MALFORMED)?;
+
+        AuthenticatorData::from_raw(&raw)
+    }
+
+    /// Parse raw authenticator data.
+    ///
+    /// This is the form returned by [CredentialRef::auth_data_raw](crate::credentials::CredentialRef::auth_data_raw)
+    /// and found in WebAuthn responses.
+    pub fn from_raw(data: &[u8]) -> Result<AuthenticatorData> {
+        if data.len() < 37 {
+            return Err(MALFORMED);
+        }
+
+        let rp_id_hash = data[..32].try_into().unwrap();
+        let flags = AuthDataFlags::from_bits_retain(data[32]);
+        let sign_count = u32::from_be_bytes(data[33..37].try_into().unwrap());
+        let mut rest = &data[37..];
+
+        let attested_credential = if flags.contains(AuthDataFlags::AT) {
+            if rest.len() < 18 {
+                return Err(MALFORMED);
+            }
+
+            let aaguid = Aaguid::try_from(&rest[..16])?;
+            let id_len = u16::from_be_bytes([rest[16], rest[17]]) as usize;
+            rest = &rest[18..];
+            if rest.len() < id_len {
+                return Err(MALFORMED);
+            }
+            let (credential_id, key) = rest.split_at(id_len);
+
+            // the COSE key is followed by the optional extensions, decode it to find its end
+            rest = key;
+            let _: Value = ciborium::de::from_reader(&mut rest).map_err(|_| MALFORMED)?;
+            let public_key = &key[..key.len() - rest.len()];
+
+            Some(AttestedCredentialData {
+                aaguid,
+                credential_id: credential_id.to_vec(),
+                public_key: public_key.to_vec(),
+            })
+        } else {
+            None
+        };
+
+        let extensions = if flags.contains(AuthDataFlags::ED) {
+            let extensions: Value = ciborium::de::from_reader(&mut rest).map_err(|_| MALFORMED)?;
+            if !extensions.is_map() {
+                return Err(MALFORMED);
+            }
+
+            Some(extensions)
+        } else {
+            None
+        };
+
+        if !rest.is_empty() {
+            return Err(MALFORMED);
+        }
+
+        Ok(AuthenticatorData {
+            rp_id_hash,
+            flags,
+            sign_count,
+            attested_credential,
+            extensions,
+        })
+    }
+
+    /// Return the output of the extension `name`, e.g. `credProtect`.
+    pub fn extension(&self, name: &str) -> Option<&Value> {
+        self.extensions
+            .as_ref()?
+            .as_map()?
+            .iter()
+            .find(|(k, _)| k.as_text() == Some(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Return true if the user was present.
+    pub fn user_present(&self) -> bool {
+        self.flags.contains(AuthDataFlags::UP)
+    }
+
+    /// Return true if the user was verified.
+    pub fn user_verified(&self) -> bool {
+        self.flags.contains(AuthDataFlags::UV)
+    }
+}
+
+/// Return the content of `data` if it is exactly one CBOR byte string.
+fn unwrap_cbor(data: &[u8]) -> Option<Vec<u8>> {
+    let mut rest = data;
+    match ciborium::de::from_reader(&mut rest) {
+        Ok(Value::Bytes(raw)) if rest.is_empty() => Some(raw),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RP_ID_HASH: [u8; 32] = [0x11; 32];
+    const AAGUID: [u8; 16] = [0x22; 16];
+    const CREDENTIAL_ID: [u8; 4] = [0xca, 0xfe, 0xba, 0xbe];
+
+    fn header(flags: AuthDataFlags, sign_count: u32) -> Vec<u8> {
+        let mut data = RP_ID_HASH.to_vec();
+        data.push(flags.bits());
+        data.extend_from_slice(&sign_count.to_be_bytes());
+
+        data
+    }
+
+    fn es256_key() -> Vec<u8> {
+        CoseKey::ES256 {
+            x: [0x33; 32],
+            y: [0x44; 32],
+        }
+        .to_cbor()
+    }
+
+    fn attested(flags: AuthDataFlags) -> Vec<u8> {
+        let mut data = header(flags | AuthDataFlags::AT, 7);
+        data.extend_from_slice(&AAGUID);
+        data.extend_from_slice(&(CREDENTIAL_ID.len() as u16).to_be_bytes());
+        data.extend_from_slice(&CREDENTIAL_ID);
+        data.extend_from_slice(&es256_key());
+
+        data
+    }
+
+    fn cbor(value: &Value) -> Vec<u8> {
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(value, &mut data).unwrap();
+
+        data
+    }
+
+    fn cred_protect() -> Value {
+        Value::Map(vec![(Value::from("credProtect"), Value::from(2))])
+    }
+
+    #[test]
+    fn flags_and_counter() {
+        let flags = AuthDataFlags::UP | AuthDataFlags::UV | AuthDataFlags::BE;
+        let parsed = AuthenticatorData::from_raw(&header(flags, 0x01020304)).unwrap();
+
+        assert_eq!(parsed.rp_id_hash, RP_ID_HASH);
+        assert_eq!(parsed.flags, flags);
+        assert_eq!(parsed.sign_count, 0x01020304);
+        assert!(parsed.user_present());
+        assert!(parsed.user_verified());
+        assert_eq!(parsed.attested_credential, None);
+        assert_eq!(parsed.extensions, None);
+    }
+
+    #[test]
+    fn unknown_flags_are_kept() {
+        let parsed =
+            AuthenticatorData::from_raw(&header(AuthDataFlags::from_bits_retain(0x22), 0)).unwrap();
+
+        assert_eq!(parsed.flags.bits(), 0x22);
+        assert!(!parsed.user_present());
+    }
+
+    #[test]
+    fn attested_credential() {
+        let parsed = AuthenticatorData::from_raw(&attested(AuthDataFlags::UP)).unwrap();
+        let credential = parsed.attested_credential.unwrap();
+
+        assert_eq!(credential.aaguid, Aaguid::try_from(&AAGUID[..]).unwrap());
+        assert_eq!(credential.credential_id, CREDENTIAL_ID);
+        assert_eq!(credential.public_key, es256_key());
+        assert_eq!(
+            credential.cose_key().unwrap(),
+            CoseKey::ES256 {
+                x: [0x33; 32],
+                y: [0x44; 32]
+            }
+        );
+    }
+
+    #[test]
+    fn extensions() {
+        let mut data = header(AuthDataFlags::UP | AuthDataFlags::ED, 1);
+        data.extend_from_slice(&cbor(&cred_protect()));
+        let parsed = AuthenticatorData::from_raw(&data).unwrap();
+
+        assert_eq!(parsed.extensions, Some(cred_protect()));
+        assert_eq!(parsed.extension("credProtect"), Some(&Value::from(2)));
+        assert_eq!(parsed.extension("hmac-secret"), None);
+    }
+
+    #[test]
+    fn attested_credential_and_extensions() {
+        let mut data = attested(AuthDataFlags::ED);
+        data.extend_from_slice(&cbor(&cred_protect()));
+        let parsed = AuthenticatorData::from_raw(&data).unwrap();
+
+        assert_eq!(
+            parsed.attested_credential.as_ref().unwrap().public_key,
+            es256_key()
+        );
+        assert_eq!(parsed.extension("credProtect"), Some(&Value::from(2)));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = attested(AuthDataFlags::UP);
+
+        // header, AAGUID and credential ID length, credential ID, COSE key
+        for len in [0, 36, 37 + 17, 37 + 18 + 3, data.len() - 1] {
+            assert!(
+                AuthenticatorData::from_raw(&data[..len]).is_err(),
+                "length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_extensions() {
+        let data = header(AuthDataFlags::ED, 0);
+
+        assert!(AuthenticatorData::from_raw(&data).is_err());
+    }
+
+    #[test]
+    fn extensions_not_a_map() {
+        let mut data = header(AuthDataFlags::ED, 0);
+        data.extend_from_slice(&cbor(&Value::from(1)));
+
+        assert!(AuthenticatorData::from_raw(&data).is_err());
+    }
+
+    #[test]
+    fn trailing_data() {
+        let mut data = header(AuthDataFlags::UP, 0);
+        data.push(0);
+
+        assert!(AuthenticatorData::from_raw(&data).is_err());
+    }
+
+    #[test]
+    fn cbor_wrapped() {
+        let raw = attested(AuthDataFlags::UP);
+        let wrapped = cbor(&Value::Bytes(raw.clone()));
+
+        assert_eq!(
+            AuthenticatorData::from_cbor(&wrapped).unwrap(),
+            AuthenticatorData::from_raw(&raw).unwrap()
+        );
+        assert!(AuthenticatorData::from_cbor(&raw).is_err());
+        assert!(AuthenticatorData::from_raw(&wrapped).is_err());
+    }
+}
@@ -0,0 +1,511 @@
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::ops::{Deref, Index};
+use std::ptr::NonNull;
+use std::time::Duration;
+
+use foreign_types::{ForeignType, ForeignTypeRef, Opaque};
+use zeroize::Zeroizing;
+
+use crate::device::Device;
+use crate::error::Result;
+use crate::utils::{check, slice_or_empty};
+
+/// FIDO2 biometric enrollment management.
+pub struct BioEnrollment<'a> {
+    dev: &'a Device,
+
+    info: BioInfo,
+
+    pin: Zeroizing<CString>,
+}
+
+impl<'a> BioEnrollment<'a> {
+    pub(crate) fn new(
+        device: &'a Device,
+        info: BioInfo,
+        pin: Zeroizing<CString>,
+    ) -> BioEnrollment<'a> {
+        BioEnrollment {
+            dev: device,
+            info,
+            pin,
+        }
+    }
+
+    /// Return the sensor information of the authenticator.
+    pub fn info(&self) -> &BioInfo {
+        &self.info
+    }
+
+    /// Get the fingerprint templates enrolled on the authenticator.
+    pub fn templates(&self) -> Result<TemplateArray> {
+        let pin_ptr = self.pin.as_ptr();
+
+        unsafe {
+            let array = TemplateArray {
+                ptr: NonNull::new_unchecked(ffi::fido_bio_template_array_new()),
+            };
+
+            self.dev
+                .check_timeout(ffi::fido_bio_dev_get_template_array(
+                    self.dev.ptr.as_ptr(),
+                    array.ptr.as_ptr(),
+                    pin_ptr,
+                ))?;
+
+            Ok(array)
+        }
+    }
+
+    /// Start a new fingerprint enrollment.
+    ///
+    /// The first sample is captured before this method returns, check [Enrollment::status] for
+    /// the result and call [Enrollment::capture] until [Enrollment::is_complete] returns true.
+    ///
+    /// `timeout` is how long the authenticator waits for each sample.
+    ///
+    /// **Please note that `fido_bio_dev_enroll_begin()` is synchronous and will block if necessary.**
+    pub fn enroll(&self, timeout: Duration) -> Result<Enrollment<'a>> {
+        let pin_ptr = self.pin.as_ptr();
+        let timeout_ms = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+
+        let template = Template::new();
+
+        unsafe {
+            let enroll = ffi::fido_bio_enroll_new();
+            let enrollment = Enrollment {
+                dev: self.dev,
+                template,
+                enroll: NonNull::new_unchecked(enroll),
+                timeout_ms,
+            };
+
+            self.dev.check_timeout(ffi::fido_bio_dev_enroll_begin(
+                self.dev.ptr.as_ptr(),
+                enrollment.template.as_ptr(),
+                enroll,
+                timeout_ms,
+                pin_ptr,
+            ))?;
+
+            Ok(enrollment)
+        }
+    }
+
+    /// Change the friendly name of the template identified by `id`.
+    pub fn rename(&self, id: &[u8], name: &str) -> Result<()> {
+        let pin_ptr = self.pin.as_ptr();
+
+        let mut template = Template::new();
+        template.set_id(id)?;
+        template.set_name(name)?;
+
+        unsafe {
+            self.dev.check_timeout(ffi::fido_bio_dev_set_template_name(
+                self.dev.ptr.as_ptr(),
+                template.as_ptr(),
+                pin_ptr,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the template identified by `id` from the authenticator.
+    pub fn remove(&self, id: &[u8]) -> Result<()> {
+        let pin_ptr = self.pin.as_ptr();
+
+        let mut template = Template::new();
+        template.set_id(id)?;
+
+        unsafe {
+            self.dev.check_timeout(ffi::fido_bio_dev_enroll_remove(
+                self.dev.ptr.as_ptr(),
+                template.as_ptr(),
+                pin_ptr,
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Biometric sensor information.
+pub struct BioInfo {
+    pub(crate) ptr: NonNull<ffi::fido_bio_info_t>,
+}
+
+impl BioInfo {
+    pub(crate) fn new() -> BioInfo {
+        unsafe {
+            BioInfo {
+                ptr: NonNull::new_unchecked(ffi::fido_bio_info_new()),
+            }
+        }
+    }
+
+    /// Return the kind of fingerprint sensor.
+    pub fn sensor_type(&self) -> FingerprintKind {
+        let kind = unsafe { ffi::fido_bio_info_type(self.ptr.as_ptr()) };
+
+        match kind {
+            1 => FingerprintKind::Touch,
+            2 => FingerprintKind::Swipe,
+            _ => FingerprintKind::Unknown(kind),
+        }
+    }
+
+    /// Return the maximum number of good samples required for an enrollment.
+    pub fn max_samples(&self) -> u8 {
+        unsafe { ffi::fido_bio_info_max_samples(self.ptr.as_ptr()) }
+    }
+}
+
+impl Drop for BioInfo {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::fido_bio_info_free(&mut self.ptr.as_ptr());
+        }
+    }
+}
+
+/// Fingerprint sensor kind.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FingerprintKind {
+    /// Touch type fingerprint sensor
+    Touch,
+    /// Swipe type fingerprint sensor
+    Swipe,
+    /// Sensor type not known to this crate, with the raw value reported by the authenticator
+    Unknown(u8),
+}
+
+/// A fingerprint enrollment in progress.
+pub struct Enrollment<'a> {
+    dev: &'a Device,
+    template: Template,
+    enroll: NonNull<ffi::fido_bio_enroll_t>,
+    timeout_ms: u32,
+}
+
+impl Enrollment<'_> {
+    /// Return the status of the last captured sample.
+    pub fn status(&self) -> EnrollStatus {
+        let status = unsafe { ffi::fido_bio_enroll_last_status(self.enroll.as_ptr()) };
+
+        EnrollStatus::from(status)
+    }
+
+    /// Return the number of good samples still required to complete this enrollment.
+    pub fn remaining_samples(&self) -> u8 {
+        unsafe { ffi::fido_bio_enroll_remaining_samples(self.enroll.as_ptr()) }
+    }
+
+    /// Returns true if no more samples are required.
+    pub fn is_complete(&self) -> bool {
+        self.remaining_samples() == 0
+    }
+
+    /// Return the template being enrolled.
+    ///
+    /// The template id is assigned by the authenticator when the enrollment begins.
+    pub fn template(&self) -> &TemplateRef {
+        &self.template
+    }
+
+    /// Capture the next sample.
+    ///
+    /// **Please note that `fido_bio_dev_enroll_continue()` is synchronous and will block if necessary.**
+    pub fn capture(&mut self) -> Result<()> {
+        unsafe {
+            self.dev.check_timeout(ffi::fido_bio_dev_enroll_continue(
+                self.dev.ptr.as_ptr(),
+                self.template.as_ptr(),
+                self.enroll.as_ptr(),
+                self.timeout_ms,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Cancel this enrollment.
+    pub fn cancel(self) -> Result<()> {
+        unsafe {
+            self.dev
+                .check_timeout(ffi::fido_bio_dev_enroll_cancel(self.dev.ptr.as_ptr()))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Enrollment<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::fido_bio_enroll_free(&mut self.enroll.as_ptr());
+        }
+    }
+}
+
+/// Status of a captured fingerprint sample.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EnrollStatus {
+    /// Good fingerprint capture
+    Good,
+    /// Fingerprint was too high
+    TooHigh,
+    /// Fingerprint was too low
+    TooLow,
+    /// Fingerprint was too left
+    TooLeft,
+    /// Fingerprint was too right
+    TooRight,
+    /// Fingerprint was too fast
+    TooFast,
+    /// Fingerprint was too slow
+    TooSlow,
+    /// Fingerprint was of poor quality
+    PoorQuality,
+    /// Fingerprint was too skewed
+    TooSkewed,
+    /// Fingerprint was too short
+    TooShort,
+    /// Merge failure of the capture
+    MergeFailure,
+    /// Fingerprint already exists
+    Exists,
+    /// No space left for another template
+    DatabaseFull,
+    /// User did not touch or swipe the sensor in time
+    NoUserActivity,
+    /// User did not lift the finger off the sensor between samples
+    NoUserPresenceTransition,
+    /// Status not known to this crate, with the raw value reported by the authenticator
+    Unknown(u8),
+}
+
+impl From<u8> for EnrollStatus {
+    fn from(value: u8) -> Self {
+        match value as i32 {
+            ffi::FIDO_BIO_ENROLL_FP_GOOD => EnrollStatus::Good,
+            ffi::FIDO_BIO_ENROLL_FP_TOO_HIGH => EnrollStatus::TooHigh,
+            ffi::FIDO_BIO_ENROLL_FP_TOO_LOW => EnrollStatus::TooLow,
+            ffi::FIDO_BIO_ENROLL_FP_TOO_LEFT => EnrollStatus::TooLeft,
+            ffi::FIDO_BIO_ENROLL_FP_TOO_RIGHT => EnrollStatus::TooRight,
+            ffi::FIDO_BIO_ENROLL_FP_TOO_FAST => EnrollStatus::TooFast,
+            ffi::FIDO_BIO_ENROLL_FP_TOO_SLOW => EnrollStatus::TooSlow,
+            ffi::FIDO_BIO_ENROLL_FP_POOR_QUALITY => EnrollStatus::PoorQuality,
+            ffi::FIDO_BIO_ENROLL_FP_TOO_SKEWED => EnrollStatus::TooSkewed,
+            ffi::FIDO_BIO_ENROLL_FP_TOO_SHORT => EnrollStatus::TooShort,
+            ffi::FIDO_BIO_ENROLL_FP_MERGE_FAILURE => EnrollStatus::MergeFailure,
+            ffi::FIDO_BIO_ENROLL_FP_EXISTS => EnrollStatus::Exists,
+            ffi::FIDO_BIO_ENROLL_FP_DATABASE_FULL => EnrollStatus::DatabaseFull,
+            ffi::FIDO_BIO_ENROLL_NO_USER_ACTIVITY => EnrollStatus::NoUserActivity,
+            ffi::FIDO_BIO_ENROLL_NO_USER_PRESENCE_TRANSITION => {
+                EnrollStatus::NoUserPresenceTransition
+            }
+            _ => EnrollStatus::Unknown(value),
+        }
+    }
+}
+
+/// A fingerprint template.
+pub struct Template(NonNull<ffi::fido_bio_template_t>);
+
+impl Drop for Template {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::fido_bio_template_free(&mut self.0.as_ptr());
+        }
+    }
+}
+
+impl ForeignType for Template {
+    type CType = ffi::fido_bio_template_t;
+    type Ref = TemplateRef;
+
+    unsafe fn from_ptr(ptr: *mut Self::CType) -> Self {
+        unsafe { Template(NonNull::new_unchecked(ptr)) }
+    }
+
+    fn as_ptr(&self) -> *mut Self::CType {
+        self.0.as_ptr()
+    }
+}
+
+/// A fingerprint template.
+pub struct TemplateRef(Opaque);
+
+impl ForeignTypeRef for TemplateRef {
+    type CType = ffi::fido_bio_template_t;
+}
+
+impl TemplateRef {
+    /// Return template ID.
+    pub fn id(&self) -> &[u8] {
+        let len = unsafe { ffi::fido_bio_template_id_len(self.as_ptr()) };
+        let ptr = unsafe { ffi::fido_bio_template_id_ptr(self.as_ptr()) };
+
+        unsafe { slice_or_empty(ptr, len) }
+    }
+
+    /// Return template friendly name, or [None] if is not set.
+    pub fn name(&self) -> Option<&str> {
+        let name = unsafe { ffi::fido_bio_template_name(self.as_ptr()) };
+        str_or_none!(name)
+    }
+}
+
+impl Template {
+    /// Create a new template
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        unsafe {
+            let template = ffi::fido_bio_template_new();
+
+            Template(NonNull::new_unchecked(template))
+        }
+    }
+
+    /// Set the template ID.
+    pub fn set_id(&mut self, id: impl AsRef<[u8]>) -> Result<()> {
+        let id = id.as_ref();
+        unsafe {
+            check(ffi::fido_bio_template_set_id(
+                self.0.as_ptr(),
+                id.as_ptr(),
+                id.len(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the template friendly name.
+    pub fn set_name(&mut self, name: impl AsRef<str>) -> Result<()> {
+        let name = CString::new(name.as_ref())?;
+        unsafe {
+            check(ffi::fido_bio_template_set_name(
+                self.0.as_ptr(),
+                name.as_ptr(),
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Deref for Template {
+    type Target = TemplateRef;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { TemplateRef::from_ptr(self.0.as_ptr()) }
+    }
+}
+
+/// Abstracts the set of fingerprint templates enrolled on the authenticator.
+pub struct TemplateArray {
+    ptr: NonNull<ffi::fido_bio_template_array_t>,
+}
+
+impl TemplateArray {
+    /// Returns the number of templates.
+    pub fn count(&self) -> usize {
+        unsafe { ffi::fido_bio_template_array_count(self.ptr.as_ptr()) }
+    }
+
+    /// Return an iterator over the templates.
+    pub fn iter(&self) -> IterTemplate<'_> {
+        IterTemplate {
+            idx: 0,
+            total: self.count(),
+            array: self.ptr,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl Index<usize> for TemplateArray {
+    type Output = TemplateRef;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        assert!(index < self.count(), "template index out of bounds");
+
+        unsafe {
+            let ptr = ffi::fido_bio_template(self.ptr.as_ptr(), index);
+
+            TemplateRef::from_ptr(ptr as *mut ffi::fido_bio_template_t)
+        }
+    }
+}
+
+impl Drop for TemplateArray {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::fido_bio_template_array_free(&mut self.ptr.as_ptr());
+        }
+    }
+}
+
+/// Iterator over fingerprint templates.
+pub struct IterTemplate<'a> {
+    idx: usize,
+    total: usize,
+    array: NonNull<ffi::fido_bio_template_array_t>,
+    _phantom: PhantomData<&'a ()>,
+}
+
+impl<'a> Iterator for IterTemplate<'a> {
+    type Item = &'a TemplateRef;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx >= self.total {
+            return None;
+        }
+
+        let ptr = unsafe { ffi::fido_bio_template(self.array.as_ptr(), self.idx) };
+
+        self.idx += 1;
+
+        let template = unsafe { TemplateRef::from_ptr(ptr as *mut ffi::fido_bio_template_t) };
+        Some(template)
+    }
+}
+
+impl ExactSizeIterator for IterTemplate<'_> {
+    fn len(&self) -> usize {
+        self.total - self.idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enroll_status() {
+        assert_eq!(EnrollStatus::from(0x00), EnrollStatus::Good);
+        assert_eq!(EnrollStatus::from(0x05), EnrollStatus::TooFast);
+        assert_eq!(
+            EnrollStatus::from(0x0e),
+            EnrollStatus::NoUserPresenceTransition
+        );
+        assert_eq!(EnrollStatus::from(0x42), EnrollStatus::Unknown(0x42));
+    }
+
+    #[test]
+    fn template() -> Result<()> {
+        let mut template = Template::new();
+        assert!(template.id().is_empty());
+        assert_eq!(template.name(), None);
+
+        template.set_id([1, 2, 3])?;
+        template.set_name("left thumb")?;
+        assert_eq!(template.id(), [1, 2, 3]);
+        assert_eq!(template.name(), Some("left thumb"));
+
+        Ok(())
+    }
+}
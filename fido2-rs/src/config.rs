@@ -0,0 +1,126 @@
+use std::ffi::CString;
+
+use zeroize::Zeroizing;
+
+use crate::device::Device;
+use crate::error::Result;
+
+/// FIDO2 authenticator configuration.
+///
+/// See the CTAP 2.1 authenticatorConfig command.
+pub struct AuthenticatorConfig<'a> {
+    dev: &'a Device,
+
+    pin: Option<Zeroizing<CString>>,
+}
+
+impl<'a> AuthenticatorConfig<'a> {
+    pub(crate) fn new(
+        device: &'a Device,
+        pin: Option<Zeroizing<CString>>,
+    ) -> AuthenticatorConfig<'a> {
+        AuthenticatorConfig { dev: device, pin }
+    }
+
+    fn pin_ptr(&self) -> *const libc::c_char {
+        match &self.pin {
+            Some(pin) => pin.as_ptr(),
+            None => std::ptr::null(),
+        }
+    }
+
+    /// Enable the CTAP 2.1 Enterprise Attestation feature on the authenticator.
+    pub fn enable_enterprise_attestation(&self) -> Result<()> {
+        unsafe {
+            self.dev.check_timeout(ffi::fido_dev_enable_entattest(
+                self.dev.ptr.as_ptr(),
+                self.pin_ptr(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Toggle the CTAP 2.1 alwaysUv feature on the authenticator.
+    ///
+    /// See [AuthenticatorConfig::set_always_uv] to set it to a known state.
+    pub fn toggle_always_uv(&self) -> Result<()> {
+        unsafe {
+            self.dev.check_timeout(ffi::fido_dev_toggle_always_uv(
+                self.dev.ptr.as_ptr(),
+                self.pin_ptr(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Enable or disable the CTAP 2.1 alwaysUv feature on the authenticator.
+    ///
+    /// The current state is read from the authenticator info, and the feature is only toggled
+    /// if it differs from `enabled`.
+    pub fn set_always_uv(&self, enabled: bool) -> Result<()> {
+        let info = self.dev.authenticator_info()?;
+        let current = info.options.always_uv_enabled();
+
+        if current != enabled {
+            self.toggle_always_uv()?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the minimum PIN length of the authenticator to `len`.
+    ///
+    /// The minimum PIN length can only be increased, unless the authenticator is reset.
+    pub fn set_pin_min_len(&self, len: usize) -> Result<()> {
+        unsafe {
+            self.dev.check_timeout(ffi::fido_dev_set_pin_minlen(
+                self.dev.ptr.as_ptr(),
+                len,
+                self.pin_ptr(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the list of relying party IDs that are allowed to read the minimum PIN length of
+    /// the authenticator through the FIDO_EXT_MINPINLEN extension.
+    pub fn set_pin_min_len_rp_ids<I, S>(&self, rp_ids: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let rp_ids = rp_ids
+            .into_iter()
+            .map(|it| CString::new(it.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rp_id_ptrs = rp_ids.iter().map(|it| it.as_ptr()).collect::<Vec<_>>();
+
+        unsafe {
+            self.dev.check_timeout(ffi::fido_dev_set_pin_minlen_rpid(
+                self.dev.ptr.as_ptr(),
+                rp_id_ptrs.as_ptr(),
+                rp_id_ptrs.len(),
+                self.pin_ptr(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Force a PIN change on the authenticator.
+    ///
+    /// Subsequent PIN operations will fail until the PIN is changed with [Device::set_pin].
+    pub fn force_pin_change(&self) -> Result<()> {
+        unsafe {
+            self.dev.check_timeout(ffi::fido_dev_force_pin_change(
+                self.dev.ptr.as_ptr(),
+                self.pin_ptr(),
+            ))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,412 @@
+//! COSE_Key encoding of credential public keys.
+//!
+//! [CoseKey] converts between the CBOR COSE_Key found in authenticator data, the raw layout of
+//! [CredentialRef::public_key](crate::credentials::CredentialRef::public_key) and openssl [PKey].
+use ciborium::Value;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Public};
+
+use crate::credentials::CoseType;
+use crate::error::{Error, Result};
+use crate::key::{ES256, ES384, Eddsa, Rsa};
+
+const MALFORMED: Error = Error::Malformed("COSE key");
+
+// COSE key parameters
+const KTY: i64 = 1;
+const ALG: i64 = 3;
+const CRV: i64 = -1;
+const X: i64 = -2;
+const Y: i64 = -3;
+const N: i64 = -1;
+const E: i64 = -2;
+
+// COSE key types
+const KTY_OKP: i64 = 1;
+const KTY_EC2: i64 = 2;
+const KTY_RSA: i64 = 3;
+
+// COSE algorithms
+const ALG_ES256: i64 = ffi::COSE_ES256 as i64;
+const ALG_ES384: i64 = ffi::COSE_ES384 as i64;
+const ALG_RS256: i64 = ffi::COSE_RS256 as i64;
+const ALG_EDDSA: i64 = ffi::COSE_EDDSA as i64;
+
+// COSE elliptic curves
+const CRV_P256: i64 = 1;
+const CRV_P384: i64 = 2;
+const CRV_ED25519: i64 = 6;
+
+/// Length of the RSA modulus supported by libfido2.
+const RS256_N_LEN: usize = 256;
+/// Length of the RSA public exponent supported by libfido2.
+const RS256_E_LEN: usize = 3;
+
+/// A credential public key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CoseKey {
+    /// ECDSA P-256 with SHA-256.
+    ES256 { x: [u8; 32], y: [u8; 32] },
+    /// ECDSA P-384 with SHA-384.
+    ES384 { x: [u8; 48], y: [u8; 48] },
+    /// RSASSA-PKCS1-v1_5 with SHA-256, `n` and `e` are big-endian.
+    RS256 { n: Vec<u8>, e: Vec<u8> },
+    /// Ed25519.
+    EdDSA { x: [u8; 32] },
+}
+
+impl CoseKey {
+    /// Parse a CBOR-encoded COSE_Key.
+    pub fn from_cbor(data: &[u8]) -> Result<CoseKey> {
+        let key: Value = ciborium::de::from_reader(data).map_err(|_| MALFORMED)?;
+        let map = key.as_map().ok_or(MALFORMED)?;
+
+        let param = |label: i64| {
+            map.iter()
+                .find(|(k, _)| k.as_integer() == Some(label.into()))
+                .map(|(_, v)| v)
+        };
+        let int = |label| {
+            param(label)
+                .and_then(Value::as_integer)
+                .and_then(|it| i64::try_from(it).ok())
+                .ok_or(MALFORMED)
+        };
+        let bytes = |label| param(label).and_then(Value::as_bytes).ok_or(MALFORMED);
+
+        match (int(KTY)?, int(ALG)?) {
+            (KTY_EC2, ALG_ES256) if int(CRV)? == CRV_P256 => Ok(CoseKey::ES256 {
+                x: fixed(bytes(X)?)?,
+                y: fixed(bytes(Y)?)?,
+            }),
+            (KTY_EC2, ALG_ES384) if int(CRV)? == CRV_P384 => Ok(CoseKey::ES384 {
+                x: fixed(bytes(X)?)?,
+                y: fixed(bytes(Y)?)?,
+            }),
+            (KTY_RSA, ALG_RS256) => Ok(CoseKey::RS256 {
+                n: bytes(N)?.clone(),
+                e: bytes(E)?.clone(),
+            }),
+            (KTY_OKP, ALG_EDDSA) if int(CRV)? == CRV_ED25519 => Ok(CoseKey::EdDSA {
+                x: fixed(bytes(X)?)?,
+            }),
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// Encode this key as a CBOR COSE_Key, in CTAP2 canonical form.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let param = |label: i64, value: Value| (Value::from(label), value);
+        let alg = Value::from(self.cose_type() as i64);
+
+        let key = match self {
+            CoseKey::ES256 { x, y } => vec![
+                param(KTY, Value::from(KTY_EC2)),
+                param(ALG, alg),
+                param(CRV, Value::from(CRV_P256)),
+                param(X, Value::from(&x[..])),
+                param(Y, Value::from(&y[..])),
+            ],
+            CoseKey::ES384 { x, y } => vec![
+                param(KTY, Value::from(KTY_EC2)),
+                param(ALG, alg),
+                param(CRV, Value::from(CRV_P384)),
+                param(X, Value::from(&x[..])),
+                param(Y, Value::from(&y[..])),
+            ],
+            CoseKey::RS256 { n, e } => vec![
+                param(KTY, Value::from(KTY_RSA)),
+                param(ALG, alg),
+                param(N, Value::from(n.as_slice())),
+                param(E, Value::from(e.as_slice())),
+            ],
+            CoseKey::EdDSA { x } => vec![
+                param(KTY, Value::from(KTY_OKP)),
+                param(ALG, alg),
+                param(CRV, Value::from(CRV_ED25519)),
+                param(X, Value::from(&x[..])),
+            ],
+        };
+
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&Value::Map(key), &mut data).expect("encode COSE key");
+
+        data
+    }
+
+    /// Parse a public key in the raw layout of libfido2, as returned by
+    /// [CredentialRef::public_key](crate::credentials::CredentialRef::public_key) for the type `ty`.
+    pub fn from_raw(ty: CoseType, data: &[u8]) -> Result<CoseKey> {
+        // libfido2 validates the layout, and for EC keys that the point is on the curve
+        let pkey = match ty {
+            CoseType::ES256 => ES256::from_raw(data)?.to_pkey()?,
+            CoseType::ES384 => ES384::from_raw(data)?.to_pkey()?,
+            CoseType::RS256 => Rsa::from_raw(data)?.to_pkey()?,
+            CoseType::EDDSA => Eddsa::from_raw(data)?.to_pkey()?,
+            CoseType::UNSPEC => return Err(Error::Unsupported),
+        };
+
+        CoseKey::from_pkey(&pkey)
+    }
+
+    /// Convert an openssl public key.
+    ///
+    /// Only P-256, P-384, Ed25519 and RSA keys are supported.
+    pub fn from_pkey(pkey: &PKey<Public>) -> Result<CoseKey> {
+        match pkey.id() {
+            Id::EC => {
+                let ec_key = pkey.ec_key()?;
+                let group = ec_key.group();
+
+                let mut x = BigNum::new()?;
+                let mut y = BigNum::new()?;
+                let mut ctx = BigNumContext::new()?;
+                ec_key
+                    .public_key()
+                    .affine_coordinates(group, &mut x, &mut y, &mut ctx)?;
+
+                match group.curve_name() {
+                    Some(Nid::X9_62_PRIME256V1) => Ok(CoseKey::ES256 {
+                        x: x.to_vec_padded(32)?.try_into().unwrap(),
+                        y: y.to_vec_padded(32)?.try_into().unwrap(),
+                    }),
+                    Some(Nid::SECP384R1) => Ok(CoseKey::ES384 {
+                        x: x.to_vec_padded(48)?.try_into().unwrap(),
+                        y: y.to_vec_padded(48)?.try_into().unwrap(),
+                    }),
+                    _ => Err(Error::Unsupported),
+                }
+            }
+            Id::RSA => {
+                let rsa = pkey.rsa()?;
+
+                Ok(CoseKey::RS256 {
+                    n: rsa.n().to_vec(),
+                    e: rsa.e().to_vec(),
+                })
+            }
+            Id::ED25519 => Ok(CoseKey::EdDSA {
+                x: pkey
+                    .raw_public_key()?
+                    .try_into()
+                    .map_err(|_| Error::Malformed("Ed25519 key"))?,
+            }),
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// Convert to an openssl public key.
+    ///
+    /// RSA keys are limited to what libfido2 supports, a 2048-bit modulus and an exponent of at
+    /// most 3 bytes.
+    pub fn to_pkey(&self) -> Result<PKey<Public>> {
+        Ok(match self {
+            CoseKey::ES256 { x, y } => ES256::from_raw(&[&x[..], &y[..]].concat())?.to_pkey()?,
+            CoseKey::ES384 { x, y } => ES384::from_raw(&[&x[..], &y[..]].concat())?.to_pkey()?,
+            CoseKey::RS256 { n, e } => Rsa::from_raw(&rs256_raw(n, e)?)?.to_pkey()?,
+            CoseKey::EdDSA { x } => Eddsa::from_raw(x)?.to_pkey()?,
+        })
+    }
+
+    /// Encode the public key as a PEM SubjectPublicKeyInfo.
+    pub fn to_pem(&self) -> Result<Vec<u8>> {
+        Ok(self.to_pkey()?.public_key_to_pem()?)
+    }
+
+    /// Encode the public key as a DER SubjectPublicKeyInfo.
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        Ok(self.to_pkey()?.public_key_to_der()?)
+    }
+
+    /// Return the COSE algorithm of this key.
+    pub fn cose_type(&self) -> CoseType {
+        match self {
+            CoseKey::ES256 { .. } => CoseType::ES256,
+            CoseKey::ES384 { .. } => CoseType::ES384,
+            CoseKey::RS256 { .. } => CoseType::RS256,
+            CoseKey::EdDSA { .. } => CoseType::EDDSA,
+        }
+    }
+}
+
+fn fixed<const LEN: usize>(data: &[u8]) -> Result<[u8; LEN]> {
+    data.try_into().map_err(|_| MALFORMED)
+}
+
+/// Lay out an RSA key as `rs256_pk_t`, a fixed size modulus followed by a fixed size exponent.
+fn rs256_raw(n: &[u8], e: &[u8]) -> Result<Vec<u8>> {
+    let n = strip_leading_zeros(n);
+    let e = strip_leading_zeros(e);
+    if n.len() != RS256_N_LEN || e.len() > RS256_E_LEN {
+        return Err(Error::Unsupported);
+    }
+
+    let mut raw = n.to_vec();
+    raw.resize(RS256_N_LEN + RS256_E_LEN - e.len(), 0);
+    raw.extend_from_slice(e);
+
+    Ok(raw)
+}
+
+fn strip_leading_zeros(data: &[u8]) -> &[u8] {
+    let start = data.iter().position(|it| *it != 0).unwrap_or(data.len());
+
+    &data[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+
+    fn cbor(value: &Value) -> Vec<u8> {
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(value, &mut data).unwrap();
+
+        data
+    }
+
+    fn public(pkey: PKey<openssl::pkey::Private>) -> PKey<Public> {
+        PKey::public_key_from_der(&pkey.public_key_to_der().unwrap()).unwrap()
+    }
+
+    fn ec_key(nid: Nid) -> PKey<Public> {
+        let group = EcGroup::from_curve_name(nid).unwrap();
+
+        public(PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap())
+    }
+
+    fn rsa_key(bits: u32) -> PKey<Public> {
+        public(PKey::from_rsa(openssl::rsa::Rsa::generate(bits).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn es256_canonical_encoding() {
+        let key = CoseKey::ES256 {
+            x: [0x11; 32],
+            y: [0x22; 32],
+        };
+
+        let mut expected = vec![0xa5, 0x01, 0x02, 0x03, 0x26, 0x20, 0x01, 0x21, 0x58, 0x20];
+        expected.extend_from_slice(&[0x11; 32]);
+        expected.extend_from_slice(&[0x22, 0x58, 0x20]);
+        expected.extend_from_slice(&[0x22; 32]);
+
+        assert_eq!(key.to_cbor(), expected);
+        assert_eq!(CoseKey::from_cbor(&expected).unwrap(), key);
+    }
+
+    #[test]
+    fn cbor_round_trip() {
+        let keys = [
+            CoseKey::ES256 {
+                x: [1; 32],
+                y: [2; 32],
+            },
+            CoseKey::ES384 {
+                x: [3; 48],
+                y: [4; 48],
+            },
+            CoseKey::RS256 {
+                n: vec![5; 256],
+                e: vec![1, 0, 1],
+            },
+            CoseKey::EdDSA { x: [6; 32] },
+        ];
+
+        for key in keys {
+            assert_eq!(CoseKey::from_cbor(&key.to_cbor()).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn pkey_round_trip() {
+        let keys = [
+            ec_key(Nid::X9_62_PRIME256V1),
+            ec_key(Nid::SECP384R1),
+            rsa_key(2048),
+            public(PKey::generate_ed25519().unwrap()),
+        ];
+
+        for pkey in keys {
+            let key = CoseKey::from_pkey(&pkey).unwrap();
+            let converted = key.to_pkey().unwrap();
+
+            assert!(converted.public_eq(&pkey), "{:?}", key.cose_type());
+            assert_eq!(CoseKey::from_cbor(&key.to_cbor()).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        let key = CoseKey::from_pkey(&ec_key(Nid::X9_62_PRIME256V1)).unwrap();
+        let CoseKey::ES256 { x, y } = &key else {
+            unreachable!()
+        };
+
+        let raw = [&x[..], &y[..]].concat();
+        assert_eq!(CoseKey::from_raw(CoseType::ES256, &raw).unwrap(), key);
+    }
+
+    #[test]
+    fn unsupported() {
+        // RSA-PSS with SHA-256
+        let pss = Value::Map(vec![
+            (Value::from(KTY), Value::from(KTY_RSA)),
+            (Value::from(ALG), Value::from(-37)),
+            (Value::from(N), Value::from(vec![1u8; 256])),
+            (Value::from(E), Value::from(vec![1u8, 0, 1])),
+        ]);
+        assert!(matches!(
+            CoseKey::from_cbor(&cbor(&pss)),
+            Err(Error::Unsupported)
+        ));
+
+        assert!(matches!(
+            CoseKey::from_pkey(&ec_key(Nid::SECP521R1)),
+            Err(Error::Unsupported)
+        ));
+
+        // libfido2 only supports 2048-bit RSA keys
+        let key = CoseKey::from_pkey(&rsa_key(1024)).unwrap();
+        assert!(matches!(key.to_pkey(), Err(Error::Unsupported)));
+    }
+
+    #[test]
+    fn malformed() {
+        let mut data = CoseKey::EdDSA { x: [6; 32] }.to_cbor();
+        data.pop();
+        assert!(matches!(
+            CoseKey::from_cbor(&data),
+            Err(Error::Malformed("COSE key"))
+        ));
+
+        let short = Value::Map(vec![
+            (Value::from(KTY), Value::from(KTY_OKP)),
+            (Value::from(ALG), Value::from(ALG_EDDSA)),
+            (Value::from(CRV), Value::from(CRV_ED25519)),
+            (Value::from(X), Value::from(vec![6u8; 31])),
+        ]);
+        assert!(matches!(
+            CoseKey::from_cbor(&cbor(&short)),
+            Err(Error::Malformed("COSE key"))
+        ));
+
+        let missing = Value::Map(vec![
+            (Value::from(KTY), Value::from(KTY_EC2)),
+            (Value::from(ALG), Value::from(ALG_ES256)),
+            (Value::from(CRV), Value::from(CRV_P256)),
+            (Value::from(X), Value::from(vec![1u8; 32])),
+        ]);
+        assert!(matches!(
+            CoseKey::from_cbor(&cbor(&missing)),
+            Err(Error::Malformed("COSE key"))
+        ));
+
+        assert!(matches!(
+            CoseKey::from_cbor(&cbor(&Value::Array(vec![]))),
+            Err(Error::Malformed("COSE key"))
+        ));
+    }
+}
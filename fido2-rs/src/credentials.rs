@@ -3,13 +3,22 @@ use std::ops::Deref;
 use std::ptr::NonNull;
 
 use bitflags::bitflags;
+use ciborium::Value;
 use foreign_types::{ForeignType, ForeignTypeRef, Opaque};
 
-use crate::error::Result;
-use crate::utils::check;
+use crate::authdata::AuthenticatorData;
+use crate::cose::CoseKey;
+use crate::error::{Error, Result};
+use crate::utils::{check, slice_or_empty};
 
 /// FIDO credential
-pub struct Credential(pub(crate) NonNull<ffi::fido_cred_t>);
+pub struct Credential(
+    pub(crate) NonNull<ffi::fido_cred_t>,
+    /// `epAtt` of the attestation object, which libfido2 does not keep.
+    Option<bool>,
+);
+
+unsafe impl Send for Credential {}
 
 impl Drop for Credential {
     fn drop(&mut self) {
@@ -25,7 +34,7 @@ impl ForeignType for Credential {
     type Ref = CredentialRef;
 
     unsafe fn from_ptr(ptr: *mut Self::CType) -> Self {
-        unsafe { Credential(NonNull::new_unchecked(ptr)) }
+        unsafe { Credential(NonNull::new_unchecked(ptr), None) }
     }
 
     fn as_ptr(&self) -> *mut Self::CType {
@@ -116,7 +125,7 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_authdata_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_authdata_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return raw authenticator data.
@@ -126,7 +135,12 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_authdata_raw_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_authdata_raw_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
+    }
+
+    /// Parse the authenticator data, see [AuthenticatorData].
+    pub fn authenticator_data(&self) -> Result<AuthenticatorData> {
+        AuthenticatorData::from_raw(self.auth_data_raw())
     }
 
     /// Return client data hash
@@ -136,7 +150,7 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_clientdata_hash_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_clientdata_hash_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return credential ID
@@ -146,7 +160,7 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_id_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_id_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return authenticator attestation GUID
@@ -156,7 +170,7 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_aaguid_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_aaguid_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return "largeBlobKey".
@@ -166,7 +180,7 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_largeblob_key_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_largeblob_key_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return public key.
@@ -176,7 +190,12 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_pubkey_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_pubkey_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
+    }
+
+    /// Return the public key as a [CoseKey].
+    pub fn cose_key(&self) -> Result<CoseKey> {
+        CoseKey::from_raw(self.cose_type(), self.public_key())
     }
 
     /// Return signature.
@@ -186,7 +205,7 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_sig_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_sig_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return user ID.
@@ -196,7 +215,7 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_user_id_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_user_id_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return X509 certificate.
@@ -206,7 +225,22 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_x5c_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_x5c_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
+    }
+
+    /// Return the X509 certificate chain of the attestation statement, starting with the
+    /// attestation certificate returned by [CredentialRef::certificate].
+    pub fn certificates(&self) -> Vec<&[u8]> {
+        let count = unsafe { ffi::fido_cred_x5c_list_count(self.as_ptr()) };
+
+        (0..count)
+            .map(|idx| unsafe {
+                let len = ffi::fido_cred_x5c_list_len(self.as_ptr(), idx);
+                let ptr = ffi::fido_cred_x5c_list_ptr(self.as_ptr(), idx);
+
+                slice_or_empty(ptr, len)
+            })
+            .collect()
     }
 
     /// Return attestation statement.
@@ -216,7 +250,7 @@ impl CredentialRef {
         let len = unsafe { ffi::fido_cred_attstmt_len(self.as_ptr()) };
         let ptr = unsafe { ffi::fido_cred_attstmt_ptr(self.as_ptr()) };
 
-        unsafe { std::slice::from_raw_parts(ptr, len) }
+        unsafe { slice_or_empty(ptr, len) }
     }
 
     /// Return the COSE algorithm of cred.
@@ -242,7 +276,8 @@ impl CredentialRef {
     /// minimum PIN length, and resident/discoverable key and user verification attributes of cred
     /// have been attested by the holder of the private counterpart of the public key contained in the credential's x509 certificate.
     ///
-    /// Please note that the x509 certificate itself is not verified.
+    /// Please note that the x509 certificate itself is not verified, see
+    /// [AttestationVerifier](crate::attestation::AttestationVerifier) for that.
     ///
     /// The attestation statement formats supported by [Credential::verify] are packed, fido-u2f, and tpm.
     ///
@@ -278,10 +313,67 @@ impl Credential {
         unsafe {
             let cred = ffi::fido_cred_new();
 
-            Credential(NonNull::new_unchecked(cred))
+            Credential(NonNull::new_unchecked(cred), None)
         }
     }
 
+    /// Create a credential from a CBOR-encoded WebAuthn attestation object, e.g. from
+    /// `AuthenticatorAttestationResponse.attestationObject`.
+    ///
+    /// The attestation statement format, the type, the authenticator data, the attestation
+    /// statement and the CTAP 2.1 `epAtt` member, see [Credential::enterprise_attestation], are
+    /// set. To verify it with [CredentialRef::verify], the relying party ID and the
+    /// client data hash must be set too, with [Credential::set_rp] and [Credential::set_client_data_hash].
+    pub fn from_attestation_object(data: &[u8]) -> Result<Credential> {
+        const MALFORMED: Error = Error::Malformed("attestation object");
+
+        let object: Value = ciborium::de::from_reader(data).map_err(|_| MALFORMED)?;
+        let field = |name| {
+            object
+                .as_map()?
+                .iter()
+                .find(|(k, _)| k.as_text() == Some(name))
+                .map(|(_, v)| v)
+        };
+
+        let fmt = match field("fmt").and_then(Value::as_text).ok_or(MALFORMED)? {
+            "packed" => AttestationFormat::Packed,
+            "fido-u2f" => AttestationFormat::FidoU2f,
+            "tpm" => AttestationFormat::Tpm,
+            "none" => AttestationFormat::None,
+            _ => return Err(Error::Unsupported),
+        };
+        let auth_data = field("authData")
+            .and_then(Value::as_bytes)
+            .ok_or(MALFORMED)?;
+        let att_stmt = field("attStmt").filter(|it| it.is_map()).ok_or(MALFORMED)?;
+        let ep_att = match field("epAtt") {
+            Some(value) => value.as_bool().ok_or(MALFORMED)?,
+            None => false,
+        };
+
+        let parsed = AuthenticatorData::from_raw(auth_data)?;
+        let ty = parsed
+            .attested_credential
+            .as_ref()
+            .ok_or(Error::Malformed("authenticator data"))?
+            .cose_key()?
+            .cose_type();
+
+        let mut cred = Credential::new();
+        cred.set_attestation_format(fmt)?;
+        cred.set_cose_type(ty)?;
+        cred.set_extensions_from(&parsed)?;
+        cred.set_authdata_raw(auth_data)?;
+
+        let mut att_stmt_data = Vec::new();
+        ciborium::ser::into_writer(att_stmt, &mut att_stmt_data).map_err(|_| MALFORMED)?;
+        cred.set_attstmt(att_stmt_data)?;
+        cred.1 = Some(ep_att);
+
+        Ok(cred)
+    }
+
     /// Set the id
     pub fn set_id(&mut self, id: impl AsRef<[u8]>) -> Result<()> {
         let id = id.as_ref();
@@ -448,6 +540,33 @@ impl Credential {
         Ok(())
     }
 
+    /// Request CTAP 2.1 enterprise attestation for cred.
+    ///
+    /// The authenticator must support enterprise attestation and have it enabled, see
+    /// [AuthenticatorConfig::enable_enterprise_attestation](crate::config::AuthenticatorConfig::enable_enterprise_attestation)
+    /// and [AuthenticatorOptions::enterprise_attestation_enabled](crate::info::AuthenticatorOptions::enterprise_attestation_enabled).
+    ///
+    /// Whether an enterprise attestation was actually returned is reported by
+    /// [Credential::enterprise_attestation].
+    pub fn set_enterprise_attestation(&mut self, ea: EnterpriseAttestation) -> Result<()> {
+        unsafe {
+            check(ffi::fido_cred_set_entattest(self.0.as_ptr(), ea as i32))?;
+        }
+
+        Ok(())
+    }
+
+    /// Return whether the attestation of cred is an enterprise attestation, i.e. the `epAtt`
+    /// member of the attestation object, or `None` if not known.
+    ///
+    /// libfido2 drops `epAtt` from the response of the authenticator, so this is only known for a
+    /// credential created with [Credential::from_attestation_object]. The attestation certificate
+    /// of a credential made with [Device::make_credential](crate::device::Device::make_credential)
+    /// tells whether it is an enterprise attestation.
+    pub fn enterprise_attestation(&self) -> Option<bool> {
+        self.1
+    }
+
     /// Set the rk (resident/discoverable key) attribute of cred.
     pub fn set_rk(&mut self, rk: Opt) -> Result<()> {
         unsafe {
@@ -497,6 +616,227 @@ impl Credential {
 
         Ok(())
     }
+
+    /// Set the authenticator data part of cred.
+    ///
+    /// The authenticator data must be a CBOR-encoded byte string, as obtained from [CredentialRef::auth_data].
+    ///
+    /// The type of cred must be set with [Credential::set_cose_type] first, as the public key in
+    /// the authenticator data is decoded according to it.
+    pub fn set_authdata(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        let data = data.as_ref();
+        unsafe {
+            check(ffi::fido_cred_set_authdata(
+                self.0.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the raw binary authenticator data part of cred.
+    ///
+    /// The type of cred must be set with [Credential::set_cose_type] first.
+    pub fn set_authdata_raw(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        let data = data.as_ref();
+        unsafe {
+            check(ffi::fido_cred_set_authdata_raw(
+                self.0.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the attestation statement of cred, a CBOR-encoded map as found in the `attStmt` of an attestation object.
+    ///
+    /// The attestation certificate and signature of cred are set from the statement, so
+    /// [Credential::set_x509] and [Credential::set_sig] are not needed.
+    ///
+    /// The attestation statement format must be set with [Credential::set_attestation_format] first.
+    pub fn set_attstmt(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        let data = data.as_ref();
+        unsafe {
+            check(ffi::fido_cred_set_attstmt(
+                self.0.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the attestation certificate of cred, a DER-encoded X509 certificate.
+    pub fn set_x509(&mut self, cert: impl AsRef<[u8]>) -> Result<()> {
+        let cert = cert.as_ref();
+        unsafe {
+            check(ffi::fido_cred_set_x509(
+                self.0.as_ptr(),
+                cert.as_ptr(),
+                cert.len(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the attestation signature of cred.
+    pub fn set_sig(&mut self, sig: impl AsRef<[u8]>) -> Result<()> {
+        let sig = sig.as_ref();
+        unsafe {
+            check(ffi::fido_cred_set_sig(
+                self.0.as_ptr(),
+                sig.as_ptr(),
+                sig.len(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the requested extensions of cred from the extension outputs of its authenticator data,
+    /// which [CredentialRef::verify] requires to match.
+    fn set_extensions_from(&mut self, auth_data: &AuthenticatorData) -> Result<()> {
+        let enabled = |name| auth_data.extension(name).and_then(Value::as_bool) == Some(true);
+
+        let mut flags = Extensions::empty();
+        if enabled("hmac-secret") {
+            flags |= Extensions::HMAC_SECRET;
+        }
+        if enabled("credBlob") {
+            flags |= Extensions::CRED_BLOB;
+        }
+        self.set_extension(flags)?;
+
+        let int = |name| {
+            auth_data
+                .extension(name)
+                .and_then(Value::as_integer)
+                .and_then(|it| i32::try_from(it).ok())
+        };
+        if let Some(prot) = int("credProtect") {
+            let prot = match prot {
+                ffi::FIDO_CRED_PROT_UV_OPTIONAL => Protection::UvOptional,
+                ffi::FIDO_CRED_PROT_UV_OPTIONAL_WITH_ID => Protection::UvOptionalWithId,
+                ffi::FIDO_CRED_PROT_UV_REQUIRED => Protection::UvRequired,
+                _ => return Err(Error::Malformed("credProtect")),
+            };
+            self.set_protection(prot)?;
+        }
+        if let Some(len) = int("minPinLength") {
+            self.set_pin_min_len(len as usize)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An owned copy of a [Credential], to be stored after [Device::make_credential](crate::device::Device::make_credential).
+///
+/// With the `serde` feature, it can be serialized and deserialized. It can be turned back into a
+/// [Credential] with [TryFrom], e.g. to verify the attestation again with [CredentialRef::verify].
+///
+/// The largeBlobKey is not included.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CredentialRecord {
+    /// Credential ID.
+    pub id: Vec<u8>,
+    /// COSE algorithm.
+    pub cose_type: CoseType,
+    /// Public key, in the raw layout of libfido2, see [CoseKey::from_raw].
+    pub public_key: Vec<u8>,
+    /// Relying party ID.
+    pub rp_id: Option<String>,
+    /// Relying party name.
+    pub rp_name: Option<String>,
+    /// User ID.
+    pub user_id: Vec<u8>,
+    /// User name.
+    pub user_name: Option<String>,
+    /// User display name.
+    pub display_name: Option<String>,
+    /// Client data hash.
+    pub client_data_hash: Vec<u8>,
+    /// CBOR-encoded authenticator data.
+    pub auth_data: Vec<u8>,
+    /// Attestation statement format.
+    pub attestation_format: Option<AttestationFormat>,
+    /// Attestation certificate.
+    pub certificate: Vec<u8>,
+    /// Attestation signature.
+    pub signature: Vec<u8>,
+    /// CBOR-encoded attestation statement.
+    pub attestation: Vec<u8>,
+}
+
+impl From<&CredentialRef> for CredentialRecord {
+    fn from(cred: &CredentialRef) -> Self {
+        CredentialRecord {
+            id: cred.id().to_vec(),
+            cose_type: cred.cose_type(),
+            public_key: cred.public_key().to_vec(),
+            rp_id: cred.rp_id().map(str::to_owned),
+            rp_name: cred.rp_name().map(str::to_owned),
+            user_id: cred.user_id().to_vec(),
+            user_name: cred.user_name().map(str::to_owned),
+            display_name: cred.display_name().map(str::to_owned),
+            client_data_hash: cred.client_data_hash().to_vec(),
+            auth_data: cred.auth_data().to_vec(),
+            attestation_format: cred.attestation_format(),
+            certificate: cred.certificate().to_vec(),
+            signature: cred.signature().to_vec(),
+            attestation: cred.attestation().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&CredentialRecord> for Credential {
+    type Error = Error;
+
+    fn try_from(record: &CredentialRecord) -> Result<Self> {
+        let mut cred = Credential::new();
+        cred.set_cose_type(record.cose_type)?;
+
+        if let Some(fmt) = record.attestation_format {
+            cred.set_attestation_format(fmt)?;
+        }
+        if let Some(rp_id) = &record.rp_id {
+            cred.set_rp(rp_id, record.rp_name.as_deref().unwrap_or_default())?;
+        }
+        if !record.user_id.is_empty() {
+            cred.set_user(
+                &record.user_id,
+                record.user_name.as_deref().unwrap_or_default(),
+                record.display_name.as_deref(),
+                None,
+            )?;
+        }
+        if !record.client_data_hash.is_empty() {
+            cred.set_client_data_hash(&record.client_data_hash)?;
+        }
+        if !record.auth_data.is_empty() {
+            cred.set_extensions_from(&AuthenticatorData::from_cbor(&record.auth_data)?)?;
+            cred.set_authdata(&record.auth_data)?;
+        }
+        if !record.attestation.is_empty() {
+            cred.set_attstmt(&record.attestation)?;
+        } else {
+            if !record.certificate.is_empty() {
+                cred.set_x509(&record.certificate)?;
+            }
+            if !record.signature.is_empty() {
+                cred.set_sig(&record.signature)?;
+            }
+        }
+
+        Ok(cred)
+    }
 }
 
 impl AsRef<CredentialRef> for Credential {
@@ -529,16 +869,33 @@ pub enum Protection {
     UvRequired = ffi::FIDO_CRED_PROT_UV_REQUIRED,
 }
 
+/// Enterprise attestation mode
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(i32)]
+pub enum EnterpriseAttestation {
+    /// Vendor-facilitated, the RP ID must be on a list pre-configured in the authenticator.
+    Vendor = ffi::FIDO_ENTATTEST_VENDOR,
+    /// Platform-managed, the platform decides whether the RP ID is allowed.
+    Platform = ffi::FIDO_ENTATTEST_PLATFORM,
+}
+
 /// Attestation statement format
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttestationFormat {
+    #[cfg_attr(feature = "serde", serde(rename = "packed"))]
     Packed,
+    #[cfg_attr(feature = "serde", serde(rename = "fido-u2f"))]
     FidoU2f,
+    #[cfg_attr(feature = "serde", serde(rename = "tpm"))]
     Tpm,
+    #[cfg_attr(feature = "serde", serde(rename = "none"))]
     None,
 }
 
 /// COSE Algorithms type
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(i32)]
 pub enum CoseType {
     ES256 = ffi::COSE_ES256,
@@ -573,3 +930,277 @@ bitflags! {
         const LARGEBLOB_KEY = ffi::FIDO_EXT_LARGEBLOB_KEY;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::sha::sha256;
+    use openssl::sign::Signer;
+    use openssl::x509::{X509, X509Builder, X509Name};
+
+    const RP_ID: &str = "example.com";
+    const CLIENT_DATA_HASH: [u8; 32] = [0x44; 32];
+    const CREDENTIAL_ID: [u8; 16] = [0x33; 16];
+
+    fn key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn certificate(key: &PKey<Private>) -> X509 {
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, "attestation")
+            .unwrap();
+        let name = name.build();
+
+        let mut cert = X509Builder::new().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        cert.sign(key, MessageDigest::sha256()).unwrap();
+
+        cert.build()
+    }
+
+    /// Return authenticator data attesting `key`, with the extension outputs `extensions`.
+    fn auth_data(key: &PKey<Private>, extensions: Option<Value>) -> Vec<u8> {
+        let public = PKey::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap();
+
+        let mut data = sha256(RP_ID.as_bytes()).to_vec();
+        data.push(if extensions.is_some() { 0xc1 } else { 0x41 }); // UP | AT | ED
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&[0x22; 16]);
+        data.extend_from_slice(&(CREDENTIAL_ID.len() as u16).to_be_bytes());
+        data.extend_from_slice(&CREDENTIAL_ID);
+        data.extend_from_slice(&CoseKey::from_pkey(&public).unwrap().to_cbor());
+        if let Some(extensions) = extensions {
+            ciborium::ser::into_writer(&extensions, &mut data).unwrap();
+        }
+
+        data
+    }
+
+    /// Return a packed statement of `auth_data` signed by `key`, with the certificate `x5c`.
+    fn packed(auth_data: &[u8], key: &PKey<Private>, x5c: Option<&X509>) -> Value {
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(auth_data).unwrap();
+        signer.update(&CLIENT_DATA_HASH).unwrap();
+
+        let mut att_stmt = vec![
+            (Value::from("alg"), Value::from(ffi::COSE_ES256)),
+            (
+                Value::from("sig"),
+                Value::from(signer.sign_to_vec().unwrap()),
+            ),
+        ];
+        if let Some(cert) = x5c {
+            let x5c = vec![Value::from(cert.to_der().unwrap())];
+            att_stmt.push((Value::from("x5c"), Value::Array(x5c)));
+        }
+
+        Value::Map(att_stmt)
+    }
+
+    fn object(entries: Vec<(&str, Value)>) -> Vec<u8> {
+        let entries = entries
+            .into_iter()
+            .map(|(k, v)| (Value::from(k), v))
+            .collect();
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&Value::Map(entries), &mut data).unwrap();
+
+        data
+    }
+
+    fn parse(entries: Vec<(&str, Value)>) -> Result<Credential> {
+        let mut cred = Credential::from_attestation_object(&object(entries))?;
+        cred.set_rp(RP_ID, "example")?;
+        cred.set_client_data_hash(CLIENT_DATA_HASH)?;
+
+        Ok(cred)
+    }
+
+    #[test]
+    fn packed_basic_attestation() {
+        let key = key();
+        let attestation_key = self::key();
+        let cert = certificate(&attestation_key);
+        let auth_data = auth_data(&key, None);
+
+        let cred = parse(vec![
+            ("fmt", Value::from("packed")),
+            ("attStmt", packed(&auth_data, &attestation_key, Some(&cert))),
+            ("authData", Value::from(auth_data.clone())),
+        ])
+        .unwrap();
+
+        assert_eq!(cred.attestation_format(), Some(AttestationFormat::Packed));
+        assert_eq!(cred.cose_type(), CoseType::ES256);
+        assert_eq!(cred.id(), CREDENTIAL_ID);
+        assert_eq!(cred.auth_data_raw(), auth_data);
+        assert_eq!(cred.certificate(), cert.to_der().unwrap());
+        cred.verify().unwrap();
+
+        // signed by another key than the certificate's
+        let cred = parse(vec![
+            ("fmt", Value::from("packed")),
+            ("attStmt", packed(&auth_data, &key, Some(&cert))),
+            ("authData", Value::from(auth_data)),
+        ])
+        .unwrap();
+        assert!(cred.verify().is_err());
+    }
+
+    #[test]
+    fn packed_self_attestation() {
+        let key = key();
+        let auth_data = auth_data(&key, None);
+
+        let object = vec![
+            ("fmt", Value::from("packed")),
+            ("attStmt", packed(&auth_data, &key, None)),
+            ("authData", Value::from(auth_data)),
+        ];
+        let mut cred = parse(object.clone()).unwrap();
+        cred.verify_self().unwrap();
+        assert!(cred.verify().is_err());
+
+        cred.set_client_data_hash([0x55; 32]).unwrap();
+        assert!(cred.verify_self().is_err());
+
+        // the relying party ID and client data hash are not part of the attestation object
+        let cred = Credential::from_attestation_object(&self::object(object)).unwrap();
+        assert!(cred.verify_self().is_err());
+    }
+
+    #[test]
+    fn none_attestation() {
+        let key = key();
+
+        let cred = parse(vec![
+            ("fmt", Value::from("none")),
+            ("attStmt", Value::Map(vec![])),
+            ("authData", Value::from(auth_data(&key, None))),
+        ])
+        .unwrap();
+
+        assert_eq!(cred.attestation_format(), Some(AttestationFormat::None));
+        assert_eq!(cred.id(), CREDENTIAL_ID);
+        assert!(cred.signature().is_empty());
+        assert!(cred.certificate().is_empty());
+        assert_eq!(cred.enterprise_attestation(), Some(false));
+        assert!(cred.verify().is_err());
+    }
+
+    #[test]
+    fn extensions() {
+        let key = key();
+        // in canonical order
+        let extensions = Value::Map(vec![
+            (Value::from("credProtect"), Value::from(3)),
+            (Value::from("hmac-secret"), Value::from(true)),
+            (Value::from("minPinLength"), Value::from(6)),
+        ]);
+        let auth_data = auth_data(&key, Some(extensions));
+
+        let cred = parse(vec![
+            ("fmt", Value::from("packed")),
+            ("attStmt", packed(&auth_data, &key, None)),
+            ("authData", Value::from(auth_data)),
+        ])
+        .unwrap();
+
+        // verification requires the extensions of cred to match the authenticator data
+        cred.verify_self().unwrap();
+        assert!(matches!(cred.protection(), Some(Protection::UvRequired)));
+        assert_eq!(cred.pin_min_len(), 6);
+    }
+
+    #[test]
+    fn enterprise_attestation() {
+        let key = key();
+        let auth_data = auth_data(&key, None);
+        let entries = |ep_att| {
+            vec![
+                ("fmt", Value::from("none")),
+                ("epAtt", ep_att),
+                ("attStmt", Value::Map(vec![])),
+                ("authData", Value::from(auth_data.clone())),
+            ]
+        };
+
+        assert_eq!(Credential::new().enterprise_attestation(), None);
+
+        let cred = parse(entries(Value::from(true))).unwrap();
+        assert_eq!(cred.enterprise_attestation(), Some(true));
+        let cred = parse(entries(Value::from(false))).unwrap();
+        assert_eq!(cred.enterprise_attestation(), Some(false));
+        assert!(matches!(
+            parse(entries(Value::from(1))),
+            Err(Error::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn unsupported_format() {
+        let key = key();
+        let auth_data = auth_data(&key, None);
+
+        let result = Credential::from_attestation_object(&object(vec![
+            ("fmt", Value::from("android-key")),
+            ("attStmt", packed(&auth_data, &key, None)),
+            ("authData", Value::from(auth_data)),
+        ]));
+        assert!(matches!(result, Err(Error::Unsupported)));
+    }
+
+    #[test]
+    fn malformed() {
+        let key = key();
+        let auth_data = auth_data(&key, None);
+        let att_stmt = packed(&auth_data, &key, None);
+
+        let malformed = |data: &[u8]| {
+            matches!(
+                Credential::from_attestation_object(data),
+                Err(Error::Malformed("attestation object"))
+            )
+        };
+
+        assert!(malformed(b"not cbor"));
+        assert!(malformed(&object(vec![
+            ("fmt", Value::from("packed")),
+            ("attStmt", att_stmt.clone()),
+        ])));
+        assert!(malformed(&object(vec![
+            ("fmt", Value::from("packed")),
+            ("authData", Value::from(auth_data.clone())),
+        ])));
+        assert!(malformed(&object(vec![
+            ("attStmt", att_stmt.clone()),
+            ("authData", Value::from(auth_data.clone())),
+        ])));
+
+        // no attested credential data
+        let result = Credential::from_attestation_object(&object(vec![
+            ("fmt", Value::from("packed")),
+            ("attStmt", att_stmt),
+            ("authData", Value::from(&auth_data[..37])),
+        ]));
+        assert!(matches!(
+            result,
+            Err(Error::Malformed("authenticator data"))
+        ));
+    }
+}
@@ -9,8 +9,8 @@ use zeroize::Zeroizing;
 
 use crate::credentials::{Credential, CredentialRef};
 use crate::device::Device;
-use crate::error::Result;
-use crate::utils::check;
+use crate::error::{Error, FidoErrorKind, Result};
+use crate::largeblob::{LargeBlobArray, LargeBlobEntry};
 
 /// FIDO2 credential management.
 pub struct CredentialManagement<'a> {
@@ -51,7 +51,7 @@ impl<'a> CredentialManagement<'a> {
         unsafe {
             let p = ffi::fido_credman_rp_new();
 
-            check(ffi::fido_credman_get_dev_rp(
+            self.dev.check_timeout(ffi::fido_credman_get_dev_rp(
                 self.dev.ptr.as_ptr(),
                 p,
                 pin_ptr,
@@ -75,7 +75,7 @@ impl<'a> CredentialManagement<'a> {
 
         unsafe {
             let rk = ffi::fido_credman_rk_new();
-            check(ffi::fido_credman_get_dev_rk(
+            self.dev.check_timeout(ffi::fido_credman_get_dev_rk(
                 self.dev.ptr.as_ptr(),
                 rp.as_ptr(),
                 rk,
@@ -84,7 +84,7 @@ impl<'a> CredentialManagement<'a> {
 
             Ok(CredManRK {
                 ptr: NonNull::new_unchecked(rk),
-                _phantom: PhantomData::default(),
+                _phantom: PhantomData,
             })
         }
     }
@@ -99,7 +99,7 @@ impl<'a> CredentialManagement<'a> {
         let pin_ptr = self.pin.as_ptr();
 
         unsafe {
-            check(ffi::fido_credman_del_dev_rk(
+            self.dev.check_timeout(ffi::fido_credman_del_dev_rk(
                 self.dev.ptr.as_ptr(),
                 cred_id.as_ptr(),
                 cred_id.len(),
@@ -121,7 +121,7 @@ impl<'a> CredentialManagement<'a> {
         let pin_ptr = self.pin.as_ptr();
 
         unsafe {
-            check(ffi::fido_credman_set_dev_rk(
+            self.dev.check_timeout(ffi::fido_credman_set_dev_rk(
                 self.dev.ptr.as_ptr(),
                 cred.as_ptr(),
                 pin_ptr,
@@ -130,6 +130,87 @@ impl<'a> CredentialManagement<'a> {
             Ok(())
         }
     }
+
+    /// Return the largeBlobKey of every resident credential in dev that has one.
+    pub fn large_blob_keys(&self) -> Result<Vec<Zeroizing<Vec<u8>>>> {
+        let mut keys = Vec::new();
+
+        // authenticators without resident credentials fail the enumeration
+        let rps = match self.get_rp() {
+            Err(e) if e.fido_kind() == Some(FidoErrorKind::NoCredentials) => return Ok(keys),
+            rps => rps?,
+        };
+
+        for rp in rps {
+            let rk = self.get_rk(rp.id)?;
+            keys.extend(
+                rk.iter()
+                    .map(CredentialRef::large_blob_key)
+                    .filter(|key| !key.is_empty())
+                    .map(|key| Zeroizing::new(key.to_vec())),
+            );
+        }
+
+        Ok(keys)
+    }
+
+    /// Return the largeBlob entries that no resident credential in dev can decrypt, i.e. the
+    /// entries [CredentialManagement::prune_large_blobs] would remove.
+    ///
+    /// An entry belongs to a credential if it is authenticated with its largeBlobKey, it is never
+    /// decompressed. Elements of the array which are not valid entries are never returned.
+    ///
+    /// Return [Error::Unsupported] if the largeBlobKeys of resident credentials cannot be
+    /// enumerated: authenticators with only the CTAP 2.1 preview of credential management do not
+    /// return them. Return [Error::Verification] if the array has entries but no resident
+    /// credential has a largeBlobKey, rather than reporting every entry.
+    pub fn orphaned_large_blobs(&self) -> Result<Vec<LargeBlobEntry>> {
+        Ok(self.orphans()?.1)
+    }
+
+    /// Remove the largeBlob entries that no resident credential in dev can decrypt.
+    ///
+    /// Deleting a resident credential with [CredentialManagement::delete_rk] leaves its largeBlob
+    /// entry behind. The pruned array is written back in a single write, and only if an entry was
+    /// removed. Return the number of removed entries.
+    ///
+    /// See [CredentialManagement::orphaned_large_blobs] for the entries that would be removed,
+    /// and when nothing is removed. Entries written by another client between the read and the
+    /// write are lost.
+    pub fn prune_large_blobs(&self) -> Result<usize> {
+        let (mut array, orphans) = self.orphans()?;
+        if orphans.is_empty() {
+            return Ok(0);
+        }
+
+        array.retain(|entry| !orphans.contains(entry));
+        array.write_with(self.dev, &self.pin)?;
+
+        Ok(orphans.len())
+    }
+
+    fn orphans(&self) -> Result<(LargeBlobArray, Vec<LargeBlobEntry>)> {
+        let options = self.dev.authenticator_info()?.options;
+        if options.cred_mgmt != Some(true) {
+            return Err(Error::Unsupported);
+        }
+
+        let keys = self.large_blob_keys()?;
+        let array = LargeBlobArray::from_device(self.dev)?;
+        if keys.is_empty() && array.entries().next().is_some() {
+            return Err(Error::Verification(
+                "no largeBlobKey of resident credentials",
+            ));
+        }
+
+        let orphans = array
+            .entries()
+            .filter(|entry| !keys.iter().any(|key| entry.is_encrypted_with(key)))
+            .cloned()
+            .collect();
+
+        Ok((array, orphans))
+    }
 }
 
 impl<'a> Drop for CredentialManagement<'a> {
@@ -1,14 +1,21 @@
 use crate::assertion::{AssertRequest, Assertions};
+use crate::bio::{BioEnrollment, BioInfo};
 use crate::cbor::CBORInfo;
+use crate::config::AuthenticatorConfig;
 use crate::credentials::Credential;
 use crate::credman::CredentialManagement;
-use crate::error::{Error, Result};
+use crate::error::{Error, FidoError, Result};
+use crate::info::{AuthenticatorInfo, PinProtocol};
+use crate::transport::{self, Transport, TransportHandle};
 use crate::utils::check;
 use bitflags::bitflags;
 use ffi::fido_dev_t;
 use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
+use std::os::raw::c_int;
 use std::ptr::NonNull;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use zeroize::Zeroizing;
 
 /// Device list.
@@ -108,28 +115,59 @@ pub struct DeviceInfo<'a> {
 impl<'a> DeviceInfo<'a> {
     /// Open the device specified by this [DeviceInfo]
     pub fn open(&self) -> Result<Device> {
-        unsafe {
-            let ptr = ffi::fido_dev_new();
-            check(ffi::fido_dev_open(ptr, self.path.as_ptr()))?;
-
-            let ptr = NonNull::new_unchecked(ptr);
+        let device = Device::new(&DeviceBuilder::new(), None)?;
 
-            Ok(Device { ptr })
+        unsafe {
+            check(ffi::fido_dev_open(device.ptr.as_ptr(), self.path.as_ptr()))?;
         }
+
+        Ok(device)
     }
 }
 
 /// A cancel handle to device, used to cancel a pending requests.
 ///
-/// This handle can be copy/clone.
-#[derive(Copy, Clone, Eq, PartialEq)]
-pub struct DeviceCancel(NonNull<fido_dev_t>);
+/// This handle can be cloned and sent to another thread, to cancel a request blocking the thread
+/// using the [Device]. It keeps the device open until it is dropped.
+#[derive(Clone)]
+pub struct DeviceCancel(Arc<RawDevice>);
 
 impl DeviceCancel {
     /// Cancel any pending requests on device.
     pub fn cancel(&self) {
         unsafe {
-            ffi::fido_dev_cancel(self.0.as_ptr());
+            ffi::fido_dev_cancel(self.0.ptr.as_ptr());
+        }
+    }
+}
+
+impl PartialEq for DeviceCancel {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for DeviceCancel {}
+
+/// The libfido2 device, closed and freed once its [Device] and every [DeviceCancel] are dropped.
+struct RawDevice {
+    ptr: NonNull<fido_dev_t>,
+
+    /// The custom transport of the device, dropped once the device is closed.
+    transport: Option<TransportHandle>,
+}
+
+// Other threads only call `fido_dev_cancel` through a [DeviceCancel], which libfido2 allows during
+// a request, and custom transports are `Sync` for this reason.
+unsafe impl Send for RawDevice {}
+unsafe impl Sync for RawDevice {}
+
+impl Drop for RawDevice {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ffi::fido_dev_close(self.ptr.as_ptr());
+            let mut raw = self.ptr.as_ptr();
+            ffi::fido_dev_free(&mut raw);
         }
     }
 }
@@ -137,31 +175,240 @@ impl DeviceCancel {
 /// A fido device.
 pub struct Device {
     pub(crate) ptr: NonNull<fido_dev_t>,
+
+    timeout: Option<Duration>,
+
+    raw: Arc<RawDevice>,
 }
 
+unsafe impl Send for Device {}
+
 impl Device {
     /// Open the device pointed to by `path`.
     ///
     /// If dev claims to be FIDO2, libfido2 will attempt to speak FIDO2 to dev.
     /// If that fails, libfido2 will fallback to U2F unless the FIDO_DISABLE_U2F_FALLBACK flag
     /// was set in fido_init(3).
+    ///
+    /// See [Device::builder] to set options before the device is opened.
     pub fn open(path: impl AsRef<str>) -> Result<Device> {
-        let path = CString::new(path.as_ref())?;
+        DeviceBuilder::new().open(path)
+    }
+
+    /// Open a device reached through a custom [Transport].
+    ///
+    /// The built-in HID, NFC and PC/SC backends of libfido2 are bypassed, and all I/O
+    /// goes through `transport`. The transport is dropped when the returned [Device] is dropped.
+    pub fn open_with_transport<T: Transport>(transport: T) -> Result<Device> {
+        DeviceBuilder::new().open_with_transport(transport)
+    }
+
+    /// Return a [DeviceBuilder] to open a device with options.
+    pub fn builder() -> DeviceBuilder {
+        DeviceBuilder::new()
+    }
+
+    /// Allocate a new unopened device with the options of `builder`, reached through `transport`
+    /// if any.
+    fn new(builder: &DeviceBuilder, transport: Option<TransportHandle>) -> Result<Device> {
         unsafe {
             let dev = ffi::fido_dev_new();
             assert!(!dev.is_null());
 
-            check(ffi::fido_dev_open(dev, path.as_ptr()))?;
+            // owns `dev` from here, so it is freed on error.
+            let ptr = NonNull::new_unchecked(dev);
+            let mut device = Device {
+                ptr,
+                timeout: None,
+                raw: Arc::new(RawDevice { ptr, transport }),
+            };
 
-            Ok(Device {
-                ptr: NonNull::new_unchecked(dev),
-            })
+            device.set_timeout(builder.timeout)?;
+
+            Ok(device)
+        }
+    }
+
+    /// Set the timeout of operations on the device.
+    ///
+    /// Blocking operations like [Device::make_credential] or [Device::get_assertion] give up if the
+    /// device does not answer within `timeout`, e.g. because the user did not touch it. They fail
+    /// with [Error::Timeout] if the device is reached through a custom [Transport] reporting the
+    /// timeout, and with a receive error, [FidoErrorKind::Rx](crate::error::FidoErrorKind::Rx), through the built-in backends of libfido2.
+    ///
+    /// A `timeout` of [None] means wait forever, which is the default.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        let ms = match timeout {
+            Some(timeout) => c_int::try_from(timeout.as_millis()).unwrap_or(c_int::MAX),
+            None => -1,
+        };
+
+        unsafe {
+            check(ffi::fido_dev_set_timeout(self.ptr.as_ptr(), ms))?;
+        }
+
+        self.timeout = timeout;
+
+        Ok(())
+    }
+
+    /// Return the timeout of operations on the device, [None] means wait forever.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Like [check], but report timeouts as [Error::Timeout].
+    ///
+    /// The authenticator reports a user action timeout with `FIDO_ERR_ACTION_TIMEOUT` or
+    /// `FIDO_ERR_USER_ACTION_TIMEOUT`. libfido2 reports an expired [Device::set_timeout] as a
+    /// receive error, `FIDO_ERR_RX`, which is only a timeout if a custom [Transport] reported one:
+    /// with the built-in backends it cannot be told apart from an I/O error, and is returned as is.
+    pub(crate) fn check_timeout(&self, code: c_int) -> Result<()> {
+        let timed_out = self
+            .raw
+            .transport
+            .as_ref()
+            .is_some_and(TransportHandle::take_timeout);
+
+        match code {
+            ffi::FIDO_ERR_ACTION_TIMEOUT | ffi::FIDO_ERR_USER_ACTION_TIMEOUT => Err(Error::Timeout),
+            ffi::FIDO_ERR_RX if timed_out => Err(Error::Timeout),
+            _ => Ok(check(code)?),
+        }
+    }
+
+    /// Open every device of `devices`, and return the first one touched by the user.
+    ///
+    /// Touch detection is started on all devices which can be opened, the others are skipped.
+    /// Once a device is touched, pending requests on the other devices are cancelled.
+    ///
+    /// Fails with [Error::Timeout] if no device is touched within `timeout`,
+    /// a `timeout` of [None] means wait forever.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use fido2_rs::device::{Device, DeviceList};
+    ///
+    /// fn main() -> anyhow::Result<()> {
+    ///     let mut devices = DeviceList::list_devices(8);
+    ///     println!("Touch the key to use...");
+    ///
+    ///     let dev = Device::select_by_touch(&mut devices, Some(Duration::from_secs(30)))?;
+    ///     dbg!(dev.ctap_protocol());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn select_by_touch<'a>(
+        devices: impl IntoIterator<Item = DeviceInfo<'a>>,
+        timeout: Option<Duration>,
+    ) -> Result<Device> {
+        let mut last_error = Error::Fido(FidoError::new(ffi::FIDO_ERR_NOTFOUND));
+        let mut opened = Vec::new();
+        for info in devices {
+            match info.open() {
+                Ok(dev) => opened.push(dev),
+                Err(e) => last_error = e,
+            }
+        }
+
+        Device::select_touched(opened, timeout, last_error)
+    }
+
+    /// Like [Device::select_by_touch], among devices already opened, e.g. with a custom [Transport].
+    pub fn select_opened_by_touch(
+        devices: impl IntoIterator<Item = Device>,
+        timeout: Option<Duration>,
+    ) -> Result<Device> {
+        let not_found = Error::Fido(FidoError::new(ffi::FIDO_ERR_NOTFOUND));
+
+        Device::select_touched(devices, timeout, not_found)
+    }
+
+    /// Return the first device of `devices` touched by the user, or `last_error` if there is none.
+    fn select_touched(
+        devices: impl IntoIterator<Item = Device>,
+        timeout: Option<Duration>,
+        mut last_error: Error,
+    ) -> Result<Device> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+        let mut pending = Vec::new();
+        for dev in devices {
+            match dev.touch_begin() {
+                Ok(()) => pending.push(dev),
+                Err(e) => last_error = e,
+            }
+        }
+
+        let start = Instant::now();
+        while !pending.is_empty() {
+            if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                last_error = Error::Timeout;
+                break;
+            }
+
+            let mut idx = 0;
+            while idx < pending.len() {
+                match pending[idx].touch_status(POLL_INTERVAL) {
+                    Ok(true) => {
+                        let dev = pending.swap_remove(idx);
+                        for other in &pending {
+                            other.cancel_handle().cancel();
+                        }
+
+                        return Ok(dev);
+                    }
+                    Ok(false) => idx += 1,
+                    Err(e) => {
+                        pending.swap_remove(idx);
+                        last_error = e;
+                    }
+                }
+            }
         }
+
+        for other in &pending {
+            other.cancel_handle().cancel();
+        }
+
+        Err(last_error)
+    }
+
+    /// Start a touch request on the device.
+    ///
+    /// See [Device::touch_status] to check whether the device was touched,
+    /// and [Device::select_by_touch] to select among multiple devices.
+    pub fn touch_begin(&self) -> Result<()> {
+        unsafe {
+            self.check_timeout(ffi::fido_dev_get_touch_begin(self.ptr.as_ptr()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for the device to be touched, after [Device::touch_begin].
+    ///
+    /// Return `true` if the device was touched.
+    pub fn touch_status(&self, timeout: Duration) -> Result<bool> {
+        let ms = c_int::try_from(timeout.as_millis()).unwrap_or(c_int::MAX);
+        let mut touched = 0;
+
+        unsafe {
+            self.check_timeout(ffi::fido_dev_get_touch_status(
+                self.ptr.as_ptr(),
+                &mut touched,
+                ms,
+            ))?;
+        }
+
+        Ok(touched != 0)
     }
 
     /// Get a handle of this device for cancel.
     pub fn cancel_handle(&self) -> DeviceCancel {
-        DeviceCancel(self.ptr)
+        DeviceCancel(self.raw.clone())
     }
 
     /// can be used to force CTAP2 communication with dev
@@ -248,7 +495,7 @@ impl Device {
         let info = CBORInfo::new();
 
         unsafe {
-            check(ffi::fido_dev_get_cbor_info(
+            self.check_timeout(ffi::fido_dev_get_cbor_info(
                 self.ptr.as_ptr(),
                 info.ptr.as_ptr(),
             ))?;
@@ -257,10 +504,36 @@ impl Device {
         Ok(info)
     }
 
+    /// Return an owned snapshot of the device info, see [AuthenticatorInfo].
+    pub fn authenticator_info(&self) -> Result<AuthenticatorInfo> {
+        self.info().map(AuthenticatorInfo::from)
+    }
+
+    /// Return the PIN/UV auth protocol used with dev, or `None` if dev supports neither.
+    ///
+    /// libfido2 uses protocol two when the authenticator supports it and falls back to protocol
+    /// one otherwise, unless another protocol was chosen with [DeviceBuilder::pin_protocol].
+    pub fn pin_protocol(&self) -> Result<Option<PinProtocol>> {
+        Ok(PinProtocol::select(self.info()?.protocols()))
+    }
+
+    /// Check that the PIN/UV auth protocol used with dev is `protocol`.
+    ///
+    /// This returns [Error::Unsupported] if another protocol would be used, e.g. to make sure a
+    /// test actually runs with protocol two on a device opened through a built-in backend of
+    /// libfido2, where the protocol cannot be chosen.
+    pub fn require_pin_protocol(&self, protocol: PinProtocol) -> Result<()> {
+        if self.pin_protocol()? != Some(protocol) {
+            return Err(Error::Unsupported);
+        }
+
+        Ok(())
+    }
+
     pub fn get_retry_count(&self) -> Result<i32> {
         let mut res = 0;
         unsafe {
-            check(ffi::fido_dev_get_retry_count(
+            self.check_timeout(ffi::fido_dev_get_retry_count(
                 self.ptr.as_ptr(),
                 &mut res as *mut i32,
             ))?;
@@ -271,7 +544,7 @@ impl Device {
     pub fn get_uv_retry_count(&self) -> Result<i32> {
         let mut res = 0;
         unsafe {
-            check(ffi::fido_dev_get_uv_retry_count(
+            self.check_timeout(ffi::fido_dev_get_uv_retry_count(
                 self.ptr.as_ptr(),
                 &mut res as *mut i32,
             ))?;
@@ -321,7 +594,7 @@ impl Device {
         };
 
         unsafe {
-            check(ffi::fido_dev_make_cred(
+            self.check_timeout(ffi::fido_dev_make_cred(
                 self.ptr.as_ptr(),
                 credential.0.as_ptr(),
                 pin_ptr,
@@ -369,7 +642,7 @@ impl Device {
         };
 
         unsafe {
-            check(ffi::fido_dev_get_assert(
+            self.check_timeout(ffi::fido_dev_get_assert(
                 self.ptr.as_ptr(),
                 request.0.ptr.as_ptr(),
                 pin_ptr,
@@ -396,7 +669,7 @@ impl Device {
         let pin_ptr = pin.as_ptr();
 
         unsafe {
-            check(ffi::fido_credman_get_dev_metadata(
+            self.check_timeout(ffi::fido_credman_get_dev_metadata(
                 self.ptr.as_ptr(),
                 ptr,
                 pin_ptr,
@@ -404,11 +677,56 @@ impl Device {
         }
 
         let ptr = unsafe { NonNull::new_unchecked(ptr) };
-        let credman = CredentialManagement::new(ptr, &self, Zeroizing::new(pin));
+        let credman = CredentialManagement::new(ptr, self, Zeroizing::new(pin));
 
         Ok(credman)
     }
 
+    /// Obtain a handle to the biometric enrollment interface of a FIDO2 device.
+    ///
+    /// The sensor information is read from the device first. If the device does not report the
+    /// `bioEnroll` or `userVerificationMgmtPreview` option, [Error::Unsupported] will be returned.
+    ///
+    /// **Pin will be kept in memory and zeroized securely when the returned BioEnrollment is dropped.**
+    pub fn bio(&self, pin: &str) -> Result<BioEnrollment<'_>> {
+        let options = self.authenticator_info()?.options;
+        if !options.supports_bio_enroll() {
+            return Err(Error::Unsupported);
+        }
+
+        let info = BioInfo::new();
+
+        unsafe {
+            self.check_timeout(ffi::fido_bio_dev_get_info(
+                self.ptr.as_ptr(),
+                info.ptr.as_ptr(),
+            ))?;
+        }
+
+        let pin = CString::new(pin)?;
+        let bio = BioEnrollment::new(self, info, Zeroizing::new(pin));
+
+        Ok(bio)
+    }
+
+    /// Obtain a handle to the configuration interface of a FIDO2 device.
+    ///
+    /// If the device has a PIN set, a valid pin must be provided. If the device does not support
+    /// the CTAP 2.1 authenticatorConfig command, [Error::Unsupported] will be returned.
+    ///
+    /// **Pin will be kept in memory and zeroized securely when the returned AuthenticatorConfig is dropped.**
+    pub fn config(&self, pin: Option<&str>) -> Result<AuthenticatorConfig<'_>> {
+        let info = self.authenticator_info()?;
+        if !info.options.supports_authnr_cfg() {
+            return Err(Error::Unsupported);
+        }
+
+        let pin = pin.map(CString::new).transpose()?.map(Zeroizing::new);
+        let config = AuthenticatorConfig::new(self, pin);
+
+        Ok(config)
+    }
+
     /// Set or change the FIDO2 device PIN.
     ///
     /// If `old_pin` is `None`, this sets the initial PIN on a device that has no
@@ -425,7 +743,7 @@ impl Device {
         };
 
         unsafe {
-            check(ffi::fido_dev_set_pin(
+            self.check_timeout(ffi::fido_dev_set_pin(
                 self.ptr.as_ptr(),
                 new_pin.as_ptr(),
                 old_pin_ptr,
@@ -447,7 +765,7 @@ impl Device {
     /// **Please note that `fido_dev_reset()` is synchronous and will block if necessary.**
     pub fn reset(&self) -> Result<()> {
         unsafe {
-            check(ffi::fido_dev_reset(self.ptr.as_ptr()))?;
+            self.check_timeout(ffi::fido_dev_reset(self.ptr.as_ptr()))?;
         }
         Ok(())
     }
@@ -468,7 +786,7 @@ impl Device {
         let mut data_len: usize = 0;
 
         unsafe {
-            check(ffi::fido_dev_largeblob_get(
+            self.check_timeout(ffi::fido_dev_largeblob_get(
                 self.ptr.as_ptr(),
                 key.as_ptr(),
                 key.len(),
@@ -500,7 +818,7 @@ impl Device {
         let pin = CString::new(pin)?;
 
         unsafe {
-            check(ffi::fido_dev_largeblob_set(
+            self.check_timeout(ffi::fido_dev_largeblob_set(
                 self.ptr.as_ptr(),
                 key.as_ptr(),
                 key.len(),
@@ -524,7 +842,7 @@ impl Device {
         let pin = CString::new(pin)?;
 
         unsafe {
-            check(ffi::fido_dev_largeblob_remove(
+            self.check_timeout(ffi::fido_dev_largeblob_remove(
                 self.ptr.as_ptr(),
                 key.as_ptr(),
                 key.len(),
@@ -548,7 +866,7 @@ impl Device {
         let mut data_len: usize = 0;
 
         unsafe {
-            check(ffi::fido_dev_largeblob_get_array(
+            self.check_timeout(ffi::fido_dev_largeblob_get_array(
                 self.ptr.as_ptr(),
                 &mut data_ptr,
                 &mut data_len,
@@ -573,10 +891,15 @@ impl Device {
     ///
     /// **Please note that `fido_dev_largeblob_set_array()` is synchronous and will block if necessary.**
     pub fn largeblob_set_array(&self, data: &[u8], pin: &str) -> Result<()> {
-        let pin = CString::new(pin)?;
+        let pin = Zeroizing::new(CString::new(pin)?);
 
+        self.largeblob_set_array_with(data, &pin)
+    }
+
+    /// Like [Device::largeblob_set_array], with a PIN already converted, e.g. kept zeroized.
+    pub(crate) fn largeblob_set_array_with(&self, data: &[u8], pin: &CStr) -> Result<()> {
         unsafe {
-            check(ffi::fido_dev_largeblob_set_array(
+            self.check_timeout(ffi::fido_dev_largeblob_set_array(
                 self.ptr.as_ptr(),
                 data.as_ptr(),
                 data.len(),
@@ -588,13 +911,94 @@ impl Device {
     }
 }
 
-impl Drop for Device {
-    fn drop(&mut self) {
+/// Builder to open a [Device] with options.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use fido2_rs::device::Device;
+///
+/// fn main() -> anyhow::Result<()> {
+///     let dev = Device::builder()
+///         .timeout(Duration::from_secs(30))
+///         .open("/dev/hidraw0")?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DeviceBuilder {
+    timeout: Option<Duration>,
+    pin_protocol: Option<PinProtocol>,
+}
+
+impl DeviceBuilder {
+    /// Return a [DeviceBuilder] with default options.
+    pub fn new() -> DeviceBuilder {
+        DeviceBuilder::default()
+    }
+
+    /// Set the timeout of operations on the device, including open, see [Device::set_timeout].
+    pub fn timeout(mut self, timeout: Duration) -> DeviceBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Use the PIN/UV auth protocol `protocol` with the device, see [Device::pin_protocol].
+    ///
+    /// Only the authenticator info of a device reached through a [Transport] exchanging whole
+    /// messages, see [Transport::MESSAGES], can be restricted to `protocol`. Opening any other
+    /// device, or a device not supporting `protocol`, fails with [Error::Unsupported].
+    pub fn pin_protocol(mut self, protocol: PinProtocol) -> DeviceBuilder {
+        self.pin_protocol = Some(protocol);
+        self
+    }
+
+    /// Open the device pointed to by `path`, see [Device::open].
+    pub fn open(&self, path: impl AsRef<str>) -> Result<Device> {
+        if self.pin_protocol.is_some() {
+            return Err(Error::Unsupported);
+        }
+
+        let path = CString::new(path.as_ref())?;
+        let device = Device::new(self, None)?;
+
         unsafe {
-            let _ = ffi::fido_dev_close(self.ptr.as_ptr());
-            let mut raw = self.ptr.as_ptr();
-            ffi::fido_dev_free(&mut raw);
+            device.check_timeout(ffi::fido_dev_open(device.ptr.as_ptr(), path.as_ptr()))?;
         }
+
+        Ok(device)
+    }
+
+    /// Open a device reached through a custom [Transport], see [Device::open_with_transport].
+    pub fn open_with_transport<T: Transport>(&self, transport: T) -> Result<Device> {
+        if self.pin_protocol.is_some() && !T::MESSAGES {
+            return Err(Error::Unsupported);
+        }
+
+        let transport = TransportHandle::new(transport, self.pin_protocol.map(|it| it as u8));
+        let path = CString::new(transport.path())?;
+
+        let device = Device::new(self, Some(transport))?;
+        let dev = device.ptr.as_ptr();
+
+        unsafe {
+            let io = transport::io_functions::<T>();
+            check(ffi::fido_dev_set_io_functions(dev, &io))?;
+
+            if T::MESSAGES {
+                let functions = transport::transport_functions::<T>();
+                check(ffi::fido_dev_set_transport_functions(dev, &functions))?;
+            }
+
+            device.check_timeout(ffi::fido_dev_open(dev, path.as_ptr()))?;
+        }
+
+        if let Some(protocol) = self.pin_protocol {
+            device.require_pin_protocol(protocol)?;
+        }
+
+        Ok(device)
     }
 }
 
@@ -623,3 +1027,63 @@ pub struct CTAPHIDInfo {
     /// CTAPHID minor version number of dev.
     pub minor: u8,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::thread;
+
+    use super::*;
+    use crate::error::FidoErrorKind;
+
+    /// A transport to a device which never answers.
+    struct Silent;
+
+    impl Transport for Silent {
+        fn read(&self, _buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+            thread::sleep(timeout.expect("read without timeout"));
+            Err(io::ErrorKind::TimedOut.into())
+        }
+
+        fn write(&self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+    }
+
+    /// A transport to a device which was unplugged.
+    struct Unplugged;
+
+    impl Transport for Unplugged {
+        fn read(&self, _buf: &mut [u8], _timeout: Option<Duration>) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn write(&self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn builder_timeout() {
+        let timeout = Duration::from_millis(100);
+
+        let start = Instant::now();
+        let res = Device::builder()
+            .timeout(timeout)
+            .open_with_transport(Silent);
+        assert!(matches!(res, Err(Error::Timeout)));
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn receive_error_is_not_a_timeout() {
+        let res = Device::builder()
+            .timeout(Duration::from_millis(100))
+            .open_with_transport(Unplugged);
+
+        match res {
+            Err(Error::Fido(e)) => assert_eq!(e.kind(), FidoErrorKind::Rx),
+            _ => panic!("expected a receive error"),
+        }
+    }
+}
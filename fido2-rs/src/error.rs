@@ -17,6 +17,28 @@ pub enum Error {
 
     #[error("unsupported")]
     Unsupported,
+
+    #[error("operation timed out")]
+    Timeout,
+
+    #[error("malformed {0}")]
+    Malformed(&'static str),
+
+    #[error("verification failed: {0}")]
+    Verification(&'static str),
+
+    #[error("largeBlob array of {size} bytes exceeds the authenticator limit of {max} bytes")]
+    LargeBlobTooLarge { size: usize, max: usize },
+}
+
+impl Error {
+    /// Return the kind of the libfido2 error, if this is one.
+    pub fn fido_kind(&self) -> Option<FidoErrorKind> {
+        match self {
+            Error::Fido(e) => Some(e.kind()),
+            _ => None,
+        }
+    }
 }
 
 /// Error from libfido2
@@ -29,6 +51,317 @@ impl FidoError {
     pub(crate) const fn new(code: i32) -> FidoError {
         FidoError { code }
     }
+
+    /// Return the kind of this error.
+    pub fn kind(&self) -> FidoErrorKind {
+        FidoErrorKind::from(self.code)
+    }
+
+    /// See [FidoErrorKind::is_pin_error].
+    pub fn is_pin_error(&self) -> bool {
+        self.kind().is_pin_error()
+    }
+
+    /// See [FidoErrorKind::is_uv_error].
+    pub fn is_uv_error(&self) -> bool {
+        self.kind().is_uv_error()
+    }
+
+    /// See [FidoErrorKind::is_user_cancel].
+    pub fn is_user_cancel(&self) -> bool {
+        self.kind().is_user_cancel()
+    }
+}
+
+/// Kind of a [FidoError], one for each `FIDO_ERR_*` code of libfido2.
+///
+/// Codes unknown to this crate are reported as [FidoErrorKind::Unknown].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum FidoErrorKind {
+    /// The operation succeeded.
+    Success,
+    /// The command is not a valid CTAP command.
+    InvalidCommand,
+    /// The command included an invalid parameter.
+    InvalidParameter,
+    /// Invalid message or item length.
+    InvalidLength,
+    /// Invalid message sequencing.
+    InvalidSeq,
+    /// Message timed out.
+    Timeout,
+    /// Channel busy.
+    ChannelBusy,
+    /// Command requires channel lock.
+    LockRequired,
+    /// Command not allowed on this cid.
+    InvalidChannel,
+    /// Invalid or unexpected CBOR type.
+    CborUnexpectedType,
+    /// Error when parsing CBOR.
+    InvalidCbor,
+    /// Missing non-optional parameter.
+    MissingParameter,
+    /// Limit for number of items exceeded.
+    LimitExceeded,
+    /// Unsupported extension.
+    UnsupportedExtension,
+    /// Fingerprint database is full.
+    FpDatabaseFull,
+    /// Large blob storage is full.
+    LargeBlobStorageFull,
+    /// Valid credential found in the exclude list.
+    CredentialExcluded,
+    /// The authenticator is still processing a lengthy request.
+    Processing,
+    /// Credential not valid for the authenticator.
+    InvalidCredential,
+    /// Authentication is waiting for user interaction.
+    UserActionPending,
+    /// Another lengthy operation is already in progress on the authenticator.
+    OperationPending,
+    /// No request is pending.
+    NoOperations,
+    /// Authenticator does not support requested algorithm.
+    UnsupportedAlgorithm,
+    /// Not authorized for requested operation, e.g. the user declined.
+    OperationDenied,
+    /// Internal key storage is full.
+    KeyStoreFull,
+    /// Authenticator is not busy.
+    NotBusy,
+    /// No outstanding operations.
+    NoOperationPending,
+    /// Unsupported option.
+    UnsupportedOption,
+    /// Not a valid option for current operation.
+    InvalidOption,
+    /// Pending keep alive was cancelled.
+    KeepaliveCancel,
+    /// No valid credentials provided.
+    NoCredentials,
+    /// Timeout waiting for user interaction.
+    UserActionTimeout,
+    /// Continuation command, such as getNextAssertion, not allowed.
+    NotAllowed,
+    /// PIN invalid.
+    PinInvalid,
+    /// PIN blocked.
+    PinBlocked,
+    /// PIN authentication, pinUvAuthParam, verification failed.
+    PinAuthInvalid,
+    /// PIN authentication blocked, requires power recycle to reset.
+    PinAuthBlocked,
+    /// No PIN has been set.
+    PinNotSet,
+    /// PIN is required for the selected operation.
+    PinRequired,
+    /// PIN policy violation, e.g. the PIN is too short.
+    PinPolicyViolation,
+    /// pinUvAuthToken expired on authenticator.
+    PinTokenExpired,
+    /// Authenticator cannot handle this request due to memory constraints.
+    RequestTooLarge,
+    /// The current operation has timed out.
+    ActionTimeout,
+    /// User presence is required for the requested operation.
+    UpRequired,
+    /// Built-in user verification is disabled.
+    UvBlocked,
+    /// User verification failed.
+    UvInvalid,
+    /// The permissions parameter contains an unauthorized permission.
+    UnauthorizedPermission,
+    /// Other unspecified error.
+    Other,
+    /// Error sending data to the device.
+    Tx,
+    /// Error receiving data from the device.
+    Rx,
+    /// The device response is not CBOR.
+    RxNotCbor,
+    /// The device response is invalid CBOR.
+    RxInvalidCbor,
+    /// Invalid parameter in the device response.
+    InvalidParam,
+    /// Signature verification failed.
+    InvalidSignature,
+    /// Invalid argument passed to libfido2.
+    InvalidArgument,
+    /// User presence is required.
+    UserPresenceRequired,
+    /// Internal libfido2 error.
+    Internal,
+    /// Not found.
+    NotFound,
+    /// Compression error.
+    Compress,
+    /// A code unknown to this crate.
+    Unknown(i32),
+}
+
+impl From<i32> for FidoErrorKind {
+    fn from(code: i32) -> Self {
+        match code {
+            ffi::FIDO_ERR_SUCCESS => FidoErrorKind::Success,
+            ffi::FIDO_ERR_INVALID_COMMAND => FidoErrorKind::InvalidCommand,
+            ffi::FIDO_ERR_INVALID_PARAMETER => FidoErrorKind::InvalidParameter,
+            ffi::FIDO_ERR_INVALID_LENGTH => FidoErrorKind::InvalidLength,
+            ffi::FIDO_ERR_INVALID_SEQ => FidoErrorKind::InvalidSeq,
+            ffi::FIDO_ERR_TIMEOUT => FidoErrorKind::Timeout,
+            ffi::FIDO_ERR_CHANNEL_BUSY => FidoErrorKind::ChannelBusy,
+            ffi::FIDO_ERR_LOCK_REQUIRED => FidoErrorKind::LockRequired,
+            ffi::FIDO_ERR_INVALID_CHANNEL => FidoErrorKind::InvalidChannel,
+            ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE => FidoErrorKind::CborUnexpectedType,
+            ffi::FIDO_ERR_INVALID_CBOR => FidoErrorKind::InvalidCbor,
+            ffi::FIDO_ERR_MISSING_PARAMETER => FidoErrorKind::MissingParameter,
+            ffi::FIDO_ERR_LIMIT_EXCEEDED => FidoErrorKind::LimitExceeded,
+            ffi::FIDO_ERR_UNSUPPORTED_EXTENSION => FidoErrorKind::UnsupportedExtension,
+            ffi::FIDO_ERR_FP_DATABASE_FULL => FidoErrorKind::FpDatabaseFull,
+            ffi::FIDO_ERR_LARGEBLOB_STORAGE_FULL => FidoErrorKind::LargeBlobStorageFull,
+            ffi::FIDO_ERR_CREDENTIAL_EXCLUDED => FidoErrorKind::CredentialExcluded,
+            ffi::FIDO_ERR_PROCESSING => FidoErrorKind::Processing,
+            ffi::FIDO_ERR_INVALID_CREDENTIAL => FidoErrorKind::InvalidCredential,
+            ffi::FIDO_ERR_USER_ACTION_PENDING => FidoErrorKind::UserActionPending,
+            ffi::FIDO_ERR_OPERATION_PENDING => FidoErrorKind::OperationPending,
+            ffi::FIDO_ERR_NO_OPERATIONS => FidoErrorKind::NoOperations,
+            ffi::FIDO_ERR_UNSUPPORTED_ALGORITHM => FidoErrorKind::UnsupportedAlgorithm,
+            ffi::FIDO_ERR_OPERATION_DENIED => FidoErrorKind::OperationDenied,
+            ffi::FIDO_ERR_KEY_STORE_FULL => FidoErrorKind::KeyStoreFull,
+            ffi::FIDO_ERR_NOT_BUSY => FidoErrorKind::NotBusy,
+            ffi::FIDO_ERR_NO_OPERATION_PENDING => FidoErrorKind::NoOperationPending,
+            ffi::FIDO_ERR_UNSUPPORTED_OPTION => FidoErrorKind::UnsupportedOption,
+            ffi::FIDO_ERR_INVALID_OPTION => FidoErrorKind::InvalidOption,
+            ffi::FIDO_ERR_KEEPALIVE_CANCEL => FidoErrorKind::KeepaliveCancel,
+            ffi::FIDO_ERR_NO_CREDENTIALS => FidoErrorKind::NoCredentials,
+            ffi::FIDO_ERR_USER_ACTION_TIMEOUT => FidoErrorKind::UserActionTimeout,
+            ffi::FIDO_ERR_NOT_ALLOWED => FidoErrorKind::NotAllowed,
+            ffi::FIDO_ERR_PIN_INVALID => FidoErrorKind::PinInvalid,
+            ffi::FIDO_ERR_PIN_BLOCKED => FidoErrorKind::PinBlocked,
+            ffi::FIDO_ERR_PIN_AUTH_INVALID => FidoErrorKind::PinAuthInvalid,
+            ffi::FIDO_ERR_PIN_AUTH_BLOCKED => FidoErrorKind::PinAuthBlocked,
+            ffi::FIDO_ERR_PIN_NOT_SET => FidoErrorKind::PinNotSet,
+            ffi::FIDO_ERR_PIN_REQUIRED => FidoErrorKind::PinRequired,
+            ffi::FIDO_ERR_PIN_POLICY_VIOLATION => FidoErrorKind::PinPolicyViolation,
+            ffi::FIDO_ERR_PIN_TOKEN_EXPIRED => FidoErrorKind::PinTokenExpired,
+            ffi::FIDO_ERR_REQUEST_TOO_LARGE => FidoErrorKind::RequestTooLarge,
+            ffi::FIDO_ERR_ACTION_TIMEOUT => FidoErrorKind::ActionTimeout,
+            ffi::FIDO_ERR_UP_REQUIRED => FidoErrorKind::UpRequired,
+            ffi::FIDO_ERR_UV_BLOCKED => FidoErrorKind::UvBlocked,
+            ffi::FIDO_ERR_UV_INVALID => FidoErrorKind::UvInvalid,
+            ffi::FIDO_ERR_UNAUTHORIZED_PERM => FidoErrorKind::UnauthorizedPermission,
+            ffi::FIDO_ERR_ERR_OTHER => FidoErrorKind::Other,
+            ffi::FIDO_ERR_TX => FidoErrorKind::Tx,
+            ffi::FIDO_ERR_RX => FidoErrorKind::Rx,
+            ffi::FIDO_ERR_RX_NOT_CBOR => FidoErrorKind::RxNotCbor,
+            ffi::FIDO_ERR_RX_INVALID_CBOR => FidoErrorKind::RxInvalidCbor,
+            ffi::FIDO_ERR_INVALID_PARAM => FidoErrorKind::InvalidParam,
+            ffi::FIDO_ERR_INVALID_SIG => FidoErrorKind::InvalidSignature,
+            ffi::FIDO_ERR_INVALID_ARGUMENT => FidoErrorKind::InvalidArgument,
+            ffi::FIDO_ERR_USER_PRESENCE_REQUIRED => FidoErrorKind::UserPresenceRequired,
+            ffi::FIDO_ERR_INTERNAL => FidoErrorKind::Internal,
+            ffi::FIDO_ERR_NOTFOUND => FidoErrorKind::NotFound,
+            ffi::FIDO_ERR_COMPRESS => FidoErrorKind::Compress,
+            code => FidoErrorKind::Unknown(code),
+        }
+    }
+}
+
+impl From<FidoErrorKind> for i32 {
+    fn from(kind: FidoErrorKind) -> Self {
+        match kind {
+            FidoErrorKind::Success => ffi::FIDO_ERR_SUCCESS,
+            FidoErrorKind::InvalidCommand => ffi::FIDO_ERR_INVALID_COMMAND,
+            FidoErrorKind::InvalidParameter => ffi::FIDO_ERR_INVALID_PARAMETER,
+            FidoErrorKind::InvalidLength => ffi::FIDO_ERR_INVALID_LENGTH,
+            FidoErrorKind::InvalidSeq => ffi::FIDO_ERR_INVALID_SEQ,
+            FidoErrorKind::Timeout => ffi::FIDO_ERR_TIMEOUT,
+            FidoErrorKind::ChannelBusy => ffi::FIDO_ERR_CHANNEL_BUSY,
+            FidoErrorKind::LockRequired => ffi::FIDO_ERR_LOCK_REQUIRED,
+            FidoErrorKind::InvalidChannel => ffi::FIDO_ERR_INVALID_CHANNEL,
+            FidoErrorKind::CborUnexpectedType => ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE,
+            FidoErrorKind::InvalidCbor => ffi::FIDO_ERR_INVALID_CBOR,
+            FidoErrorKind::MissingParameter => ffi::FIDO_ERR_MISSING_PARAMETER,
+            FidoErrorKind::LimitExceeded => ffi::FIDO_ERR_LIMIT_EXCEEDED,
+            FidoErrorKind::UnsupportedExtension => ffi::FIDO_ERR_UNSUPPORTED_EXTENSION,
+            FidoErrorKind::FpDatabaseFull => ffi::FIDO_ERR_FP_DATABASE_FULL,
+            FidoErrorKind::LargeBlobStorageFull => ffi::FIDO_ERR_LARGEBLOB_STORAGE_FULL,
+            FidoErrorKind::CredentialExcluded => ffi::FIDO_ERR_CREDENTIAL_EXCLUDED,
+            FidoErrorKind::Processing => ffi::FIDO_ERR_PROCESSING,
+            FidoErrorKind::InvalidCredential => ffi::FIDO_ERR_INVALID_CREDENTIAL,
+            FidoErrorKind::UserActionPending => ffi::FIDO_ERR_USER_ACTION_PENDING,
+            FidoErrorKind::OperationPending => ffi::FIDO_ERR_OPERATION_PENDING,
+            FidoErrorKind::NoOperations => ffi::FIDO_ERR_NO_OPERATIONS,
+            FidoErrorKind::UnsupportedAlgorithm => ffi::FIDO_ERR_UNSUPPORTED_ALGORITHM,
+            FidoErrorKind::OperationDenied => ffi::FIDO_ERR_OPERATION_DENIED,
+            FidoErrorKind::KeyStoreFull => ffi::FIDO_ERR_KEY_STORE_FULL,
+            FidoErrorKind::NotBusy => ffi::FIDO_ERR_NOT_BUSY,
+            FidoErrorKind::NoOperationPending => ffi::FIDO_ERR_NO_OPERATION_PENDING,
+            FidoErrorKind::UnsupportedOption => ffi::FIDO_ERR_UNSUPPORTED_OPTION,
+            FidoErrorKind::InvalidOption => ffi::FIDO_ERR_INVALID_OPTION,
+            FidoErrorKind::KeepaliveCancel => ffi::FIDO_ERR_KEEPALIVE_CANCEL,
+            FidoErrorKind::NoCredentials => ffi::FIDO_ERR_NO_CREDENTIALS,
+            FidoErrorKind::UserActionTimeout => ffi::FIDO_ERR_USER_ACTION_TIMEOUT,
+            FidoErrorKind::NotAllowed => ffi::FIDO_ERR_NOT_ALLOWED,
+            FidoErrorKind::PinInvalid => ffi::FIDO_ERR_PIN_INVALID,
+            FidoErrorKind::PinBlocked => ffi::FIDO_ERR_PIN_BLOCKED,
+            FidoErrorKind::PinAuthInvalid => ffi::FIDO_ERR_PIN_AUTH_INVALID,
+            FidoErrorKind::PinAuthBlocked => ffi::FIDO_ERR_PIN_AUTH_BLOCKED,
+            FidoErrorKind::PinNotSet => ffi::FIDO_ERR_PIN_NOT_SET,
+            FidoErrorKind::PinRequired => ffi::FIDO_ERR_PIN_REQUIRED,
+            FidoErrorKind::PinPolicyViolation => ffi::FIDO_ERR_PIN_POLICY_VIOLATION,
+            FidoErrorKind::PinTokenExpired => ffi::FIDO_ERR_PIN_TOKEN_EXPIRED,
+            FidoErrorKind::RequestTooLarge => ffi::FIDO_ERR_REQUEST_TOO_LARGE,
+            FidoErrorKind::ActionTimeout => ffi::FIDO_ERR_ACTION_TIMEOUT,
+            FidoErrorKind::UpRequired => ffi::FIDO_ERR_UP_REQUIRED,
+            FidoErrorKind::UvBlocked => ffi::FIDO_ERR_UV_BLOCKED,
+            FidoErrorKind::UvInvalid => ffi::FIDO_ERR_UV_INVALID,
+            FidoErrorKind::UnauthorizedPermission => ffi::FIDO_ERR_UNAUTHORIZED_PERM,
+            FidoErrorKind::Other => ffi::FIDO_ERR_ERR_OTHER,
+            FidoErrorKind::Tx => ffi::FIDO_ERR_TX,
+            FidoErrorKind::Rx => ffi::FIDO_ERR_RX,
+            FidoErrorKind::RxNotCbor => ffi::FIDO_ERR_RX_NOT_CBOR,
+            FidoErrorKind::RxInvalidCbor => ffi::FIDO_ERR_RX_INVALID_CBOR,
+            FidoErrorKind::InvalidParam => ffi::FIDO_ERR_INVALID_PARAM,
+            FidoErrorKind::InvalidSignature => ffi::FIDO_ERR_INVALID_SIG,
+            FidoErrorKind::InvalidArgument => ffi::FIDO_ERR_INVALID_ARGUMENT,
+            FidoErrorKind::UserPresenceRequired => ffi::FIDO_ERR_USER_PRESENCE_REQUIRED,
+            FidoErrorKind::Internal => ffi::FIDO_ERR_INTERNAL,
+            FidoErrorKind::NotFound => ffi::FIDO_ERR_NOTFOUND,
+            FidoErrorKind::Compress => ffi::FIDO_ERR_COMPRESS,
+            FidoErrorKind::Unknown(code) => code,
+        }
+    }
+}
+
+impl FidoErrorKind {
+    /// Return true if the error is about the PIN, e.g. invalid, blocked, not set or required.
+    pub fn is_pin_error(&self) -> bool {
+        matches!(
+            self,
+            FidoErrorKind::PinInvalid
+                | FidoErrorKind::PinBlocked
+                | FidoErrorKind::PinAuthInvalid
+                | FidoErrorKind::PinAuthBlocked
+                | FidoErrorKind::PinNotSet
+                | FidoErrorKind::PinRequired
+                | FidoErrorKind::PinPolicyViolation
+                | FidoErrorKind::PinTokenExpired
+        )
+    }
+
+    /// Return true if the error is about built-in user verification, e.g. fingerprint.
+    pub fn is_uv_error(&self) -> bool {
+        matches!(self, FidoErrorKind::UvBlocked | FidoErrorKind::UvInvalid)
+    }
+
+    /// Return true if the request was cancelled or declined, by the user or with [DeviceCancel::cancel](crate::device::DeviceCancel::cancel).
+    pub fn is_user_cancel(&self) -> bool {
+        matches!(
+            self,
+            FidoErrorKind::KeepaliveCancel | FidoErrorKind::OperationDenied
+        )
+    }
 }
 
 impl Debug for FidoError {
@@ -52,3 +385,168 @@ impl Display for FidoError {
 }
 
 impl std::error::Error for FidoError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [(i32, FidoErrorKind); 59] = [
+        (ffi::FIDO_ERR_SUCCESS, FidoErrorKind::Success),
+        (ffi::FIDO_ERR_INVALID_COMMAND, FidoErrorKind::InvalidCommand),
+        (
+            ffi::FIDO_ERR_INVALID_PARAMETER,
+            FidoErrorKind::InvalidParameter,
+        ),
+        (ffi::FIDO_ERR_INVALID_LENGTH, FidoErrorKind::InvalidLength),
+        (ffi::FIDO_ERR_INVALID_SEQ, FidoErrorKind::InvalidSeq),
+        (ffi::FIDO_ERR_TIMEOUT, FidoErrorKind::Timeout),
+        (ffi::FIDO_ERR_CHANNEL_BUSY, FidoErrorKind::ChannelBusy),
+        (ffi::FIDO_ERR_LOCK_REQUIRED, FidoErrorKind::LockRequired),
+        (ffi::FIDO_ERR_INVALID_CHANNEL, FidoErrorKind::InvalidChannel),
+        (
+            ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE,
+            FidoErrorKind::CborUnexpectedType,
+        ),
+        (ffi::FIDO_ERR_INVALID_CBOR, FidoErrorKind::InvalidCbor),
+        (
+            ffi::FIDO_ERR_MISSING_PARAMETER,
+            FidoErrorKind::MissingParameter,
+        ),
+        (ffi::FIDO_ERR_LIMIT_EXCEEDED, FidoErrorKind::LimitExceeded),
+        (
+            ffi::FIDO_ERR_UNSUPPORTED_EXTENSION,
+            FidoErrorKind::UnsupportedExtension,
+        ),
+        (
+            ffi::FIDO_ERR_FP_DATABASE_FULL,
+            FidoErrorKind::FpDatabaseFull,
+        ),
+        (
+            ffi::FIDO_ERR_LARGEBLOB_STORAGE_FULL,
+            FidoErrorKind::LargeBlobStorageFull,
+        ),
+        (
+            ffi::FIDO_ERR_CREDENTIAL_EXCLUDED,
+            FidoErrorKind::CredentialExcluded,
+        ),
+        (ffi::FIDO_ERR_PROCESSING, FidoErrorKind::Processing),
+        (
+            ffi::FIDO_ERR_INVALID_CREDENTIAL,
+            FidoErrorKind::InvalidCredential,
+        ),
+        (
+            ffi::FIDO_ERR_USER_ACTION_PENDING,
+            FidoErrorKind::UserActionPending,
+        ),
+        (
+            ffi::FIDO_ERR_OPERATION_PENDING,
+            FidoErrorKind::OperationPending,
+        ),
+        (ffi::FIDO_ERR_NO_OPERATIONS, FidoErrorKind::NoOperations),
+        (
+            ffi::FIDO_ERR_UNSUPPORTED_ALGORITHM,
+            FidoErrorKind::UnsupportedAlgorithm,
+        ),
+        (
+            ffi::FIDO_ERR_OPERATION_DENIED,
+            FidoErrorKind::OperationDenied,
+        ),
+        (ffi::FIDO_ERR_KEY_STORE_FULL, FidoErrorKind::KeyStoreFull),
+        (ffi::FIDO_ERR_NOT_BUSY, FidoErrorKind::NotBusy),
+        (
+            ffi::FIDO_ERR_NO_OPERATION_PENDING,
+            FidoErrorKind::NoOperationPending,
+        ),
+        (
+            ffi::FIDO_ERR_UNSUPPORTED_OPTION,
+            FidoErrorKind::UnsupportedOption,
+        ),
+        (ffi::FIDO_ERR_INVALID_OPTION, FidoErrorKind::InvalidOption),
+        (
+            ffi::FIDO_ERR_KEEPALIVE_CANCEL,
+            FidoErrorKind::KeepaliveCancel,
+        ),
+        (ffi::FIDO_ERR_NO_CREDENTIALS, FidoErrorKind::NoCredentials),
+        (
+            ffi::FIDO_ERR_USER_ACTION_TIMEOUT,
+            FidoErrorKind::UserActionTimeout,
+        ),
+        (ffi::FIDO_ERR_NOT_ALLOWED, FidoErrorKind::NotAllowed),
+        (ffi::FIDO_ERR_PIN_INVALID, FidoErrorKind::PinInvalid),
+        (ffi::FIDO_ERR_PIN_BLOCKED, FidoErrorKind::PinBlocked),
+        (
+            ffi::FIDO_ERR_PIN_AUTH_INVALID,
+            FidoErrorKind::PinAuthInvalid,
+        ),
+        (
+            ffi::FIDO_ERR_PIN_AUTH_BLOCKED,
+            FidoErrorKind::PinAuthBlocked,
+        ),
+        (ffi::FIDO_ERR_PIN_NOT_SET, FidoErrorKind::PinNotSet),
+        (ffi::FIDO_ERR_PIN_REQUIRED, FidoErrorKind::PinRequired),
+        (
+            ffi::FIDO_ERR_PIN_POLICY_VIOLATION,
+            FidoErrorKind::PinPolicyViolation,
+        ),
+        (
+            ffi::FIDO_ERR_PIN_TOKEN_EXPIRED,
+            FidoErrorKind::PinTokenExpired,
+        ),
+        (
+            ffi::FIDO_ERR_REQUEST_TOO_LARGE,
+            FidoErrorKind::RequestTooLarge,
+        ),
+        (ffi::FIDO_ERR_ACTION_TIMEOUT, FidoErrorKind::ActionTimeout),
+        (ffi::FIDO_ERR_UP_REQUIRED, FidoErrorKind::UpRequired),
+        (ffi::FIDO_ERR_UV_BLOCKED, FidoErrorKind::UvBlocked),
+        (ffi::FIDO_ERR_UV_INVALID, FidoErrorKind::UvInvalid),
+        (
+            ffi::FIDO_ERR_UNAUTHORIZED_PERM,
+            FidoErrorKind::UnauthorizedPermission,
+        ),
+        (ffi::FIDO_ERR_ERR_OTHER, FidoErrorKind::Other),
+        (ffi::FIDO_ERR_TX, FidoErrorKind::Tx),
+        (ffi::FIDO_ERR_RX, FidoErrorKind::Rx),
+        (ffi::FIDO_ERR_RX_NOT_CBOR, FidoErrorKind::RxNotCbor),
+        (ffi::FIDO_ERR_RX_INVALID_CBOR, FidoErrorKind::RxInvalidCbor),
+        (ffi::FIDO_ERR_INVALID_PARAM, FidoErrorKind::InvalidParam),
+        (ffi::FIDO_ERR_INVALID_SIG, FidoErrorKind::InvalidSignature),
+        (
+            ffi::FIDO_ERR_INVALID_ARGUMENT,
+            FidoErrorKind::InvalidArgument,
+        ),
+        (
+            ffi::FIDO_ERR_USER_PRESENCE_REQUIRED,
+            FidoErrorKind::UserPresenceRequired,
+        ),
+        (ffi::FIDO_ERR_INTERNAL, FidoErrorKind::Internal),
+        (ffi::FIDO_ERR_NOTFOUND, FidoErrorKind::NotFound),
+        (ffi::FIDO_ERR_COMPRESS, FidoErrorKind::Compress),
+    ];
+
+    #[test]
+    fn kind_from_code() {
+        for (code, kind) in KINDS {
+            assert_eq!(FidoErrorKind::from(code), kind, "code {code}");
+            assert_eq!(i32::from(kind), code);
+            assert_eq!(FidoError::new(code).kind(), kind);
+        }
+    }
+
+    #[test]
+    fn unknown_code() {
+        assert_eq!(FidoErrorKind::from(-1000), FidoErrorKind::Unknown(-1000));
+        assert_eq!(FidoErrorKind::from(0xff), FidoErrorKind::Unknown(0xff));
+        assert_eq!(i32::from(FidoErrorKind::Unknown(-1000)), -1000);
+    }
+
+    #[test]
+    fn error_classes() {
+        assert!(FidoErrorKind::PinBlocked.is_pin_error());
+        assert!(!FidoErrorKind::UvBlocked.is_pin_error());
+        assert!(FidoErrorKind::UvBlocked.is_uv_error());
+        assert!(FidoErrorKind::KeepaliveCancel.is_user_cancel());
+        assert!(FidoErrorKind::OperationDenied.is_user_cancel());
+        assert!(!FidoErrorKind::ActionTimeout.is_user_cancel());
+    }
+}
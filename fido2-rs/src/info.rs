@@ -0,0 +1,497 @@
+//! Owned authenticator information.
+//!
+//! [AuthenticatorInfo] is a snapshot of the authenticatorGetInfo response, which does not
+//! borrow from libfido2 and can be sent across threads or stored.
+//!
+//! With the `serde` feature, it can be serialized and deserialized.
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+
+use crate::cbor::CBORInfo;
+use crate::error::Error;
+
+/// Authenticator Attestation GUID, identifying the model of an authenticator.
+///
+/// Displayed and parsed in the hyphenated UUID form, e.g. `cb69481e-8ff7-4039-93ec-0a2729a154a8`.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Aaguid(pub [u8; 16]);
+
+impl Aaguid {
+    /// Return the raw bytes of this AAGUID.
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// Return true if all bytes are zero, as reported by authenticators without an AAGUID.
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 16]
+    }
+}
+
+impl From<[u8; 16]> for Aaguid {
+    fn from(value: [u8; 16]) -> Self {
+        Aaguid(value)
+    }
+}
+
+impl TryFrom<&[u8]> for Aaguid {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        value
+            .try_into()
+            .map(Aaguid)
+            .map_err(|_| Error::Malformed("AAGUID"))
+    }
+}
+
+impl Display for Aaguid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, byte) in self.0.iter().enumerate() {
+            if matches!(idx, 4 | 6 | 8 | 10) {
+                f.write_str("-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for Aaguid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Aaguid({})", self)
+    }
+}
+
+impl FromStr for Aaguid {
+    type Err = Error;
+
+    /// Parse an AAGUID from 32 hex digits, hyphens are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s
+            .chars()
+            .filter(|it| *it != '-')
+            .map(|it| it.to_digit(16).map(|it| it as u8))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::Malformed("AAGUID"))?;
+
+        if digits.len() != 32 {
+            return Err(Error::Malformed("AAGUID"));
+        }
+
+        let mut aaguid = [0; 16];
+        for (byte, pair) in aaguid.iter_mut().zip(digits.chunks(2)) {
+            *byte = pair[0] << 4 | pair[1];
+        }
+
+        Ok(Aaguid(aaguid))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Aaguid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Aaguid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A public key credential algorithm supported by the authenticator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Algorithm {
+    /// Credential type, e.g. `public-key`.
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub ty: String,
+    /// COSE algorithm identifier, e.g. `-7` for ES256.
+    pub alg: i32,
+}
+
+macro_rules! authenticator_options {
+    ($($(#[$doc:meta])* $field:ident => $name:literal,)*) => {
+        /// Options of the authenticator, as reported by authenticatorGetInfo.
+        ///
+        /// Each option is tri-state: [None] if the authenticator did not report it,
+        /// otherwise its value. The meaning of an absent option depends on the option,
+        /// see the predicates like [AuthenticatorOptions::supports_large_blobs].
+        #[derive(Clone, Debug, Default, Eq, PartialEq)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize),
+            serde(from = "BTreeMap<String, bool>", into = "BTreeMap<String, bool>")
+        )]
+        pub struct AuthenticatorOptions {
+            $(
+                $(#[$doc])*
+                #[doc = concat!("\n\nReported as `", $name, "`.")]
+                pub $field: Option<bool>,
+            )*
+            /// Options unknown to this crate.
+            pub other: BTreeMap<String, bool>,
+        }
+
+        impl AuthenticatorOptions {
+            /// Return the value of the option `name`, as reported by the authenticator.
+            pub fn get(&self, name: &str) -> Option<bool> {
+                match name {
+                    $($name => self.$field,)*
+                    _ => self.other.get(name).copied(),
+                }
+            }
+
+            /// Set the value of the option `name`.
+            pub fn set(&mut self, name: &str, value: bool) {
+                match name {
+                    $($name => self.$field = Some(value),)*
+                    _ => {
+                        self.other.insert(name.to_owned(), value);
+                    }
+                }
+            }
+
+            /// Return an iterator over the reported options and their value.
+            pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+                [$(($name, self.$field)),*]
+                    .into_iter()
+                    .filter_map(|(name, value)| value.map(|value| (name, value)))
+                    .chain(self.other.iter().map(|(name, value)| (name.as_str(), *value)))
+            }
+        }
+    };
+}
+
+authenticator_options! {
+    /// Whether the authenticator is attached to the client device and cannot be removed.
+    plat => "plat",
+    /// Whether the authenticator can store discoverable credentials.
+    rk => "rk",
+    /// Client PIN: absent if not supported, `false` if supported but not set, `true` if set.
+    client_pin => "clientPin",
+    /// Whether the authenticator can test user presence, `true` if absent.
+    up => "up",
+    /// Built-in user verification: absent if not supported, `false` if supported but not configured,
+    /// `true` if configured.
+    uv => "uv",
+    /// Whether the authenticator supports getPinUvAuthTokenUsingPinWithPermissions and
+    /// getPinUvAuthTokenUsingUvWithPermissions.
+    pin_uv_auth_token => "pinUvAuthToken",
+    /// Whether the authenticator rejects the makeCredential and getAssertion permissions with a PIN.
+    no_mc_ga_permissions_with_client_pin => "noMcGaPermissionsWithClientPin",
+    /// Whether the authenticator supports authenticatorLargeBlobs.
+    large_blobs => "largeBlobs",
+    /// Enterprise attestation: absent if not supported, `false` if disabled, `true` if enabled.
+    ep => "ep",
+    /// Biometric enrollment: absent if not supported, `false` if supported but nothing is enrolled,
+    /// `true` if at least one template is enrolled.
+    bio_enroll => "bioEnroll",
+    /// Prototype of `bioEnroll`, for CTAP 2.1 preview authenticators.
+    user_verification_mgmt_preview => "userVerificationMgmtPreview",
+    /// Whether a pinUvAuthToken from built-in user verification can be used for biometric enrollment.
+    uv_bio_enroll => "uvBioEnroll",
+    /// Whether the authenticator supports authenticatorConfig.
+    authnr_cfg => "authnrCfg",
+    /// Whether a pinUvAuthToken from built-in user verification can be used for authenticatorConfig.
+    uv_acfg => "uvAcfg",
+    /// Whether the authenticator supports authenticatorCredentialManagement.
+    cred_mgmt => "credMgmt",
+    /// Prototype of `credMgmt`, for CTAP 2.1 preview authenticators.
+    credential_mgmt_preview => "credentialMgmtPreview",
+    /// Whether the authenticator supports the setMinPINLength subcommand of authenticatorConfig.
+    set_min_pin_length => "setMinPINLength",
+    /// Whether the authenticator allows non-discoverable credentials to be made without user verification.
+    make_cred_uv_not_rqd => "makeCredUvNotRqd",
+    /// Always require user verification: absent if not supported, `false` if disabled, `true` if enabled.
+    always_uv => "alwaysUv",
+}
+
+impl AuthenticatorOptions {
+    /// Return true if the authenticator can store discoverable credentials.
+    pub fn supports_rk(&self) -> bool {
+        self.rk.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports a client PIN.
+    pub fn supports_client_pin(&self) -> bool {
+        self.client_pin.is_some()
+    }
+
+    /// Return true if a client PIN is set.
+    pub fn has_client_pin(&self) -> bool {
+        self.client_pin.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports built-in user verification.
+    pub fn supports_uv(&self) -> bool {
+        self.uv.is_some()
+    }
+
+    /// Return true if built-in user verification is configured.
+    pub fn has_uv(&self) -> bool {
+        self.uv.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports pinUvAuthToken permissions.
+    pub fn supports_pin_uv_auth_token(&self) -> bool {
+        self.pin_uv_auth_token.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports largeBlobs.
+    pub fn supports_large_blobs(&self) -> bool {
+        self.large_blobs.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports enterprise attestation.
+    pub fn supports_enterprise_attestation(&self) -> bool {
+        self.ep.is_some()
+    }
+
+    /// Return true if enterprise attestation is enabled.
+    pub fn enterprise_attestation_enabled(&self) -> bool {
+        self.ep.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports biometric enrollment, including the CTAP 2.1 preview.
+    pub fn supports_bio_enroll(&self) -> bool {
+        self.bio_enroll.is_some() || self.user_verification_mgmt_preview.is_some()
+    }
+
+    /// Return true if at least one biometric template is enrolled.
+    pub fn has_bio_enrollment(&self) -> bool {
+        self.bio_enroll
+            .or(self.user_verification_mgmt_preview)
+            .unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports authenticatorConfig.
+    pub fn supports_authnr_cfg(&self) -> bool {
+        self.authnr_cfg.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports credential management, including the CTAP 2.1 preview.
+    pub fn supports_cred_mgmt(&self) -> bool {
+        self.cred_mgmt.unwrap_or(false) || self.credential_mgmt_preview.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports setting the minimum PIN length.
+    pub fn supports_set_min_pin_length(&self) -> bool {
+        self.set_min_pin_length.unwrap_or(false)
+    }
+
+    /// Return true if non-discoverable credentials can be made without user verification.
+    pub fn make_cred_uv_not_required(&self) -> bool {
+        self.make_cred_uv_not_rqd.unwrap_or(false)
+    }
+
+    /// Return true if the authenticator supports alwaysUv.
+    pub fn supports_always_uv(&self) -> bool {
+        self.always_uv.is_some()
+    }
+
+    /// Return true if alwaysUv is enabled.
+    pub fn always_uv_enabled(&self) -> bool {
+        self.always_uv.unwrap_or(false)
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<(S, bool)> for AuthenticatorOptions {
+    fn from_iter<T: IntoIterator<Item = (S, bool)>>(iter: T) -> Self {
+        let mut options = AuthenticatorOptions::default();
+        for (name, value) in iter {
+            options.set(name.as_ref(), value);
+        }
+
+        options
+    }
+}
+
+impl From<BTreeMap<String, bool>> for AuthenticatorOptions {
+    fn from(value: BTreeMap<String, bool>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl From<AuthenticatorOptions> for BTreeMap<String, bool> {
+    fn from(value: AuthenticatorOptions) -> Self {
+        value
+            .iter()
+            .map(|(name, value)| (name.to_owned(), value))
+            .collect()
+    }
+}
+
+/// PIN/UV auth protocol.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum PinProtocol {
+    /// PIN/UV auth protocol one, CTAP 2.0.
+    V1 = 1,
+    /// PIN/UV auth protocol two, CTAP 2.1, using HKDF and AES-256-CBC with a random IV.
+    V2 = 2,
+}
+
+impl PinProtocol {
+    /// Return the protocol libfido2 selects among `protocols`: two if supported, otherwise one.
+    pub(crate) fn select(protocols: &[u8]) -> Option<PinProtocol> {
+        if protocols.contains(&(PinProtocol::V2 as u8)) {
+            Some(PinProtocol::V2)
+        } else if protocols.contains(&(PinProtocol::V1 as u8)) {
+            Some(PinProtocol::V1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Limits reported by the authenticator.
+///
+/// A value of `0` means the authenticator did not report it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Limits {
+    /// Maximum message size.
+    pub max_msg_size: u64,
+    /// Maximum number of credentials in an allow or exclude list.
+    pub max_cred_count_list: u64,
+    /// Maximum credential ID length.
+    pub max_cred_id_len: u64,
+    /// Maximum credBlob length.
+    pub max_cred_blob_len: u64,
+    /// Maximum size of the serialized largeBlob array.
+    pub max_large_blob: u64,
+    /// Maximum number of relying party IDs for the minPinLength extension.
+    pub max_rp_id_min_pin_len: u64,
+    /// Minimum PIN length.
+    pub min_pin_len: u64,
+    /// Number of built-in user verification attempts before falling back to PIN.
+    pub uv_attempts: u64,
+    /// Built-in user verification modality, as defined in the FIDO Registry of Predefined Values.
+    pub uv_modality: u64,
+    /// Estimated number of additional discoverable credentials that can be stored.
+    pub rk_remaining: Option<u64>,
+}
+
+/// Owned snapshot of the authenticatorGetInfo response.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuthenticatorInfo {
+    /// Supported versions, e.g. `FIDO_2_0`, `FIDO_2_1` or `U2F_V2`.
+    pub versions: Vec<String>,
+    /// Supported extensions, e.g. `hmac-secret` or `credProtect`.
+    pub extensions: Vec<String>,
+    /// Model of the authenticator, `None` if not reported.
+    pub aaguid: Option<Aaguid>,
+    /// Options of the authenticator.
+    pub options: AuthenticatorOptions,
+    /// Supported algorithms, in order of preference.
+    pub algorithms: Vec<Algorithm>,
+    /// Supported PIN/UV auth protocols, in order of preference.
+    pub pin_protocols: Vec<u8>,
+    /// Supported transports, e.g. `usb` or `nfc`.
+    pub transports: Vec<String>,
+    /// Limits of the authenticator.
+    pub limits: Limits,
+    /// Firmware version, `0` if not reported.
+    pub firmware_version: u64,
+    /// Certifications of the authenticator, e.g. `FIDO` with its level.
+    pub certifications: BTreeMap<String, u64>,
+    /// Whether the PIN must be changed before use.
+    pub force_pin_change: bool,
+}
+
+impl AuthenticatorInfo {
+    /// Return the PIN/UV auth protocol used with this authenticator, see [Device::pin_protocol](crate::device::Device::pin_protocol).
+    pub fn pin_protocol(&self) -> Option<PinProtocol> {
+        PinProtocol::select(&self.pin_protocols)
+    }
+}
+
+impl From<&CBORInfo> for AuthenticatorInfo {
+    fn from(info: &CBORInfo) -> Self {
+        let strings = |it: Vec<&str>| it.into_iter().map(str::to_owned).collect::<Vec<_>>();
+
+        let rk_remaining = info.rk_remaining();
+
+        AuthenticatorInfo {
+            versions: strings(info.versions()),
+            extensions: strings(info.extensions()),
+            aaguid: Aaguid::try_from(info.aaguid()).ok(),
+            options: info.options().into_iter().collect(),
+            algorithms: info
+                .algorithms()
+                .into_iter()
+                .map(|(ty, alg)| Algorithm {
+                    ty: ty.to_owned(),
+                    alg,
+                })
+                .collect(),
+            pin_protocols: info.protocols().to_vec(),
+            transports: strings(info.transports()),
+            limits: Limits {
+                max_msg_size: info.max_msg_size(),
+                max_cred_count_list: info.max_cred_count_list(),
+                max_cred_id_len: info.max_cred_id_len(),
+                max_cred_blob_len: info.max_cred_blob_len(),
+                max_large_blob: info.max_large_blob(),
+                max_rp_id_min_pin_len: info.max_rp_id_minpinlen(),
+                min_pin_len: info.min_pin_len(),
+                uv_attempts: info.uv_attempts(),
+                uv_modality: info.uv_modality(),
+                rk_remaining: u64::try_from(rk_remaining).ok(),
+            },
+            firmware_version: info.fw_version(),
+            certifications: info
+                .certs()
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v))
+                .collect(),
+            force_pin_change: info.new_pin_required(),
+        }
+    }
+}
+
+impl From<CBORInfo> for AuthenticatorInfo {
+    fn from(info: CBORInfo) -> Self {
+        AuthenticatorInfo::from(&info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aaguid_text() {
+        let aaguid: Aaguid = "cb69481e-8ff7-4039-93ec-0a2729a154a8".parse().unwrap();
+        assert_eq!(aaguid.0[..4], [0xcb, 0x69, 0x48, 0x1e]);
+        assert_eq!(aaguid.to_string(), "cb69481e-8ff7-4039-93ec-0a2729a154a8");
+        assert_eq!(
+            "CB69481E8FF7403993EC0A2729A154A8"
+                .parse::<Aaguid>()
+                .unwrap(),
+            aaguid
+        );
+
+        assert!(
+            "cb69481e-8ff7-4039-93ec-0a2729a154"
+                .parse::<Aaguid>()
+                .is_err()
+        );
+        assert!(
+            "zb69481e-8ff7-4039-93ec-0a2729a154a8"
+                .parse::<Aaguid>()
+                .is_err()
+        );
+        assert!(Aaguid::try_from(&[0u8; 15][..]).is_err());
+        assert!(Aaguid::default().is_zero());
+    }
+}
@@ -8,6 +8,8 @@ macro_rules! impl_key {
         type CType = $ctype:ty;
         fn new = $new:expr;
         $(fn from<$t:ty> = $from:expr;)*
+        fn from_ptr = $from_ptr:expr;
+        fn to_pkey = $to_pkey:expr;
         fn drop = $drop:expr;
 
         pub struct $ty:ident;
@@ -44,6 +46,29 @@ macro_rules! impl_key {
             pub(crate) fn as_ptr(&self) -> *const $ctype {
                 self.0.as_ptr()
             }
+
+            /// Load the key from its raw libfido2 layout, as returned by `fido_cred_pubkey_ptr`.
+            pub(crate) fn from_raw(data: &[u8]) -> Result<Self, FidoError> {
+                unsafe {
+                    let pk = $ty(NonNull::new_unchecked($new()));
+                    crate::utils::check($from_ptr(pk.0.as_ptr(), data.as_ptr().cast(), data.len()))?;
+
+                    Ok(pk)
+                }
+            }
+
+            pub(crate) fn to_pkey(&self) -> Result<PKey<Public>, FidoError> {
+                use foreign_types::ForeignType;
+
+                unsafe {
+                    let pkey = $to_pkey(self.as_ptr());
+                    if pkey.is_null() {
+                        return Err(FidoError::new(ffi::FIDO_ERR_INTERNAL));
+                    }
+
+                    Ok(PKey::from_ptr(pkey.cast()))
+                }
+            }
         }
     };
 }
@@ -52,6 +77,8 @@ impl_key! {
     type CType = ffi::eddsa_pk_t;
     fn new = ffi::eddsa_pk_new;
     fn from<PKey<Public>> = ffi::eddsa_pk_from_EVP_PKEY;
+    fn from_ptr = ffi::eddsa_pk_from_ptr;
+    fn to_pkey = ffi::eddsa_pk_to_EVP_PKEY;
     fn drop = ffi::eddsa_pk_free;
 
     pub struct Eddsa;
@@ -61,6 +88,8 @@ impl_key! {
     type CType = ffi::rs256_pk_t;
     fn new = ffi::rs256_pk_new;
     fn from<PKey<Public>> = ffi::rs256_pk_from_EVP_PKEY;
+    fn from_ptr = ffi::rs256_pk_from_ptr;
+    fn to_pkey = ffi::rs256_pk_to_EVP_PKEY;
     fn drop = ffi::rs256_pk_free;
 
     pub struct Rsa;
@@ -70,6 +99,8 @@ impl_key! {
     type CType = ffi::es256_pk_t;
     fn new = ffi::es256_pk_new;
     fn from<EcKey<Public>> = ffi::es256_pk_from_EC_KEY;
+    fn from_ptr = ffi::es256_pk_from_ptr;
+    fn to_pkey = ffi::es256_pk_to_EVP_PKEY;
     fn drop = ffi::es256_pk_free;
 
     pub struct ES256;
@@ -79,6 +110,8 @@ impl_key! {
     type CType = ffi::es384_pk_t;
     fn new = ffi::es384_pk_new;
     fn from<EcKey<Public>> = ffi::es384_pk_from_EC_KEY;
+    fn from_ptr = ffi::es384_pk_from_ptr;
+    fn to_pkey = ffi::es384_pk_to_EVP_PKEY;
     fn drop = ffi::es384_pk_free;
 
     pub struct ES384;
@@ -0,0 +1,845 @@
+//! largeBlob array parsing and editing.
+//!
+//! [Device::largeblob_set](crate::device::Device::largeblob_set) rewrites the whole array on the
+//! authenticator for each entry. [LargeBlobArray] decodes the array, so that entries can be
+//! listed and several of them updated in a single write.
+//! [CredentialManagement::prune_large_blobs](crate::credman::CredentialManagement::prune_large_blobs)
+//! removes the entries of deleted credentials.
+//!
+//! [LargeBlobStore] keeps named values in the entry of a single credential.
+//!
+//! Each entry is compressed with DEFLATE and encrypted with AES-256-GCM under the `largeBlobKey`
+//! of a credential, see
+//! [CTAP 2.1 §6.10.3](https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#large-blob).
+//!
+//! # Example
+//! ```rust,no_run
+//! use fido2_rs::device::Device;
+//! use fido2_rs::largeblob::LargeBlobArray;
+//!
+//! fn update(dev: &Device, keys: &[&[u8]], pin: &str) -> anyhow::Result<()> {
+//!     let mut array = LargeBlobArray::from_device(dev)?;
+//!     for key in keys {
+//!         array.set(key, b"new configuration")?;
+//!     }
+//!     array.write(dev, pin)?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::io::{Read, Write};
+
+use ciborium::Value;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use openssl::sha::sha256;
+use openssl::symm::{Cipher, decrypt_aead, encrypt_aead};
+use zeroize::Zeroizing;
+
+use crate::device::Device;
+use crate::error::{Error, Result};
+
+const MALFORMED: Error = Error::Malformed("largeBlob array");
+const COMPRESSION: Error = Error::Malformed("largeBlob compression");
+const STORE_MALFORMED: Error = Error::Malformed("largeBlob store");
+
+// store map keys
+const STORE_VERSION: u64 = 0;
+const STORE_VALUES: u64 = 1;
+
+/// Format version of [LargeBlobStore].
+const VERSION: u64 = 1;
+
+/// Length of a `largeBlobKey`.
+pub const KEY_LEN: usize = 32;
+/// Length of the AES-GCM nonce of an entry.
+pub const NONCE_LEN: usize = 12;
+/// Length of the AES-GCM authentication tag at the end of the ciphertext.
+const TAG_LEN: usize = 16;
+/// Length of the truncated SHA-256 digest following the serialized array.
+const DIGEST_LEN: usize = 16;
+
+// entry map keys
+const CIPHERTEXT: u64 = 1;
+const NONCE: u64 = 2;
+const ORIG_SIZE: u64 = 3;
+
+/// An encrypted entry of a [LargeBlobArray].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LargeBlobEntry {
+    /// AES-256-GCM ciphertext of the compressed data, followed by the authentication tag.
+    pub ciphertext: Vec<u8>,
+    /// AES-256-GCM nonce.
+    pub nonce: [u8; NONCE_LEN],
+    /// Length of the data before compression.
+    pub orig_size: u64,
+    /// The map this entry was parsed from, empty for a new entry.
+    original: Vec<(Value, Value)>,
+}
+
+impl LargeBlobEntry {
+    /// Compress and encrypt `data` with `key`, using a random nonce.
+    pub fn encrypt(key: &[u8], data: &[u8]) -> Result<LargeBlobEntry> {
+        check_key(key)?;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).map_err(|_| COMPRESSION)?;
+        let compressed = encoder.finish().map_err(|_| COMPRESSION)?;
+
+        let mut nonce = [0; NONCE_LEN];
+        openssl::rand::rand_bytes(&mut nonce)?;
+
+        let orig_size = data.len() as u64;
+        let mut tag = [0; TAG_LEN];
+        let mut ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            key,
+            Some(&nonce),
+            &associated_data(orig_size),
+            &compressed,
+            &mut tag,
+        )?;
+        ciphertext.extend_from_slice(&tag);
+
+        Ok(LargeBlobEntry {
+            ciphertext,
+            nonce,
+            orig_size,
+            original: Vec::new(),
+        })
+    }
+
+    /// Decrypt and decompress this entry with `key`.
+    ///
+    /// Return `None` if the entry was not encrypted with `key`.
+    pub fn decrypt(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        check_key(key)?;
+
+        let Some(compressed) = self.authenticate(key) else {
+            return Ok(None);
+        };
+
+        // never inflate more than announced
+        let mut data = Vec::new();
+        DeflateDecoder::new(compressed.as_slice())
+            .take(self.orig_size.saturating_add(1))
+            .read_to_end(&mut data)
+            .map_err(|_| COMPRESSION)?;
+        if data.len() as u64 != self.orig_size {
+            return Err(Error::Malformed("largeBlob size"));
+        }
+
+        Ok(Some(data))
+    }
+
+    /// Return true if this entry was encrypted with `key`.
+    ///
+    /// Only the AES-GCM authentication tag is checked, so an entry of `key` whose data cannot be
+    /// decompressed is still reported.
+    pub fn is_encrypted_with(&self, key: &[u8]) -> bool {
+        key.len() == KEY_LEN && self.authenticate(key).is_some()
+    }
+
+    /// Return the compressed data of this entry if it was encrypted with `key`.
+    fn authenticate(&self, key: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+        if self.ciphertext.len() < TAG_LEN {
+            return None;
+        }
+        let (ciphertext, tag) = self.ciphertext.split_at(self.ciphertext.len() - TAG_LEN);
+
+        decrypt_aead(
+            Cipher::aes_256_gcm(),
+            key,
+            Some(&self.nonce),
+            &associated_data(self.orig_size),
+            ciphertext,
+            tag,
+        )
+        .ok()
+        .map(Zeroizing::new)
+    }
+
+    fn from_value(value: &Value) -> Result<LargeBlobEntry> {
+        let map = value.as_map().ok_or(MALFORMED)?;
+        let field = |label: u64| {
+            map.iter()
+                .find(|(k, _)| k.as_integer() == Some(label.into()))
+                .map(|(_, v)| v)
+                .ok_or(MALFORMED)
+        };
+
+        let ciphertext = field(CIPHERTEXT)?.as_bytes().ok_or(MALFORMED)?;
+        if ciphertext.len() < TAG_LEN {
+            return Err(MALFORMED);
+        }
+
+        Ok(LargeBlobEntry {
+            ciphertext: ciphertext.clone(),
+            nonce: field(NONCE)?
+                .as_bytes()
+                .and_then(|it| it.as_slice().try_into().ok())
+                .ok_or(MALFORMED)?,
+            orig_size: field(ORIG_SIZE)?
+                .as_integer()
+                .and_then(|it| u64::try_from(it).ok())
+                .ok_or(MALFORMED)?,
+            original: map.clone(),
+        })
+    }
+
+    /// Encode this entry, keeping the order and the unknown keys of the map it was parsed from.
+    fn to_value(&self) -> Value {
+        let field = |label: u64| match label {
+            CIPHERTEXT => Some(Value::from(self.ciphertext.as_slice())),
+            NONCE => Some(Value::from(&self.nonce[..])),
+            ORIG_SIZE => Some(Value::from(self.orig_size)),
+            _ => None,
+        };
+        let label = |key: &Value| key.as_integer().and_then(|it| u64::try_from(it).ok());
+
+        let mut map = self
+            .original
+            .iter()
+            .map(|(k, v)| (k.clone(), label(k).and_then(field).unwrap_or(v.clone())))
+            .collect::<Vec<_>>();
+        for it in [CIPHERTEXT, NONCE, ORIG_SIZE] {
+            if !map.iter().any(|(k, _)| label(k) == Some(it)) {
+                map.push((Value::from(it), field(it).unwrap()));
+            }
+        }
+
+        Value::Map(map)
+    }
+}
+
+/// An element of a [LargeBlobArray].
+#[derive(Clone, Debug, PartialEq)]
+enum Item {
+    Entry(LargeBlobEntry),
+    /// An element which is not a valid entry, e.g. written by a buggy client, kept as is.
+    Invalid(Value),
+}
+
+impl Item {
+    fn entry(&self) -> Option<&LargeBlobEntry> {
+        match self {
+            Item::Entry(entry) => Some(entry),
+            Item::Invalid(_) => None,
+        }
+    }
+
+    fn is_encrypted_with(&self, key: &[u8]) -> bool {
+        self.entry().is_some_and(|it| it.is_encrypted_with(key))
+    }
+}
+
+/// The decoded largeBlob array of an authenticator.
+///
+/// Elements of the array which are not valid entries are never modified, and written back as
+/// they were read.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LargeBlobArray {
+    items: Vec<Item>,
+}
+
+impl LargeBlobArray {
+    /// Return an empty [LargeBlobArray].
+    pub fn new() -> LargeBlobArray {
+        LargeBlobArray::default()
+    }
+
+    /// Parse a largeBlob array.
+    ///
+    /// `data` is either the CBOR array returned by [Device::largeblob_get_array], or the
+    /// serialized array as stored on the authenticator, followed by the first 16 bytes of its
+    /// SHA-256 digest. The digest is verified if present.
+    pub fn parse(data: &[u8]) -> Result<LargeBlobArray> {
+        let mut rest = data;
+        let array: Value = ciborium::de::from_reader(&mut rest).map_err(|_| MALFORMED)?;
+        let array_len = data.len() - rest.len();
+
+        match rest.len() {
+            0 => {}
+            DIGEST_LEN => {
+                let digest = sha256(&data[..array_len]);
+                if !openssl::memcmp::eq(&digest[..DIGEST_LEN], rest) {
+                    return Err(Error::Malformed("largeBlob array digest"));
+                }
+            }
+            _ => return Err(MALFORMED),
+        }
+
+        let items = array
+            .into_array()
+            .map_err(|_| MALFORMED)?
+            .into_iter()
+            .map(|it| match LargeBlobEntry::from_value(&it) {
+                Ok(entry) => Item::Entry(entry),
+                Err(_) => Item::Invalid(it),
+            })
+            .collect();
+
+        Ok(LargeBlobArray { items })
+    }
+
+    /// Read and parse the largeBlob array of `dev`.
+    pub fn from_device(dev: &Device) -> Result<LargeBlobArray> {
+        let data = dev.largeblob_get_array()?;
+        if data.is_empty() {
+            return Ok(LargeBlobArray::new());
+        }
+
+        LargeBlobArray::parse(&data)
+    }
+
+    /// Encode this array as a CBOR array, as passed to [Device::largeblob_set_array].
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let array = Value::Array(
+            self.items
+                .iter()
+                .map(|it| match it {
+                    Item::Entry(entry) => entry.to_value(),
+                    Item::Invalid(value) => value.clone(),
+                })
+                .collect(),
+        );
+
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&array, &mut data).expect("encode largeBlob array");
+
+        data
+    }
+
+    /// Serialize this array as stored on the authenticator, followed by its truncated digest.
+    ///
+    /// The length of the result is the one to compare with the `maxSerializedLargeBlobArray` limit
+    /// of the authenticator.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = self.to_cbor();
+        let digest = sha256(&data);
+        data.extend_from_slice(&digest[..DIGEST_LEN]);
+
+        data
+    }
+
+    /// Replace the largeBlob array of `dev` with this array.
+    ///
+    /// PIN is required for write operations.
+    pub fn write(&self, dev: &Device, pin: &str) -> Result<()> {
+        dev.largeblob_set_array(&self.to_cbor(), pin)
+    }
+
+    /// Like [LargeBlobArray::write], with a PIN already converted, e.g. kept zeroized.
+    pub(crate) fn write_with(&self, dev: &Device, pin: &CStr) -> Result<()> {
+        dev.largeblob_set_array_with(&self.to_cbor(), pin)
+    }
+
+    /// Return the valid entries of this array.
+    pub fn entries(&self) -> impl Iterator<Item = &LargeBlobEntry> {
+        self.items.iter().filter_map(Item::entry)
+    }
+
+    /// Return the number of elements, including the invalid ones.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Return true if this array has no element.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Return the data of the first entry encrypted with `key`.
+    ///
+    /// Return an error if that entry cannot be decompressed, see [LargeBlobEntry::decrypt].
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        check_key(key)?;
+
+        for entry in self.entries() {
+            if let Some(data) = entry.decrypt(key)? {
+                return Ok(Some(data));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Store `data` encrypted with `key`, replacing the entries of `key` if any.
+    pub fn set(&mut self, key: &[u8], data: &[u8]) -> Result<()> {
+        let mut entry = Some(Item::Entry(LargeBlobEntry::encrypt(key, data)?));
+
+        // the new entry takes the place of the first old one, the others are dropped
+        let mut items = Vec::with_capacity(self.items.len() + 1);
+        for it in self.items.drain(..) {
+            if !it.is_encrypted_with(key) {
+                items.push(it);
+            } else if let Some(entry) = entry.take() {
+                items.push(entry);
+            }
+        }
+        items.extend(entry);
+        self.items = items;
+
+        Ok(())
+    }
+
+    /// Remove the entries encrypted with `key`, return true if any was removed.
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        let len = self.items.len();
+        self.items.retain(|it| !it.is_encrypted_with(key));
+
+        self.items.len() != len
+    }
+
+    /// Keep only the valid entries for which `f` returns true, and the invalid ones.
+    pub fn retain(&mut self, mut f: impl FnMut(&LargeBlobEntry) -> bool) {
+        self.items.retain(|it| it.entry().is_none_or(&mut f));
+    }
+}
+
+/// Named values stored in the largeBlob entry of a credential.
+///
+/// The entry holds a CBOR map with two keys:
+///
+/// * `0`: the format version, `1`.
+/// * `1`: a map of text names to byte string values, sorted by name.
+///
+/// The entry itself is compressed, see [LargeBlobEntry]. An entry with another version, or which
+/// is not such a map, is not overwritten unless [LargeBlobStore::new] is used explicitly.
+///
+/// # Example
+/// ```rust,no_run
+/// use fido2_rs::device::Device;
+/// use fido2_rs::largeblob::LargeBlobStore;
+///
+/// fn add_certificate(dev: &Device, key: &[u8], cert: &[u8], pin: &str) -> anyhow::Result<()> {
+///     let mut store = LargeBlobStore::load(dev, key)?;
+///     store.put("ssh-cert", cert);
+///     store.save(dev, pin)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub struct LargeBlobStore {
+    key: Zeroizing<Vec<u8>>,
+    values: BTreeMap<String, Vec<u8>>,
+}
+
+impl LargeBlobStore {
+    /// Return an empty [LargeBlobStore] for the credential with the largeBlobKey `key`.
+    pub fn new(key: &[u8]) -> Result<LargeBlobStore> {
+        check_key(key)?;
+
+        Ok(LargeBlobStore {
+            key: Zeroizing::new(key.to_vec()),
+            values: BTreeMap::new(),
+        })
+    }
+
+    /// Return the store of `key` in `array`, empty if `array` has no entry for `key`.
+    ///
+    /// Return [Error::Unsupported] if the entry has another format version, and an error if it
+    /// cannot be decompressed or decoded.
+    pub fn from_array(array: &LargeBlobArray, key: &[u8]) -> Result<LargeBlobStore> {
+        let mut store = LargeBlobStore::new(key)?;
+        let Some(data) = array.get(key)? else {
+            return Ok(store);
+        };
+
+        let value: Value =
+            ciborium::de::from_reader(data.as_slice()).map_err(|_| STORE_MALFORMED)?;
+        let map = value.into_map().map_err(|_| STORE_MALFORMED)?;
+        let field = |label: u64| {
+            map.iter()
+                .find(|(k, _)| k.as_integer() == Some(label.into()))
+                .map(|(_, v)| v)
+                .ok_or(STORE_MALFORMED)
+        };
+
+        if field(STORE_VERSION)?.as_integer() != Some(VERSION.into()) {
+            return Err(Error::Unsupported);
+        }
+        for (name, value) in field(STORE_VALUES)?.as_map().ok_or(STORE_MALFORMED)? {
+            match (name, value) {
+                (Value::Text(name), Value::Bytes(value)) => {
+                    store.values.insert(name.clone(), value.clone());
+                }
+                _ => return Err(STORE_MALFORMED),
+            }
+        }
+
+        Ok(store)
+    }
+
+    /// Read the store of `key` from `dev`.
+    pub fn load(dev: &Device, key: &[u8]) -> Result<LargeBlobStore> {
+        LargeBlobStore::from_array(&LargeBlobArray::from_device(dev)?, key)
+    }
+
+    /// Return the value `name`.
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.values.get(name).map(Vec::as_slice)
+    }
+
+    /// Set the value `name`, replacing the previous one.
+    pub fn put(&mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    /// Remove the value `name` and return it.
+    pub fn remove(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.values.remove(name)
+    }
+
+    /// Return the names of the values, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    /// Store the values in the entry of the key of this store in `array`.
+    ///
+    /// An empty store removes the entry.
+    pub fn store_into(&self, array: &mut LargeBlobArray) -> Result<()> {
+        if self.values.is_empty() {
+            array.remove(&self.key);
+
+            return Ok(());
+        }
+
+        let values = self
+            .values
+            .iter()
+            .map(|(name, value)| (Value::from(name.as_str()), Value::from(value.as_slice())))
+            .collect();
+        let map = Value::Map(vec![
+            (Value::from(STORE_VERSION), Value::from(VERSION)),
+            (Value::from(STORE_VALUES), Value::Map(values)),
+        ]);
+        let mut data = Zeroizing::new(Vec::new());
+        ciborium::ser::into_writer(&map, &mut *data).expect("encode largeBlob store");
+
+        array.set(&self.key, &data)
+    }
+
+    /// Write the values to `dev`, keeping the entries of other credentials.
+    ///
+    /// The size of the resulting array is checked against the `maxSerializedLargeBlobArray` limit
+    /// of `dev` first, and [Error::LargeBlobTooLarge] returned if it does not fit. Nothing is
+    /// written in that case.
+    ///
+    /// PIN is required for write operations.
+    pub fn save(&self, dev: &Device, pin: &str) -> Result<()> {
+        let max = dev.info()?.max_large_blob() as usize;
+        if max == 0 {
+            return Err(Error::Unsupported);
+        }
+
+        let mut array = LargeBlobArray::from_device(dev)?;
+        self.store_into(&mut array)?;
+
+        let size = array.to_bytes().len();
+        if size > max {
+            return Err(Error::LargeBlobTooLarge { size, max });
+        }
+
+        array.write(dev, pin)
+    }
+}
+
+/// Check the length of a `largeBlobKey`, openssl would otherwise reject it with a less clear error.
+fn check_key(key: &[u8]) -> Result<()> {
+    if key.len() != KEY_LEN {
+        return Err(Error::Malformed("largeBlobKey"));
+    }
+
+    Ok(())
+}
+
+/// Return the AES-GCM associated data of an entry, `"blob"` followed by its original size.
+fn associated_data(orig_size: u64) -> [u8; 12] {
+    let mut ad = [0; 12];
+    ad[..4].copy_from_slice(b"blob");
+    ad[4..].copy_from_slice(&orig_size.to_le_bytes());
+
+    ad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; KEY_LEN] = [0x11; KEY_LEN];
+    const OTHER_KEY: [u8; KEY_LEN] = [0x22; KEY_LEN];
+
+    fn cbor(value: &Value) -> Vec<u8> {
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(value, &mut data).unwrap();
+
+        data
+    }
+
+    fn with_digest(mut data: Vec<u8>) -> Vec<u8> {
+        let digest = sha256(&data);
+        data.extend_from_slice(&digest[..DIGEST_LEN]);
+
+        data
+    }
+
+    fn entry_value(entry: &LargeBlobEntry) -> Vec<(Value, Value)> {
+        entry.to_value().into_map().unwrap()
+    }
+
+    #[test]
+    fn empty_array() {
+        // initial serialized largeBlob array, CTAP 2.1 §6.10.2
+        let initial = [
+            0x80, 0x76, 0xbe, 0x8b, 0x52, 0x8d, 0x00, 0x75, 0xf7, 0xaa, 0xe9, 0x8d, 0x6f, 0xa5,
+            0x7a, 0x6d, 0x3c,
+        ];
+
+        assert_eq!(LargeBlobArray::new().to_bytes(), initial);
+        assert!(LargeBlobArray::parse(&initial).unwrap().is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut array = LargeBlobArray::new();
+        array.set(&KEY, b"first").unwrap();
+        array.set(&OTHER_KEY, b"other").unwrap();
+        array.set(&KEY, b"second").unwrap();
+
+        for data in [array.to_cbor(), array.to_bytes()] {
+            let parsed = LargeBlobArray::parse(&data).unwrap();
+
+            assert_eq!(parsed.to_cbor(), array.to_cbor());
+            assert_eq!(parsed.len(), 2);
+            assert_eq!(parsed.get(&KEY).unwrap().as_deref(), Some(&b"second"[..]));
+            assert_eq!(
+                parsed.get(&OTHER_KEY).unwrap().as_deref(),
+                Some(&b"other"[..])
+            );
+            assert_eq!(parsed.get(&[0x33; KEY_LEN]).unwrap(), None);
+        }
+
+        assert!(array.remove(&KEY));
+        assert!(!array.remove(&KEY));
+        assert_eq!(array.get(&KEY).unwrap(), None);
+        assert_eq!(array.len(), 1);
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let mut array = LargeBlobArray::new();
+        array.set(&KEY, b"data").unwrap();
+        let mut data = array.to_bytes();
+        *data.last_mut().unwrap() ^= 1;
+
+        assert!(matches!(
+            LargeBlobArray::parse(&data),
+            Err(Error::Malformed("largeBlob array digest"))
+        ));
+
+        // neither the array alone nor with a digest
+        data.pop();
+        assert!(LargeBlobArray::parse(&data).is_err());
+    }
+
+    #[test]
+    fn orig_size_mismatch() {
+        let mut entry = LargeBlobEntry::encrypt(&KEY, b"data").unwrap();
+        assert_eq!(entry.decrypt(&KEY).unwrap().as_deref(), Some(&b"data"[..]));
+
+        // origSize is authenticated as associated data
+        entry.orig_size += 1;
+        assert_eq!(entry.decrypt(&KEY).unwrap(), None);
+        assert!(!entry.is_encrypted_with(&KEY));
+    }
+
+    /// Return an entry of [KEY] whose data is not deflate compressed.
+    fn undecodable_entry() -> LargeBlobEntry {
+        let orig_size = 4;
+        let mut tag = [0; TAG_LEN];
+        let mut ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &KEY,
+            Some(&[0; NONCE_LEN]),
+            &associated_data(orig_size),
+            b"not deflate",
+            &mut tag,
+        )
+        .unwrap();
+        ciphertext.extend_from_slice(&tag);
+        LargeBlobEntry {
+            ciphertext,
+            nonce: [0; NONCE_LEN],
+            orig_size,
+            original: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn authenticated_but_not_compressed() {
+        let entry = undecodable_entry();
+
+        assert!(entry.decrypt(&KEY).is_err());
+        // still the entry of the key, e.g. not to be pruned
+        assert!(entry.is_encrypted_with(&KEY));
+        assert!(!entry.is_encrypted_with(&OTHER_KEY));
+    }
+
+    #[test]
+    fn undecodable_entry_is_an_error() {
+        let mut other = LargeBlobArray::new();
+        other.set(&OTHER_KEY, b"other").unwrap();
+        let elements = other
+            .entries()
+            .chain([&undecodable_entry()])
+            .map(LargeBlobEntry::to_value)
+            .collect();
+        let array = LargeBlobArray::parse(&with_digest(cbor(&Value::Array(elements)))).unwrap();
+
+        assert!(array.get(&KEY).is_err());
+        assert!(LargeBlobStore::from_array(&array, &KEY).is_err());
+        assert_eq!(
+            array.get(&OTHER_KEY).unwrap().as_deref(),
+            Some(&b"other"[..])
+        );
+        assert!(LargeBlobStore::from_array(&array, &[0x33; KEY_LEN]).is_ok());
+    }
+
+    #[test]
+    fn wrong_key() {
+        let entry = LargeBlobEntry::encrypt(&KEY, b"data").unwrap();
+
+        assert_eq!(entry.decrypt(&OTHER_KEY).unwrap(), None);
+        assert!(entry.decrypt(&KEY[..16]).is_err());
+    }
+
+    #[test]
+    fn invalid_entries_are_kept() {
+        let valid = LargeBlobEntry::encrypt(&KEY, b"data").unwrap();
+        let mut short_nonce = entry_value(&valid);
+        short_nonce[1].1 = Value::from(&[0u8; 8][..]);
+        let mut short_ciphertext = entry_value(&valid);
+        short_ciphertext[0].1 = Value::from(&[0u8; 8][..]);
+        let mut missing = entry_value(&valid);
+        missing.pop();
+
+        let invalid = vec![
+            Value::from("not a map"),
+            Value::Map(short_nonce),
+            Value::Map(short_ciphertext),
+            Value::Map(missing),
+        ];
+        let mut elements = invalid.clone();
+        elements.push(valid.to_value());
+        let data = with_digest(cbor(&Value::Array(elements)));
+
+        let mut array = LargeBlobArray::parse(&data).unwrap();
+        assert_eq!(array.len(), 5);
+        assert_eq!(array.entries().count(), 1);
+        assert_eq!(array.get(&KEY).unwrap().as_deref(), Some(&b"data"[..]));
+        assert_eq!(array.to_bytes(), data);
+
+        array.set(&KEY, b"new data").unwrap();
+        array.set(&OTHER_KEY, b"other").unwrap();
+        assert!(array.remove(&OTHER_KEY));
+        array.retain(|_| false);
+
+        let written: Value = ciborium::de::from_reader(array.to_cbor().as_slice()).unwrap();
+        assert_eq!(written.into_array().unwrap(), invalid);
+    }
+
+    #[test]
+    fn store_round_trip() {
+        let mut array = LargeBlobArray::new();
+        array.set(&OTHER_KEY, b"other").unwrap();
+
+        let mut store = LargeBlobStore::new(&KEY).unwrap();
+        store.put("b", &b"second"[..]);
+        store.put("a", &b"first"[..]);
+        store.store_into(&mut array).unwrap();
+
+        let data: Value =
+            ciborium::de::from_reader(array.get(&KEY).unwrap().unwrap().as_slice()).unwrap();
+        let expected = Value::Map(vec![
+            (Value::from(0), Value::from(1)),
+            (
+                Value::from(1),
+                Value::Map(vec![
+                    (Value::from("a"), Value::from(&b"first"[..])),
+                    (Value::from("b"), Value::from(&b"second"[..])),
+                ]),
+            ),
+        ]);
+        assert_eq!(data, expected);
+
+        let mut store = LargeBlobStore::from_array(&array, &KEY).unwrap();
+        assert_eq!(store.names().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(store.get("b"), Some(&b"second"[..]));
+        assert_eq!(
+            array.get(&OTHER_KEY).unwrap().as_deref(),
+            Some(&b"other"[..])
+        );
+
+        // an empty store removes the entry
+        store.remove("a");
+        store.remove("b");
+        store.store_into(&mut array).unwrap();
+        assert_eq!(array.get(&KEY).unwrap(), None);
+        assert_eq!(array.len(), 1);
+    }
+
+    #[test]
+    fn store_version() {
+        let other_version = Value::Map(vec![
+            (Value::from(0), Value::from(2)),
+            (Value::from(1), Value::Map(vec![])),
+        ]);
+        let mut array = LargeBlobArray::new();
+        array.set(&KEY, &cbor(&other_version)).unwrap();
+        assert!(matches!(
+            LargeBlobStore::from_array(&array, &KEY),
+            Err(Error::Unsupported)
+        ));
+
+        // the unversioned map of names
+        let unversioned = Value::Map(vec![(Value::from("a"), Value::from(&b"first"[..]))]);
+        array.set(&KEY, &cbor(&unversioned)).unwrap();
+        assert!(matches!(
+            LargeBlobStore::from_array(&array, &KEY),
+            Err(Error::Malformed("largeBlob store"))
+        ));
+    }
+
+    #[test]
+    fn foreign_entry_untouched() {
+        // an entry of another credential, with a key this crate does not know about
+        let foreign = LargeBlobEntry::encrypt(&OTHER_KEY, b"foreign").unwrap();
+        let mut foreign = entry_value(&foreign);
+        foreign.push((Value::from(4), Value::from("extension")));
+        let foreign = Value::Map(foreign);
+
+        let data = cbor(&Value::Array(vec![foreign.clone()]));
+        let mut array = LargeBlobArray::parse(&data).unwrap();
+        array.set(&KEY, b"data").unwrap();
+        assert_eq!(
+            array
+                .entries()
+                .next()
+                .unwrap()
+                .decrypt(&OTHER_KEY)
+                .unwrap()
+                .as_deref(),
+            Some(&b"foreign"[..])
+        );
+
+        let written: Value = ciborium::de::from_reader(array.to_cbor().as_slice()).unwrap();
+        let written = written.into_array().unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(written[0], foreign);
+    }
+}
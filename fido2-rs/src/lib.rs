@@ -32,6 +32,14 @@
 //! The other dependency like libcbor, libcrypto, zlib will use system version. Currently there is no way to
 //! set these library directory, but you can put them together in `FIDO2_LIB_DIR`.
 //!
+//! # Features
+//!
+//! - `tokio`: an async wrapper over [device::Device] in the [async_device] module.
+//! - `serde`: serialization of owned types like [info::AuthenticatorInfo].
+//! - `mds`: FIDO Metadata Service BLOB parsing in the [mds] module.
+//! - `rp`: relying party side verification of WebAuthn responses in the [rp] module.
+//! - `soft-authenticator`: an in-process software authenticator in the [soft] module, for testing without a device.
+//!
 //! # Example
 //!
 //! ## Enumerate fido devices on system
@@ -72,9 +80,26 @@ extern crate libfido2_sys as ffi;
 mod utils;
 
 pub mod assertion;
+#[cfg(feature = "tokio")]
+pub mod async_device;
+pub mod attestation;
+pub mod authdata;
+pub mod bio;
 mod cbor;
+pub mod config;
+pub mod cose;
 pub mod credentials;
 pub mod credman;
 pub mod device;
 pub mod error;
+pub mod info;
 mod key;
+pub mod largeblob;
+#[cfg(feature = "mds")]
+pub mod mds;
+pub mod prf;
+#[cfg(feature = "rp")]
+pub mod rp;
+#[cfg(feature = "soft-authenticator")]
+pub mod soft;
+pub mod transport;
@@ -0,0 +1,581 @@
+//! FIDO Metadata Service (MDS3) BLOB parsing.
+//!
+//! The [MDS3 BLOB](https://fidoalliance.org/specs/mds/fido-metadata-service-v3.0-ps-20210518.html)
+//! is a signed JWT listing metadata of certified authenticators. [MetadataBlob] verifies a locally
+//! stored BLOB against the FIDO root certificate and indexes its entries by AAGUID.
+//!
+//! Revocation of the BLOB signing certificates is not checked.
+//!
+//! # Example
+//! ```rust,no_run
+//! use fido2_rs::credentials::Credential;
+//! use fido2_rs::info::Aaguid;
+//! use fido2_rs::mds::MetadataBlob;
+//! use openssl::x509::X509;
+//!
+//! fn check(cred: &Credential) -> anyhow::Result<()> {
+//!     let root = X509::from_pem(&std::fs::read("Root-R3.crt")?)?;
+//!     let blob = MetadataBlob::from_jwt(&std::fs::read("blob.jwt")?, &root)?;
+//!
+//!     let aaguid = Aaguid::try_from(cred.attestation_guid())?;
+//!     let entry = blob.get(&aaguid).ok_or(anyhow::anyhow!("unknown authenticator"))?;
+//!     anyhow::ensure!(!entry.is_compromised(), "{} is compromised", entry.description);
+//!
+//!     entry.attestation_verifier()?.verify(cred)?;
+//!     Ok(())
+//! }
+//! ```
+use std::collections::HashMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use openssl::bn::BigNum;
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::sign::Verifier;
+use openssl::stack::Stack;
+use openssl::x509::store::{X509Store, X509StoreBuilder};
+use openssl::x509::verify::X509VerifyFlags;
+use openssl::x509::{X509, X509Ref, X509StoreContext};
+use serde::Deserialize;
+
+use crate::attestation::AttestationVerifier;
+use crate::error::{Error, Result};
+use crate::info::Aaguid;
+
+const MALFORMED: Error = Error::Malformed("MDS BLOB");
+
+/// A verified MDS3 BLOB.
+pub struct MetadataBlob {
+    /// Serial number of this BLOB.
+    pub no: u64,
+    /// Date of the next BLOB update, as `YYYY-MM-DD`.
+    pub next_update: String,
+    /// Legal header.
+    pub legal_header: Option<String>,
+    entries: HashMap<Aaguid, MetadataEntry>,
+}
+
+/// Metadata of an authenticator model.
+#[derive(Clone, Debug)]
+pub struct MetadataEntry {
+    /// AAGUID of the authenticator.
+    pub aaguid: Aaguid,
+    /// Human-readable description.
+    pub description: String,
+    /// Icon, as a `data:` URL.
+    pub icon: Option<String>,
+    /// Root certificates of the attestation certificates.
+    pub attestation_root_certificates: Vec<X509>,
+    /// Status history, in chronological order.
+    pub status_reports: Vec<StatusReport>,
+    /// Date of the last status change, as `YYYY-MM-DD`.
+    pub time_of_last_status_change: Option<String>,
+}
+
+/// A status report of an authenticator model.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StatusReport {
+    /// Status.
+    pub status: AuthenticatorStatus,
+    /// Date since when the status applies, as `YYYY-MM-DD`.
+    pub effective_date: Option<String>,
+}
+
+/// Status of an authenticator model.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AuthenticatorStatus {
+    NotFidoCertified,
+    FidoCertified,
+    UserVerificationBypass,
+    AttestationKeyCompromise,
+    UserKeyRemoteCompromise,
+    UserKeyPhysicalCompromise,
+    UpdateAvailable,
+    Revoked,
+    SelfAssertionSubmitted,
+    FidoCertifiedL1,
+    FidoCertifiedL1Plus,
+    FidoCertifiedL2,
+    FidoCertifiedL2Plus,
+    FidoCertifiedL3,
+    FidoCertifiedL3Plus,
+    /// A status unknown to this crate.
+    Other(String),
+}
+
+impl From<&str> for AuthenticatorStatus {
+    fn from(value: &str) -> Self {
+        match value {
+            "NOT_FIDO_CERTIFIED" => AuthenticatorStatus::NotFidoCertified,
+            "FIDO_CERTIFIED" => AuthenticatorStatus::FidoCertified,
+            "USER_VERIFICATION_BYPASS" => AuthenticatorStatus::UserVerificationBypass,
+            "ATTESTATION_KEY_COMPROMISE" => AuthenticatorStatus::AttestationKeyCompromise,
+            "USER_KEY_REMOTE_COMPROMISE" => AuthenticatorStatus::UserKeyRemoteCompromise,
+            "USER_KEY_PHYSICAL_COMPROMISE" => AuthenticatorStatus::UserKeyPhysicalCompromise,
+            "UPDATE_AVAILABLE" => AuthenticatorStatus::UpdateAvailable,
+            "REVOKED" => AuthenticatorStatus::Revoked,
+            "SELF_ASSERTION_SUBMITTED" => AuthenticatorStatus::SelfAssertionSubmitted,
+            "FIDO_CERTIFIED_L1" => AuthenticatorStatus::FidoCertifiedL1,
+            "FIDO_CERTIFIED_L1plus" => AuthenticatorStatus::FidoCertifiedL1Plus,
+            "FIDO_CERTIFIED_L2" => AuthenticatorStatus::FidoCertifiedL2,
+            "FIDO_CERTIFIED_L2plus" => AuthenticatorStatus::FidoCertifiedL2Plus,
+            "FIDO_CERTIFIED_L3" => AuthenticatorStatus::FidoCertifiedL3,
+            "FIDO_CERTIFIED_L3plus" => AuthenticatorStatus::FidoCertifiedL3Plus,
+            other => AuthenticatorStatus::Other(other.to_string()),
+        }
+    }
+}
+
+impl AuthenticatorStatus {
+    /// Return true if this is one of the `FIDO_CERTIFIED*` statuses.
+    pub fn is_certified(&self) -> bool {
+        matches!(
+            self,
+            AuthenticatorStatus::FidoCertified
+                | AuthenticatorStatus::FidoCertifiedL1
+                | AuthenticatorStatus::FidoCertifiedL1Plus
+                | AuthenticatorStatus::FidoCertifiedL2
+                | AuthenticatorStatus::FidoCertifiedL2Plus
+                | AuthenticatorStatus::FidoCertifiedL3
+                | AuthenticatorStatus::FidoCertifiedL3Plus
+        )
+    }
+
+    /// Return true if this status reports a security issue, e.g. a key compromise.
+    pub fn is_compromise(&self) -> bool {
+        matches!(
+            self,
+            AuthenticatorStatus::UserVerificationBypass
+                | AuthenticatorStatus::AttestationKeyCompromise
+                | AuthenticatorStatus::UserKeyRemoteCompromise
+                | AuthenticatorStatus::UserKeyPhysicalCompromise
+                | AuthenticatorStatus::Revoked
+        )
+    }
+}
+
+impl MetadataEntry {
+    /// Return the latest status.
+    pub fn status(&self) -> Option<&AuthenticatorStatus> {
+        self.status_reports.last().map(|it| &it.status)
+    }
+
+    /// Return true if any status report is a certification.
+    pub fn is_certified(&self) -> bool {
+        self.status_reports
+            .iter()
+            .any(|it| it.status.is_certified())
+    }
+
+    /// Return true if any status report is a compromise, see [AuthenticatorStatus::is_compromise].
+    pub fn is_compromised(&self) -> bool {
+        self.status_reports
+            .iter()
+            .any(|it| it.status.is_compromise())
+    }
+
+    /// Return a store of the attestation root certificates.
+    pub fn attestation_store(&self) -> Result<X509Store> {
+        let mut store = X509StoreBuilder::new()?;
+        // some metadata statements list intermediate certificates as roots
+        store.set_flags(X509VerifyFlags::PARTIAL_CHAIN)?;
+        for cert in &self.attestation_root_certificates {
+            store.add_cert(cert.clone())?;
+        }
+
+        Ok(store.build())
+    }
+
+    /// Return a [AttestationVerifier] trusting the attestation root certificates.
+    pub fn attestation_verifier(&self) -> Result<AttestationVerifier> {
+        Ok(AttestationVerifier::new(self.attestation_store()?))
+    }
+}
+
+impl MetadataBlob {
+    /// Parse a BLOB and verify its signature, with a certificate chain up to `root`.
+    ///
+    /// Entries without an AAGUID, i.e. of U2F and UAF authenticators, are skipped.
+    pub fn from_jwt(jwt: &[u8], root: &X509Ref) -> Result<MetadataBlob> {
+        let jwt = std::str::from_utf8(jwt).map_err(|_| MALFORMED)?.trim();
+        let parts = jwt.split('.').collect::<Vec<_>>();
+        let [encoded_header, encoded_payload, signature] = parts[..] else {
+            return Err(MALFORMED);
+        };
+
+        let decode = |it: &str| URL_SAFE_NO_PAD.decode(it).map_err(|_| MALFORMED);
+        let header: JwtHeader =
+            serde_json::from_slice(&decode(encoded_header)?).map_err(|_| MALFORMED)?;
+        let signature = decode(signature)?;
+
+        let certs = header
+            .x5c
+            .iter()
+            .map(|it| {
+                Ok(X509::from_der(
+                    &STANDARD.decode(it).map_err(|_| MALFORMED)?,
+                )?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (leaf, intermediates) = certs.split_first().ok_or(MALFORMED)?;
+
+        let mut store = X509StoreBuilder::new()?;
+        store.add_cert(root.to_owned())?;
+        let store = store.build();
+
+        let mut chain = Stack::new()?;
+        for cert in intermediates {
+            chain.push(cert.clone())?;
+        }
+
+        let mut ctx = X509StoreContext::new()?;
+        if !ctx.init(&store, leaf, &chain, |ctx| ctx.verify_cert())? {
+            return Err(Error::Verification("MDS BLOB certificate chain"));
+        }
+
+        // JWS signs the encoded header and payload
+        let signed = &jwt[..encoded_header.len() + 1 + encoded_payload.len()];
+        let (digest, signature) = match header.alg.as_str() {
+            "RS256" => (MessageDigest::sha256(), signature),
+            "ES256" => (MessageDigest::sha256(), jws_to_der(&signature, 32)?),
+            "ES384" => (MessageDigest::sha384(), jws_to_der(&signature, 48)?),
+            _ => return Err(Error::Unsupported),
+        };
+
+        let public_key = leaf.public_key()?;
+        let mut verifier = Verifier::new(digest, &public_key)?;
+        if !verifier.verify_oneshot(&signature, signed.as_bytes())? {
+            return Err(Error::Verification("MDS BLOB signature"));
+        }
+
+        let payload: Payload =
+            serde_json::from_slice(&decode(encoded_payload)?).map_err(|_| MALFORMED)?;
+
+        let mut entries = HashMap::new();
+        for entry in payload.entries {
+            let (Some(aaguid), Some(statement)) = (entry.aaguid, entry.metadata_statement) else {
+                continue;
+            };
+            let aaguid = aaguid.parse::<Aaguid>()?;
+
+            let attestation_root_certificates = statement
+                .attestation_root_certificates
+                .iter()
+                .map(|it| {
+                    Ok(X509::from_der(
+                        &STANDARD.decode(it).map_err(|_| MALFORMED)?,
+                    )?)
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let status_reports = entry
+                .status_reports
+                .into_iter()
+                .map(|it| StatusReport {
+                    status: AuthenticatorStatus::from(it.status.as_str()),
+                    effective_date: it.effective_date,
+                })
+                .collect();
+
+            entries.insert(
+                aaguid,
+                MetadataEntry {
+                    aaguid,
+                    description: statement.description,
+                    icon: statement.icon,
+                    attestation_root_certificates,
+                    status_reports,
+                    time_of_last_status_change: entry.time_of_last_status_change,
+                },
+            );
+        }
+
+        Ok(MetadataBlob {
+            no: payload.no,
+            next_update: payload.next_update,
+            legal_header: payload.legal_header,
+            entries,
+        })
+    }
+
+    /// Return the entry of the authenticator model `aaguid`.
+    pub fn get(&self, aaguid: &Aaguid) -> Option<&MetadataEntry> {
+        self.entries.get(aaguid)
+    }
+
+    /// Return a iterator of all entries.
+    pub fn entries(&self) -> impl Iterator<Item = &MetadataEntry> {
+        self.entries.values()
+    }
+
+    /// Return the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return true if there is no entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Convert a JWS ECDSA signature, `r || s`, to DER.
+fn jws_to_der(signature: &[u8], len: usize) -> Result<Vec<u8>> {
+    if signature.len() != len * 2 {
+        return Err(Error::Verification("MDS BLOB signature"));
+    }
+
+    let (r, s) = signature.split_at(len);
+    let signature =
+        EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?;
+
+    Ok(signature.to_der()?)
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    #[serde(default)]
+    x5c: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Payload {
+    legal_header: Option<String>,
+    no: u64,
+    next_update: String,
+    entries: Vec<RawEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawEntry {
+    aaguid: Option<String>,
+    metadata_statement: Option<RawStatement>,
+    #[serde(default)]
+    status_reports: Vec<RawStatusReport>,
+    time_of_last_status_change: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawStatement {
+    description: String,
+    icon: Option<String>,
+    #[serde(default)]
+    attestation_root_certificates: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawStatusReport {
+    status: String,
+    effective_date: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::sign::Signer;
+    use openssl::x509::X509NameBuilder;
+    use openssl::x509::extension::BasicConstraints;
+    use serde_json::json;
+
+    const AAGUID: &str = "cb69481e-8ff7-4039-93ec-0a2729a154a8";
+
+    fn ec_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn certificate(cn: &str, key: &PKey<Private>, issuer: Option<(&X509, &PKey<Private>)>) -> X509 {
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", cn).unwrap();
+        let name = name.build();
+
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_pubkey(key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        match issuer {
+            Some((issuer, issuer_key)) => {
+                cert.set_issuer_name(issuer.subject_name()).unwrap();
+                cert.sign(issuer_key, MessageDigest::sha256()).unwrap();
+            }
+            None => {
+                let ca = BasicConstraints::new().critical().ca().build().unwrap();
+                cert.append_extension(ca).unwrap();
+                cert.set_issuer_name(&name).unwrap();
+                cert.sign(key, MessageDigest::sha256()).unwrap();
+            }
+        }
+
+        cert.build()
+    }
+
+    struct Signing {
+        root: X509,
+        leaf: X509,
+        key: PKey<Private>,
+    }
+
+    impl Signing {
+        fn new() -> Signing {
+            let root_key = ec_key();
+            let root = certificate("MDS root", &root_key, None);
+            let key = ec_key();
+            let leaf = certificate("MDS signer", &key, Some((&root, &root_key)));
+
+            Signing { root, leaf, key }
+        }
+
+        fn jwt(&self, payload: &serde_json::Value) -> String {
+            let header = json!({
+                "alg": "ES256",
+                "typ": "JWT",
+                "x5c": [STANDARD.encode(self.leaf.to_der().unwrap())],
+            });
+            let signed = format!(
+                "{}.{}",
+                URL_SAFE_NO_PAD.encode(header.to_string()),
+                URL_SAFE_NO_PAD.encode(payload.to_string())
+            );
+
+            let mut signer = Signer::new(MessageDigest::sha256(), &self.key).unwrap();
+            let der = signer.sign_oneshot_to_vec(signed.as_bytes()).unwrap();
+            let signature = EcdsaSig::from_der(&der).unwrap();
+            let mut raw = signature.r().to_vec_padded(32).unwrap();
+            raw.extend(signature.s().to_vec_padded(32).unwrap());
+
+            format!("{signed}.{}", URL_SAFE_NO_PAD.encode(raw))
+        }
+    }
+
+    fn payload(attestation_root: &X509) -> serde_json::Value {
+        json!({
+            "legalHeader": "legal",
+            "no": 42,
+            "nextUpdate": "2030-01-01",
+            "entries": [
+                {
+                    "aaguid": AAGUID,
+                    "metadataStatement": {
+                        "description": "Test Key",
+                        "attestationRootCertificates": [
+                            STANDARD.encode(attestation_root.to_der().unwrap())
+                        ],
+                    },
+                    "statusReports": [
+                        { "status": "FIDO_CERTIFIED_L1", "effectiveDate": "2020-01-01" },
+                        { "status": "ATTESTATION_KEY_COMPROMISE", "effectiveDate": "2021-01-01" },
+                        { "status": "SOMETHING_NEW" },
+                    ],
+                    "timeOfLastStatusChange": "2021-01-01",
+                },
+                {
+                    "aaid": "4e4e#4005",
+                    "statusReports": [{ "status": "FIDO_CERTIFIED" }],
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn parse() {
+        let signing = Signing::new();
+        let jwt = signing.jwt(&payload(&signing.root));
+        let blob = MetadataBlob::from_jwt(jwt.as_bytes(), &signing.root).unwrap();
+
+        assert_eq!(blob.no, 42);
+        assert_eq!(blob.next_update, "2030-01-01");
+        assert_eq!(blob.legal_header.as_deref(), Some("legal"));
+        // the UAF entry has no AAGUID
+        assert_eq!(blob.len(), 1);
+
+        let entry = blob.get(&AAGUID.parse().unwrap()).unwrap();
+        assert_eq!(entry.description, "Test Key");
+        assert_eq!(entry.attestation_root_certificates.len(), 1);
+        assert_eq!(
+            entry.time_of_last_status_change.as_deref(),
+            Some("2021-01-01")
+        );
+        assert_eq!(
+            entry.status(),
+            Some(&AuthenticatorStatus::Other("SOMETHING_NEW".to_string()))
+        );
+        assert!(entry.is_certified());
+        assert!(entry.is_compromised());
+    }
+
+    #[test]
+    fn untrusted_root() {
+        let signing = Signing::new();
+        let other = Signing::new();
+        let jwt = signing.jwt(&payload(&signing.root));
+
+        assert!(matches!(
+            MetadataBlob::from_jwt(jwt.as_bytes(), &other.root),
+            Err(Error::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn tampered_payload() {
+        let signing = Signing::new();
+        let jwt = signing.jwt(&payload(&signing.root));
+        let [header, _, signature] = jwt.split('.').collect::<Vec<_>>()[..] else {
+            unreachable!()
+        };
+
+        let mut tampered = payload(&signing.root);
+        tampered["no"] = json!(43);
+        let tampered = format!(
+            "{header}.{}.{signature}",
+            URL_SAFE_NO_PAD.encode(tampered.to_string())
+        );
+
+        assert!(matches!(
+            MetadataBlob::from_jwt(tampered.as_bytes(), &signing.root),
+            Err(Error::Verification(_))
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        let signing = Signing::new();
+        let jwt = signing.jwt(&payload(&signing.root));
+
+        for jwt in ["", "a.b", &jwt[..jwt.rfind('.').unwrap()], "a.b.c.d"] {
+            assert!(
+                matches!(
+                    MetadataBlob::from_jwt(jwt.as_bytes(), &signing.root),
+                    Err(Error::Malformed(_))
+                ),
+                "{jwt}"
+            );
+        }
+
+        let not_json = signing.jwt(&json!("entries"));
+        assert!(matches!(
+            MetadataBlob::from_jwt(not_json.as_bytes(), &signing.root),
+            Err(Error::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn jws_signature_length() {
+        assert!(jws_to_der(&[1; 64], 32).is_ok());
+        assert!(jws_to_der(&[1; 63], 32).is_err());
+    }
+}
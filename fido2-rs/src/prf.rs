@@ -0,0 +1,173 @@
+//! WebAuthn PRF extension, on top of the CTAP2 hmac-secret extension.
+//!
+//! A PRF-capable credential derives up to two 32 bytes secrets per assertion from caller chosen
+//! inputs. The same input always gives the same output for a credential, which makes it suitable
+//! to derive keys, e.g. to unlock an encrypted disk.
+//!
+//! Inputs are hashed into hmac-secret salts as in the WebAuthn PRF extension, so the outputs match
+//! those a browser returns for the same credential and inputs.
+//!
+//! Outputs are only available from assertions. The CTAP 2.2 `hmac-secret-mc` extension, returning
+//! them at registration, is not supported: libfido2 does not send a salt in makeCredential, and
+//! the output is encrypted with a PIN/UV auth protocol shared secret that libfido2 does not expose.
+//!
+//! # Example
+//! ```rust,no_run
+//! use fido2_rs::credentials::{CoseType, Credential, Extensions};
+//! use fido2_rs::device::Device;
+//! use fido2_rs::prf::{self, PrfInput};
+//!
+//! fn unlock(dev: &Device, pin: &str) -> anyhow::Result<()> {
+//!     let mut cred = Credential::new();
+//!     cred.set_client_data(&[0; 32])?;
+//!     cred.set_rp("vault", "vault")?;
+//!     cred.set_user(&[1, 2, 3, 4], "alice", None, None)?;
+//!     cred.set_cose_type(CoseType::ES256)?;
+//!     prf::enable(&mut cred, Extensions::empty())?;
+//!     dev.make_credential(&mut cred, Some(pin))?;
+//!     anyhow::ensure!(prf::is_enabled(&cred), "PRF not supported");
+//!
+//!     let output = prf::evaluate(dev, "vault", cred.id(), &PrfInput::new(b"disk key"), Some(pin))?;
+//!     let _key: &[u8; 32] = &output.first;
+//!
+//!     Ok(())
+//! }
+//! ```
+use ciborium::Value;
+use openssl::sha::Sha256;
+use zeroize::Zeroizing;
+
+use crate::assertion::{AssertRequest, Assertion};
+use crate::credentials::{Credential, CredentialRef, Extensions};
+use crate::device::Device;
+use crate::error::{Error, Result};
+
+/// Length of a PRF salt and output.
+pub const PRF_LEN: usize = 32;
+
+/// Prefix of the PRF salts, `"WebAuthn PRF"` followed by a zero byte.
+const SALT_PREFIX: &[u8] = b"WebAuthn PRF\0";
+
+/// Return the hmac-secret salt of the PRF input `input`, `SHA-256("WebAuthn PRF" || 0x00 || input)`.
+pub fn salt(input: &[u8]) -> [u8; PRF_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.update(SALT_PREFIX);
+    hasher.update(input);
+
+    hasher.finish()
+}
+
+/// Request the hmac-secret extension on `cred`, in addition to `extensions`.
+///
+/// This replaces the extensions set with [Credential::set_extension].
+pub fn enable(cred: &mut Credential, extensions: Extensions) -> Result<()> {
+    cred.set_extension(extensions | Extensions::HMAC_SECRET)
+}
+
+/// Return true if the authenticator enabled the hmac-secret extension on the new credential `cred`.
+pub fn is_enabled(cred: &CredentialRef) -> bool {
+    cred.authenticator_data()
+        .ok()
+        .and_then(|it| it.extension("hmac-secret").cloned())
+        == Some(Value::Bool(true))
+}
+
+/// One or two PRF inputs to evaluate in an assertion.
+pub struct PrfInput {
+    first: [u8; PRF_LEN],
+    second: Option<[u8; PRF_LEN]>,
+}
+
+impl PrfInput {
+    /// Return a [PrfInput] evaluating `first`.
+    pub fn new(first: impl AsRef<[u8]>) -> PrfInput {
+        PrfInput {
+            first: salt(first.as_ref()),
+            second: None,
+        }
+    }
+
+    /// Return a [PrfInput] evaluating `first` and `second`, e.g. the current and the next key
+    /// when rotating.
+    pub fn with_second(first: impl AsRef<[u8]>, second: impl AsRef<[u8]>) -> PrfInput {
+        PrfInput {
+            first: salt(first.as_ref()),
+            second: Some(salt(second.as_ref())),
+        }
+    }
+
+    /// Request the hmac-secret extension on `request` with the salts of this input, in addition
+    /// to `extensions`.
+    ///
+    /// This replaces the extensions set with `AssertRequest::set_extensions`.
+    pub fn apply(&self, request: &mut AssertRequest, extensions: Extensions) -> Result<()> {
+        let mut salt = Zeroizing::new(self.first.to_vec());
+        if let Some(second) = &self.second {
+            salt.extend_from_slice(second);
+        }
+
+        request.set_extensions(extensions | Extensions::HMAC_SECRET)?;
+        request.set_hmac_salt(&salt)
+    }
+}
+
+/// PRF outputs of an assertion, zeroized on drop.
+pub struct PrfOutput {
+    /// Output of the first input.
+    pub first: Zeroizing<[u8; PRF_LEN]>,
+    /// Output of the second input, if one was given.
+    pub second: Option<Zeroizing<[u8; PRF_LEN]>>,
+}
+
+impl PrfOutput {
+    /// Return the PRF outputs of `assertion`.
+    ///
+    /// Return [Error::Unsupported] if the authenticator did not return an hmac-secret output.
+    pub fn from_assertion(assertion: &Assertion) -> Result<PrfOutput> {
+        let secret = assertion.hmac_secret();
+        let output = |data: &[u8]| Zeroizing::new(data.try_into().unwrap());
+
+        match secret.len() {
+            0 => Err(Error::Unsupported),
+            PRF_LEN => Ok(PrfOutput {
+                first: output(secret),
+                second: None,
+            }),
+            len if len == 2 * PRF_LEN => Ok(PrfOutput {
+                first: output(&secret[..PRF_LEN]),
+                second: Some(output(&secret[PRF_LEN..])),
+            }),
+            _ => Err(Error::Malformed("hmac-secret output")),
+        }
+    }
+}
+
+/// Evaluate `input` with the credential `credential_id` of the relying party `rp_id`.
+///
+/// The outputs depend on whether the user was verified, so the same `pin` (or lack of) must be
+/// used every time.
+pub fn evaluate(
+    dev: &Device,
+    rp_id: &str,
+    credential_id: &[u8],
+    input: &PrfInput,
+    pin: Option<&str>,
+) -> Result<PrfOutput> {
+    // the assertion itself is not verified, the client data hash only has to be fresh
+    let mut client_data_hash = [0; 32];
+    openssl::rand::rand_bytes(&mut client_data_hash)?;
+
+    let mut request = AssertRequest::new();
+    request.set_rp(rp_id)?;
+    request.set_client_data_hash(client_data_hash)?;
+    request.set_allow_credential(credential_id)?;
+    input.apply(&mut request, Extensions::empty())?;
+
+    let assertions = dev.get_assertion(request, pin)?;
+    let assertion = assertions
+        .iter()
+        .next()
+        .ok_or(Error::Malformed("assertion"))?;
+
+    PrfOutput::from_assertion(&assertion)
+}
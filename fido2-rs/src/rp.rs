@@ -0,0 +1,636 @@
+//! Relying party side verification of WebAuthn ceremonies.
+//!
+//! A [RelyingParty] checks the responses a browser returns from `navigator.credentials.create()`
+//! and `navigator.credentials.get()`: the `clientDataJSON`, the authenticator data and, for
+//! assertions, the signature made with the public key stored at registration.
+//!
+//! The attestation statement of a registration is not verified here, only the authenticator data
+//! it carries.
+//!
+//! # Example
+//! ```rust,no_run
+//! use fido2_rs::rp::{Challenge, RelyingParty, UserVerification};
+//!
+//! fn login(
+//!     registration: (&[u8], &[u8]),
+//!     assertion: (&[u8], &[u8], &[u8]),
+//! ) -> anyhow::Result<()> {
+//!     let mut rp = RelyingParty::new("example.com", "https://example.com");
+//!     rp.set_user_verification(UserVerification::Required);
+//!
+//!     // send `challenge.to_base64url()` to the browser and keep the challenge in the session
+//!     let challenge = Challenge::random()?;
+//!     let (client_data_json, attestation_object) = registration;
+//!     let mut credential =
+//!         rp.verify_registration(&challenge, client_data_json, attestation_object)?;
+//!
+//!     let challenge = Challenge::random()?;
+//!     let (client_data_json, auth_data, signature) = assertion;
+//!     rp.verify_assertion(&challenge, &mut credential, client_data_json, auth_data, signature)?;
+//!
+//!     // `credential.sign_count` has been updated, store it back
+//!     Ok(())
+//! }
+//! ```
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ciborium::Value;
+use openssl::pkey::{PKey, Public};
+use openssl::sha::sha256;
+
+use crate::assertion::AssertVerifier;
+use crate::authdata::{AuthDataFlags, AuthenticatorData};
+use crate::cose::CoseKey;
+use crate::credentials::Opt;
+use crate::error::{Error, Result};
+use crate::info::Aaguid;
+
+const CHALLENGE_LEN: usize = 32;
+
+/// A random challenge for a registration or authentication ceremony.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Challenge([u8; CHALLENGE_LEN]);
+
+impl Challenge {
+    /// Generate a new challenge of 32 random bytes.
+    pub fn random() -> Result<Challenge> {
+        let mut challenge = [0; CHALLENGE_LEN];
+        openssl::rand::rand_bytes(&mut challenge)?;
+
+        Ok(Challenge(challenge))
+    }
+
+    /// Return the raw bytes of this challenge.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Return the challenge in unpadded base64url, as it appears in `clientDataJSON`.
+    pub fn to_base64url(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.0)
+    }
+}
+
+impl From<[u8; CHALLENGE_LEN]> for Challenge {
+    fn from(value: [u8; CHALLENGE_LEN]) -> Self {
+        Challenge(value)
+    }
+}
+
+impl AsRef<[u8]> for Challenge {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The parsed `clientDataJSON` of a WebAuthn response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClientData {
+    /// `webauthn.create` or `webauthn.get`.
+    pub ty: String,
+    /// The decoded challenge.
+    pub challenge: Vec<u8>,
+    /// The origin of the page that made the request.
+    pub origin: String,
+    /// Whether the request was made from a cross-origin iframe.
+    pub cross_origin: bool,
+}
+
+impl ClientData {
+    /// Parse a `clientDataJSON`.
+    pub fn parse(json: &[u8]) -> Result<ClientData> {
+        let json: serde_json::Value =
+            serde_json::from_slice(json).map_err(|_| Error::Malformed("clientDataJSON"))?;
+
+        let field = |name| json.get(name).and_then(serde_json::Value::as_str);
+
+        let ty = field("type").ok_or(Error::Malformed("clientDataJSON type"))?;
+        let challenge = field("challenge")
+            .and_then(|it| URL_SAFE_NO_PAD.decode(it).ok())
+            .ok_or(Error::Malformed("clientDataJSON challenge"))?;
+        let origin = field("origin").ok_or(Error::Malformed("clientDataJSON origin"))?;
+        let cross_origin = match json.get("crossOrigin") {
+            None | Some(serde_json::Value::Null) => false,
+            Some(it) => it
+                .as_bool()
+                .ok_or(Error::Malformed("clientDataJSON crossOrigin"))?,
+        };
+
+        Ok(ClientData {
+            ty: ty.to_string(),
+            challenge,
+            origin: origin.to_string(),
+            cross_origin,
+        })
+    }
+}
+
+/// User verification requirement of a [RelyingParty].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum UserVerification {
+    /// The UV flag must be set.
+    Required,
+    /// The UV flag is recorded in [RegisteredCredential::user_verified] but not enforced, as for
+    /// the WebAuthn `preferred` and `discouraged` requirements.
+    #[default]
+    Preferred,
+}
+
+/// A credential accepted by [RelyingParty::verify_registration], to be stored by the relying party.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisteredCredential {
+    /// Credential ID.
+    pub id: Vec<u8>,
+    /// CBOR-encoded COSE_Key of the credential.
+    pub public_key: Vec<u8>,
+    /// Last seen signature counter.
+    pub sign_count: u32,
+    /// AAGUID of the authenticator that created the credential.
+    pub aaguid: Aaguid,
+    /// Whether the user was verified at registration.
+    pub user_verified: bool,
+    /// Whether the credential may be backed up (BE flag).
+    pub backup_eligible: bool,
+    /// Whether the credential is currently backed up (BS flag).
+    pub backup_state: bool,
+}
+
+impl RegisteredCredential {
+    /// Return the public key of this credential.
+    pub fn public_key(&self) -> Result<PKey<Public>> {
+        CoseKey::from_cbor(&self.public_key)?.to_pkey()
+    }
+}
+
+/// A relying party, verifying registrations and assertions for one RP ID.
+#[derive(Clone, Debug)]
+pub struct RelyingParty {
+    id: String,
+    origins: Vec<String>,
+    user_verification: UserVerification,
+}
+
+impl RelyingParty {
+    /// Return a [RelyingParty] with the RP ID `id`, accepting requests from `origin`.
+    pub fn new(id: impl Into<String>, origin: impl Into<String>) -> RelyingParty {
+        RelyingParty {
+            id: id.into(),
+            origins: vec![origin.into()],
+            user_verification: UserVerification::default(),
+        }
+    }
+
+    /// Accept requests from another origin, e.g. a subdomain of the RP ID.
+    pub fn add_origin(&mut self, origin: impl Into<String>) {
+        self.origins.push(origin.into());
+    }
+
+    /// Set the user verification requirement.
+    ///
+    /// **Default to [UserVerification::Preferred]**
+    pub fn set_user_verification(&mut self, uv: UserVerification) {
+        self.user_verification = uv;
+    }
+
+    /// Return the RP ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Verify the response of a registration ceremony and return the new credential.
+    ///
+    /// `challenge` is the challenge sent to the browser, `client_data_json` and `attestation_object`
+    /// are the fields of the `AuthenticatorAttestationResponse`.
+    pub fn verify_registration(
+        &self,
+        challenge: impl AsRef<[u8]>,
+        client_data_json: &[u8],
+        attestation_object: &[u8],
+    ) -> Result<RegisteredCredential> {
+        self.check_client_data(client_data_json, "webauthn.create", challenge.as_ref())?;
+
+        let attestation_object: Value = ciborium::de::from_reader(attestation_object)
+            .map_err(|_| Error::Malformed("attestation object"))?;
+        let auth_data = attestation_object
+            .as_map()
+            .and_then(|it| {
+                it.iter()
+                    .find(|(k, _)| k.as_text() == Some("authData"))
+                    .and_then(|(_, v)| v.as_bytes())
+            })
+            .ok_or(Error::Malformed("attestation object"))?;
+
+        let auth_data = AuthenticatorData::from_raw(auth_data)?;
+        self.check_auth_data(&auth_data)?;
+
+        let attested = auth_data
+            .attested_credential
+            .ok_or(Error::Malformed("authenticator data"))?;
+
+        let credential = RegisteredCredential {
+            id: attested.credential_id,
+            public_key: attested.public_key,
+            sign_count: auth_data.sign_count,
+            aaguid: attested.aaguid,
+            user_verified: auth_data.flags.contains(AuthDataFlags::UV),
+            backup_eligible: auth_data.flags.contains(AuthDataFlags::BE),
+            backup_state: auth_data.flags.contains(AuthDataFlags::BS),
+        };
+
+        // reject unsupported keys now rather than at the first login
+        credential.public_key()?;
+
+        Ok(credential)
+    }
+
+    /// Verify the response of an authentication ceremony with a stored credential.
+    ///
+    /// `client_data_json`, `auth_data` and `signature` are the fields of the
+    /// `AuthenticatorAssertionResponse`, `auth_data` is the raw authenticator data.
+    ///
+    /// On success, the signature counter and backup state of `credential` are updated, and the
+    /// caller should store it back.
+    ///
+    /// The signature counter must increase, unless both the stored and the received counter are 0.
+    /// Otherwise the authenticator may have been cloned, and this method return [Error::Verification].
+    pub fn verify_assertion(
+        &self,
+        challenge: impl AsRef<[u8]>,
+        credential: &mut RegisteredCredential,
+        client_data_json: &[u8],
+        auth_data: &[u8],
+        signature: &[u8],
+    ) -> Result<()> {
+        self.check_client_data(client_data_json, "webauthn.get", challenge.as_ref())?;
+
+        let parsed = AuthenticatorData::from_raw(auth_data)?;
+        self.check_auth_data(&parsed)?;
+
+        let mut verifier = AssertVerifier::new();
+        verifier.set_rp(&self.id)?;
+        verifier.set_client_data_hash(sha256(client_data_json))?;
+        verifier.set_auth_data_raw(auth_data)?;
+        verifier.set_signature(signature)?;
+        verifier.set_up(Opt::True)?;
+        if self.user_verification == UserVerification::Required {
+            verifier.set_uv(Opt::True)?;
+        }
+        verifier
+            .verify(credential.public_key()?)
+            .map_err(|_| Error::Verification("signature"))?;
+
+        if (parsed.sign_count != 0 || credential.sign_count != 0)
+            && parsed.sign_count <= credential.sign_count
+        {
+            return Err(Error::Verification("signature counter"));
+        }
+
+        credential.sign_count = parsed.sign_count;
+        credential.backup_state = parsed.flags.contains(AuthDataFlags::BS);
+
+        Ok(())
+    }
+
+    fn check_client_data(&self, json: &[u8], ty: &str, challenge: &[u8]) -> Result<()> {
+        let client_data = ClientData::parse(json)?;
+
+        if client_data.ty != ty {
+            return Err(Error::Verification("client data type"));
+        }
+        if client_data.challenge.len() != challenge.len()
+            || !openssl::memcmp::eq(&client_data.challenge, challenge)
+        {
+            return Err(Error::Verification("challenge"));
+        }
+        if !self.origins.contains(&client_data.origin) || client_data.cross_origin {
+            return Err(Error::Verification("origin"));
+        }
+
+        Ok(())
+    }
+
+    fn check_auth_data(&self, auth_data: &AuthenticatorData) -> Result<()> {
+        if auth_data.rp_id_hash != sha256(self.id.as_bytes()) {
+            return Err(Error::Verification("RP ID hash"));
+        }
+        if !auth_data.user_present() {
+            return Err(Error::Verification("user presence"));
+        }
+        if self.user_verification == UserVerification::Required && !auth_data.user_verified() {
+            return Err(Error::Verification("user verification"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::Private;
+    use openssl::sign::Signer;
+
+    const RP_ID: &str = "example.com";
+    const ORIGIN: &str = "https://example.com";
+    const AAGUID: [u8; 16] = [0x22; 16];
+    const CREDENTIAL_ID: [u8; 16] = [0x33; 16];
+
+    fn client_data(ty: &str, challenge: &Challenge, origin: &str) -> Vec<u8> {
+        format!(
+            r#"{{"type":"{ty}","challenge":"{}","origin":"{origin}"}}"#,
+            challenge.to_base64url()
+        )
+        .into_bytes()
+    }
+
+    fn key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn auth_data(rp_id: &str, flags: AuthDataFlags, sign_count: u32) -> Vec<u8> {
+        let mut data = sha256(rp_id.as_bytes()).to_vec();
+        data.push(flags.bits());
+        data.extend_from_slice(&sign_count.to_be_bytes());
+
+        data
+    }
+
+    fn attestation_object(key: &PKey<Private>, rp_id: &str, flags: AuthDataFlags) -> Vec<u8> {
+        let public = PKey::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap();
+
+        let mut auth_data = auth_data(rp_id, flags | AuthDataFlags::AT, 0);
+        auth_data.extend_from_slice(&AAGUID);
+        auth_data.extend_from_slice(&(CREDENTIAL_ID.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&CREDENTIAL_ID);
+        auth_data.extend_from_slice(&CoseKey::from_pkey(&public).unwrap().to_cbor());
+
+        let value = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (Value::from("authData"), Value::from(auth_data)),
+        ]);
+        let mut data = Vec::new();
+        ciborium::ser::into_writer(&value, &mut data).unwrap();
+
+        data
+    }
+
+    fn sign(key: &PKey<Private>, auth_data: &[u8], client_data_json: &[u8]) -> Vec<u8> {
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(auth_data).unwrap();
+        signer.update(&sha256(client_data_json)).unwrap();
+
+        signer.sign_to_vec().unwrap()
+    }
+
+    fn register(rp: &RelyingParty, key: &PKey<Private>) -> RegisteredCredential {
+        let challenge = Challenge::random().unwrap();
+        let client_data_json = client_data("webauthn.create", &challenge, ORIGIN);
+        let attestation_object = attestation_object(key, RP_ID, AuthDataFlags::UP);
+
+        rp.verify_registration(&challenge, &client_data_json, &attestation_object)
+            .unwrap()
+    }
+
+    /// Sign an assertion for `credential` with `auth_data` and verify it with `rp`.
+    fn assert(
+        rp: &RelyingParty,
+        key: &PKey<Private>,
+        credential: &mut RegisteredCredential,
+        auth_data: &[u8],
+    ) -> Result<()> {
+        let challenge = Challenge::random().unwrap();
+        let client_data_json = client_data("webauthn.get", &challenge, ORIGIN);
+        let signature = sign(key, auth_data, &client_data_json);
+
+        rp.verify_assertion(
+            &challenge,
+            credential,
+            &client_data_json,
+            auth_data,
+            &signature,
+        )
+    }
+
+    #[test]
+    fn client_data_parse() {
+        let json = br#"{"type":"webauthn.get","challenge":"AQID","origin":"https://a.example","crossOrigin":false}"#;
+        let client_data = ClientData::parse(json).unwrap();
+        assert_eq!(client_data.ty, "webauthn.get");
+        assert_eq!(client_data.challenge, [1, 2, 3]);
+        assert_eq!(client_data.origin, "https://a.example");
+        assert!(!client_data.cross_origin);
+
+        assert!(ClientData::parse(b"not json").is_err());
+        assert!(ClientData::parse(br#"{"type":"webauthn.get","origin":"o"}"#).is_err());
+        assert!(
+            ClientData::parse(br#"{"type":"webauthn.get","challenge":"!","origin":"o"}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn registration() {
+        let rp = RelyingParty::new(RP_ID, ORIGIN);
+        let key = key();
+
+        let credential = register(&rp, &key);
+        assert_eq!(credential.id, CREDENTIAL_ID);
+        assert_eq!(credential.aaguid, Aaguid::from(AAGUID));
+        assert_eq!(credential.sign_count, 0);
+        assert!(!credential.user_verified);
+        assert!(
+            credential
+                .public_key()
+                .unwrap()
+                .public_eq(&PKey::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap())
+        );
+    }
+
+    #[test]
+    fn registration_rejected() {
+        let mut rp = RelyingParty::new(RP_ID, ORIGIN);
+        let key = key();
+        let challenge = Challenge::random().unwrap();
+        let object = attestation_object(&key, RP_ID, AuthDataFlags::UP);
+
+        let verify = |rp: &RelyingParty, client_data_json: &[u8], object: &[u8]| {
+            rp.verify_registration(&challenge, client_data_json, object)
+        };
+
+        let other = Challenge::random().unwrap();
+        let json = client_data("webauthn.create", &other, ORIGIN);
+        assert!(matches!(
+            verify(&rp, &json, &object),
+            Err(Error::Verification("challenge"))
+        ));
+
+        let json = client_data("webauthn.get", &challenge, ORIGIN);
+        assert!(matches!(
+            verify(&rp, &json, &object),
+            Err(Error::Verification("client data type"))
+        ));
+
+        let json = client_data("webauthn.create", &challenge, "https://evil.example");
+        assert!(matches!(
+            verify(&rp, &json, &object),
+            Err(Error::Verification("origin"))
+        ));
+
+        let json = format!(
+            r#"{{"type":"webauthn.create","challenge":"{}","origin":"{ORIGIN}","crossOrigin":true}}"#,
+            challenge.to_base64url()
+        );
+        assert!(matches!(
+            verify(&rp, json.as_bytes(), &object),
+            Err(Error::Verification("origin"))
+        ));
+
+        let json = client_data("webauthn.create", &challenge, ORIGIN);
+        let other_rp = attestation_object(&key, "evil.example", AuthDataFlags::UP);
+        assert!(matches!(
+            verify(&rp, &json, &other_rp),
+            Err(Error::Verification("RP ID hash"))
+        ));
+
+        let no_up = attestation_object(&key, RP_ID, AuthDataFlags::empty());
+        assert!(matches!(
+            verify(&rp, &json, &no_up),
+            Err(Error::Verification("user presence"))
+        ));
+
+        rp.set_user_verification(UserVerification::Required);
+        assert!(matches!(
+            verify(&rp, &json, &object),
+            Err(Error::Verification("user verification"))
+        ));
+        let uv = attestation_object(&key, RP_ID, AuthDataFlags::UP | AuthDataFlags::UV);
+        assert!(verify(&rp, &json, &uv).unwrap().user_verified);
+    }
+
+    #[test]
+    fn additional_origin() {
+        let mut rp = RelyingParty::new(RP_ID, ORIGIN);
+        rp.add_origin("https://login.example.com");
+        let challenge = Challenge::random().unwrap();
+        let attestation_object = attestation_object(&key(), RP_ID, AuthDataFlags::UP);
+
+        let json = client_data("webauthn.create", &challenge, "https://login.example.com");
+        assert!(
+            rp.verify_registration(&challenge, &json, &attestation_object)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn assertion() {
+        let rp = RelyingParty::new(RP_ID, ORIGIN);
+        let key = key();
+        let mut credential = register(&rp, &key);
+
+        let flags = AuthDataFlags::UP | AuthDataFlags::BS;
+        assert(&rp, &key, &mut credential, &auth_data(RP_ID, flags, 5)).unwrap();
+        assert_eq!(credential.sign_count, 5);
+        assert!(credential.backup_state);
+
+        assert(
+            &rp,
+            &key,
+            &mut credential,
+            &auth_data(RP_ID, AuthDataFlags::UP, 6),
+        )
+        .unwrap();
+        assert_eq!(credential.sign_count, 6);
+        assert!(!credential.backup_state);
+    }
+
+    #[test]
+    fn assertion_zero_counter() {
+        let rp = RelyingParty::new(RP_ID, ORIGIN);
+        let key = key();
+        let mut credential = register(&rp, &key);
+
+        for _ in 0..2 {
+            assert(
+                &rp,
+                &key,
+                &mut credential,
+                &auth_data(RP_ID, AuthDataFlags::UP, 0),
+            )
+            .unwrap();
+        }
+        assert_eq!(credential.sign_count, 0);
+    }
+
+    #[test]
+    fn assertion_rejected() {
+        let mut rp = RelyingParty::new(RP_ID, ORIGIN);
+        let other_key = key();
+        let key = key();
+        let mut credential = register(&rp, &key);
+        assert(
+            &rp,
+            &key,
+            &mut credential,
+            &auth_data(RP_ID, AuthDataFlags::UP, 10),
+        )
+        .unwrap();
+
+        for sign_count in [10, 9, 0] {
+            let data = auth_data(RP_ID, AuthDataFlags::UP, sign_count);
+            assert!(matches!(
+                assert(&rp, &key, &mut credential, &data),
+                Err(Error::Verification("signature counter"))
+            ));
+        }
+        assert_eq!(credential.sign_count, 10);
+
+        let data = auth_data(RP_ID, AuthDataFlags::UP, 11);
+        assert!(matches!(
+            assert(&rp, &other_key, &mut credential, &data),
+            Err(Error::Verification("signature"))
+        ));
+
+        let other_rp = auth_data("evil.example", AuthDataFlags::UP, 11);
+        assert!(matches!(
+            assert(&rp, &key, &mut credential, &other_rp),
+            Err(Error::Verification("RP ID hash"))
+        ));
+
+        let no_up = auth_data(RP_ID, AuthDataFlags::empty(), 11);
+        assert!(matches!(
+            assert(&rp, &key, &mut credential, &no_up),
+            Err(Error::Verification("user presence"))
+        ));
+
+        rp.set_user_verification(UserVerification::Required);
+        assert!(matches!(
+            assert(&rp, &key, &mut credential, &data),
+            Err(Error::Verification("user verification"))
+        ));
+        let uv = auth_data(RP_ID, AuthDataFlags::UP | AuthDataFlags::UV, 11);
+        assert(&rp, &key, &mut credential, &uv).unwrap();
+
+        let challenge = Challenge::random().unwrap();
+        let json = client_data("webauthn.get", &challenge, ORIGIN);
+        let uv = auth_data(RP_ID, AuthDataFlags::UP | AuthDataFlags::UV, 12);
+        let signature = sign(&key, &uv, &json);
+        let other = Challenge::random().unwrap();
+        assert!(matches!(
+            rp.verify_assertion(&other, &mut credential, &json, &uv, &signature),
+            Err(Error::Verification("challenge"))
+        ));
+        let json = client_data("webauthn.create", &challenge, ORIGIN);
+        let signature = sign(&key, &uv, &json);
+        assert!(matches!(
+            rp.verify_assertion(&challenge, &mut credential, &json, &uv, &signature),
+            Err(Error::Verification("client data type"))
+        ));
+        assert_eq!(credential.sign_count, 11);
+    }
+}
@@ -0,0 +1,96 @@
+//! authenticatorConfig.
+use ciborium::Value;
+
+use super::pin::PERMISSION_ACFG;
+use super::{
+    CTAP_CBOR_CONFIG, CTAP2_ERR_INVALID_SUBCOMMAND, CtapResult, State, as_array, as_bytes, as_map,
+    as_text, as_uint, map_get,
+};
+
+const ENABLE_ENTERPRISE_ATTESTATION: u64 = 0x01;
+const TOGGLE_ALWAYS_UV: u64 = 0x02;
+const SET_MIN_PIN_LENGTH: u64 = 0x03;
+
+impl State {
+    pub(super) fn authenticator_config(
+        &mut self,
+        request: &[(Value, Value)],
+    ) -> CtapResult<Option<Value>> {
+        let sub_command = map_get(request, 0x01)
+            .map(as_uint)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+        let params = map_get(request, 0x02);
+
+        match sub_command {
+            ENABLE_ENTERPRISE_ATTESTATION | TOGGLE_ALWAYS_UV | SET_MIN_PIN_LENGTH => {}
+            _ => return Err(CTAP2_ERR_INVALID_SUBCOMMAND),
+        }
+
+        // pinUvAuthParam is computed over 32 * 0xff || 0x0d || subCommand || subCommandParams,
+        // and only required once a PIN is set
+        let pin_auth = map_get(request, 0x04).map(as_bytes).transpose()?;
+        match pin_auth {
+            Some(pin_auth) => {
+                let mut msg = vec![0xff; 32];
+                msg.extend_from_slice(&[CTAP_CBOR_CONFIG, sub_command as u8]);
+                if let Some(params) = params {
+                    msg.extend_from_slice(&super::encode(params));
+                }
+                self.check_pin_uv_auth(
+                    map_get(request, 0x03),
+                    pin_auth,
+                    &msg,
+                    PERMISSION_ACFG,
+                    None,
+                )?;
+            }
+            None if self.pin_hash.is_some() => return Err(ffi::FIDO_ERR_PIN_REQUIRED),
+            None => {}
+        }
+
+        match sub_command {
+            ENABLE_ENTERPRISE_ATTESTATION => self.enterprise_attestation = true,
+            TOGGLE_ALWAYS_UV => self.always_uv = !self.always_uv,
+            SET_MIN_PIN_LENGTH => {
+                let params = params.map(as_map).transpose()?.unwrap_or_default();
+                self.set_min_pin_length(params)?;
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(None)
+    }
+
+    fn set_min_pin_length(&mut self, params: &[(Value, Value)]) -> CtapResult<()> {
+        let min_pin_len = match map_get(params, 0x01).map(as_uint).transpose()? {
+            Some(len) => usize::try_from(len).map_err(|_| ffi::FIDO_ERR_PIN_POLICY_VIOLATION)?,
+            None => self.min_pin_len,
+        };
+        // minPinLengthRPIDs are accepted, but the minPinLength extension is not supported
+        if let Some(rp_ids) = map_get(params, 0x02) {
+            as_array(rp_ids)?
+                .iter()
+                .try_for_each(|it| as_text(it).map(drop))?;
+        }
+        let force_change = map_get(params, 0x03)
+            .map(|it| it.as_bool().ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE))
+            .transpose()?
+            .unwrap_or(false);
+
+        // the minimum PIN length can only be increased
+        if min_pin_len < self.min_pin_len {
+            return Err(ffi::FIDO_ERR_PIN_POLICY_VIOLATION);
+        }
+        if force_change && self.pin_hash.is_none() {
+            return Err(ffi::FIDO_ERR_PIN_NOT_SET);
+        }
+
+        self.min_pin_len = min_pin_len;
+        if force_change || (self.pin_hash.is_some() && self.pin_len < min_pin_len) {
+            self.force_pin_change = true;
+        }
+
+        Ok(())
+    }
+}
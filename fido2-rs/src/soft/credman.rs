@@ -0,0 +1,228 @@
+//! authenticatorCredentialManagement.
+use ciborium::Value;
+
+use super::ctap::{cose_public_key, descriptor_id};
+use super::pin::PERMISSION_CM;
+use super::{
+    CTAP2_ERR_INVALID_SUBCOMMAND, CtapResult, MAX_RESIDENT_KEYS, State, StoredCredential, as_bytes,
+    as_map, as_uint, map_get, map_get_text, sha256,
+};
+
+const GET_CREDS_METADATA: u64 = 0x01;
+const ENUMERATE_RPS_BEGIN: u64 = 0x02;
+const ENUMERATE_RPS_GET_NEXT_RP: u64 = 0x03;
+const ENUMERATE_CREDENTIALS_BEGIN: u64 = 0x04;
+const ENUMERATE_CREDENTIALS_GET_NEXT_CREDENTIAL: u64 = 0x05;
+const DELETE_CREDENTIAL: u64 = 0x06;
+const UPDATE_USER_INFORMATION: u64 = 0x07;
+
+impl State {
+    pub(super) fn credential_management(
+        &mut self,
+        request: &[(Value, Value)],
+    ) -> CtapResult<Option<Value>> {
+        let sub_command = map_get(request, 0x01)
+            .map(as_uint)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+        let params = map_get(request, 0x02);
+
+        match sub_command {
+            ENUMERATE_RPS_GET_NEXT_RP => return self.next_rp().map(Some),
+            ENUMERATE_CREDENTIALS_GET_NEXT_CREDENTIAL => return self.next_credential().map(Some),
+            GET_CREDS_METADATA
+            | ENUMERATE_RPS_BEGIN
+            | ENUMERATE_CREDENTIALS_BEGIN
+            | DELETE_CREDENTIAL
+            | UPDATE_USER_INFORMATION => {}
+            _ => return Err(CTAP2_ERR_INVALID_SUBCOMMAND),
+        }
+
+        // pinUvAuthParam is computed over subCommand || subCommandParams
+        let pin_auth = map_get(request, 0x04)
+            .map(as_bytes)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_PIN_REQUIRED)?;
+        let mut msg = vec![sub_command as u8];
+        if let Some(params) = params {
+            msg.extend_from_slice(&super::encode(params));
+        }
+        self.check_pin_uv_auth(map_get(request, 0x03), pin_auth, &msg, PERMISSION_CM, None)?;
+
+        let params = params.map(as_map).transpose()?.unwrap_or_default();
+
+        match sub_command {
+            GET_CREDS_METADATA => {
+                let count = self.resident_credentials().count();
+
+                Ok(Some(Value::Map(vec![
+                    (Value::from(0x01), Value::from(count as u64)),
+                    (
+                        Value::from(0x02),
+                        Value::from((MAX_RESIDENT_KEYS - count) as u64),
+                    ),
+                ])))
+            }
+            ENUMERATE_RPS_BEGIN => {
+                let mut rps = Vec::<String>::new();
+                for credential in self.credentials.iter().filter(|it| it.discoverable) {
+                    if !rps.contains(&credential.rp_id) {
+                        rps.push(credential.rp_id.clone());
+                    }
+                }
+
+                let total = rps.len();
+                self.next_rps = rps.into();
+
+                let mut response = self.next_rp()?;
+                if let Value::Map(response) = &mut response {
+                    response.push((Value::from(0x05), Value::from(total as u64)));
+                }
+
+                Ok(Some(response))
+            }
+            ENUMERATE_CREDENTIALS_BEGIN => {
+                let rp_id_hash = map_get(params, 0x01)
+                    .map(as_bytes)
+                    .transpose()?
+                    .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+
+                let indexes = self
+                    .credentials
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, it)| {
+                        it.discoverable && sha256(it.rp_id.as_bytes()).as_slice() == rp_id_hash
+                    })
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>();
+
+                let total = indexes.len();
+                self.next_credentials = indexes.into();
+
+                // libfido2 requires the keys in canonical order, totalCredentials comes before credProtect
+                let mut response = self.next_credential()?;
+                if let Value::Map(response) = &mut response {
+                    let at = response
+                        .iter()
+                        .position(|(k, _)| *k == Value::from(0x0a))
+                        .unwrap_or(response.len());
+                    response.insert(at, (Value::from(0x09), Value::from(total as u64)));
+                }
+
+                Ok(Some(response))
+            }
+            DELETE_CREDENTIAL => {
+                let id = map_get(params, 0x02)
+                    .map(descriptor_id)
+                    .transpose()?
+                    .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+
+                let index = self
+                    .credentials
+                    .iter()
+                    .position(|it| it.discoverable && it.id == id)
+                    .ok_or(ffi::FIDO_ERR_NO_CREDENTIALS)?;
+                self.credentials.remove(index);
+                self.next_credentials.clear();
+
+                Ok(None)
+            }
+            _ => {
+                let id = map_get(params, 0x02).map(descriptor_id).transpose()?;
+                let user = map_get(params, 0x03).map(as_map).transpose()?;
+                let (Some(id), Some(user)) = (id, user) else {
+                    return Err(ffi::FIDO_ERR_MISSING_PARAMETER);
+                };
+
+                let credential = self
+                    .credentials
+                    .iter_mut()
+                    .find(|it| it.discoverable && it.id == id)
+                    .ok_or(ffi::FIDO_ERR_NO_CREDENTIALS)?;
+
+                let user_id = map_get_text(user, "id").map(as_bytes).transpose()?;
+                if user_id != Some(credential.user_id.as_slice()) {
+                    return Err(ffi::FIDO_ERR_INVALID_PARAMETER);
+                }
+
+                credential.user_name = map_get_text(user, "name")
+                    .and_then(Value::as_text)
+                    .map(str::to_owned);
+                credential.display_name = map_get_text(user, "displayName")
+                    .and_then(Value::as_text)
+                    .map(str::to_owned);
+
+                Ok(None)
+            }
+        }
+    }
+
+    fn resident_credentials(&self) -> impl Iterator<Item = &StoredCredential> {
+        self.credentials.iter().filter(|it| it.discoverable)
+    }
+
+    fn next_rp(&mut self) -> CtapResult<Value> {
+        let rp_id = self
+            .next_rps
+            .pop_front()
+            .ok_or(ffi::FIDO_ERR_NO_CREDENTIALS)?;
+        let rp_name = self
+            .resident_credentials()
+            .filter(|it| it.rp_id == rp_id)
+            .find_map(|it| it.rp_name.clone());
+
+        let mut rp = vec![(Value::from("id"), Value::from(rp_id.as_str()))];
+        if let Some(rp_name) = rp_name {
+            rp.push((Value::from("name"), Value::from(rp_name)));
+        }
+
+        Ok(Value::Map(vec![
+            (Value::from(0x03), Value::Map(rp)),
+            (
+                Value::from(0x04),
+                Value::from(&sha256(rp_id.as_bytes())[..]),
+            ),
+        ]))
+    }
+
+    fn next_credential(&mut self) -> CtapResult<Value> {
+        let index = self
+            .next_credentials
+            .pop_front()
+            .ok_or(ffi::FIDO_ERR_NO_CREDENTIALS)?;
+        let credential = self
+            .credentials
+            .get(index)
+            .ok_or(ffi::FIDO_ERR_NO_CREDENTIALS)?;
+
+        let mut user = vec![(Value::from("id"), Value::from(credential.user_id.clone()))];
+        if let Some(name) = &credential.user_name {
+            user.push((Value::from("name"), Value::from(name.as_str())));
+        }
+        if let Some(display_name) = &credential.display_name {
+            user.push((
+                Value::from("displayName"),
+                Value::from(display_name.as_str()),
+            ));
+        }
+
+        let mut response = vec![
+            (Value::from(0x06), Value::Map(user)),
+            (
+                Value::from(0x07),
+                Value::Map(vec![
+                    (Value::from("id"), Value::from(credential.id.clone())),
+                    (Value::from("type"), Value::from("public-key")),
+                ]),
+            ),
+            (Value::from(0x08), cose_public_key(credential)),
+            (Value::from(0x0a), Value::from(credential.cred_protect)),
+        ];
+        if let Some(key) = &credential.large_blob_key {
+            response.push((Value::from(0x0b), Value::from(key.clone())));
+        }
+
+        Ok(Value::Map(response))
+    }
+}
@@ -0,0 +1,574 @@
+//! authenticatorMakeCredential, authenticatorGetAssertion and authenticatorGetNextAssertion.
+use ciborium::Value;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Private};
+use openssl::sign::Signer;
+
+use super::pin::{self, PERMISSION_GA, PERMISSION_MC};
+use super::{
+    AAGUID, CtapResult, MAX_RESIDENT_KEYS, State, StoredCredential, as_array, as_bytes, as_int,
+    as_map, as_text, as_uint, map_get, map_get_text, random_bytes, sha256,
+};
+
+const FLAG_UP: u8 = 0x01;
+const FLAG_UV: u8 = 0x04;
+const FLAG_AT: u8 = 0x40;
+const FLAG_ED: u8 = 0x80;
+
+const CRED_ID_LEN: usize = 32;
+
+/// An hmac-secret extension request of getAssertion.
+#[derive(Clone)]
+struct HmacSecretRequest {
+    protocol: u8,
+    shared: Vec<u8>,
+    salt: Vec<u8>,
+}
+
+/// Everything needed to answer an authenticatorGetNextAssertion.
+#[derive(Clone)]
+pub(super) struct PendingAssertion {
+    credential_id: Vec<u8>,
+    client_data_hash: Vec<u8>,
+    up: bool,
+    uv: bool,
+    hmac_secret: Option<HmacSecretRequest>,
+    large_blob_key: bool,
+}
+
+impl State {
+    pub(super) fn make_credential(&mut self, request: &[(Value, Value)]) -> CtapResult<Value> {
+        let client_data_hash = map_get(request, 0x01).map(as_bytes).transpose()?;
+        let rp = map_get(request, 0x02).map(as_map).transpose()?;
+        let user = map_get(request, 0x03).map(as_map).transpose()?;
+        let params = map_get(request, 0x04).map(as_array).transpose()?;
+        let (Some(client_data_hash), Some(rp), Some(user), Some(params)) =
+            (client_data_hash, rp, user, params)
+        else {
+            return Err(ffi::FIDO_ERR_MISSING_PARAMETER);
+        };
+
+        let pin_auth = map_get(request, 0x08).map(as_bytes).transpose()?;
+        if pin_auth.is_some_and(<[u8]>::is_empty) {
+            return self.touch_probe();
+        }
+
+        // only platform-managed enterprise attestation is granted, no RP ID is pre-configured
+        // for vendor-facilitated enterprise attestation
+        let enterprise_attestation = map_get(request, 0x0a).map(as_uint).transpose()?;
+        self.last_enterprise_attestation = enterprise_attestation;
+        let ep_att = match enterprise_attestation {
+            None => false,
+            Some(_) if !self.enterprise_attestation => return Err(ffi::FIDO_ERR_INVALID_PARAMETER),
+            Some(1) => false,
+            Some(2) => true,
+            Some(_) => return Err(ffi::FIDO_ERR_INVALID_OPTION),
+        };
+
+        let rp_id = map_get_text(rp, "id")
+            .map(as_text)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+        let rp_name = map_get_text(rp, "name").map(as_text).transpose()?;
+        let user_id = map_get_text(user, "id")
+            .map(as_bytes)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+        let user_name = map_get_text(user, "name").map(as_text).transpose()?;
+        let display_name = map_get_text(user, "displayName").map(as_text).transpose()?;
+
+        let mut alg = None;
+        for param in params {
+            let param = as_map(param)?;
+            if let Some(value) = map_get_text(param, "alg") {
+                let value = as_int(value)?;
+                if value == i64::from(ffi::COSE_ES256) || value == i64::from(ffi::COSE_EDDSA) {
+                    alg = Some(value as i32);
+                    break;
+                }
+            }
+        }
+        let alg = alg.ok_or(ffi::FIDO_ERR_UNSUPPORTED_ALGORITHM)?;
+
+        let options = map_get(request, 0x07).map(as_map).transpose()?;
+        let rk = option(options, "rk")?.unwrap_or(false);
+        if option(options, "uv")? == Some(true) || option(options, "up")? == Some(false) {
+            return Err(ffi::FIDO_ERR_INVALID_OPTION);
+        }
+
+        let uv = match pin_auth {
+            Some(pin_auth) => {
+                self.check_pin_uv_auth(
+                    map_get(request, 0x09),
+                    pin_auth,
+                    client_data_hash,
+                    PERMISSION_MC,
+                    Some(rp_id),
+                )?;
+                true
+            }
+            None if self.pin_hash.is_some() && (rk || self.always_uv) => {
+                return Err(ffi::FIDO_ERR_PIN_REQUIRED);
+            }
+            None if self.always_uv => return Err(ffi::FIDO_ERR_PIN_NOT_SET),
+            None => false,
+        };
+
+        let mut cred_protect = 1;
+        let mut hmac_secret = false;
+        let mut large_blob_key = false;
+        if let Some(extensions) = map_get(request, 0x06) {
+            for (key, value) in as_map(extensions)? {
+                match key.as_text() {
+                    Some("credProtect") => {
+                        cred_protect = match as_uint(value)? {
+                            level @ 1..=3 => level as u8,
+                            _ => return Err(ffi::FIDO_ERR_INVALID_OPTION),
+                        }
+                    }
+                    Some("hmac-secret") => {
+                        hmac_secret = value.as_bool().ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)?
+                    }
+                    Some("largeBlobKey") => {
+                        large_blob_key =
+                            value.as_bool().ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)?;
+                        if !large_blob_key {
+                            return Err(ffi::FIDO_ERR_INVALID_OPTION);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if large_blob_key && !rk {
+            return Err(ffi::FIDO_ERR_INVALID_OPTION);
+        }
+
+        if let Some(exclude_list) = map_get(request, 0x05) {
+            for descriptor in as_array(exclude_list)? {
+                let id = descriptor_id(descriptor)?;
+                let excluded = self
+                    .credentials
+                    .iter()
+                    .any(|it| it.id == id && it.rp_id == rp_id && (it.cred_protect < 3 || uv));
+                if excluded {
+                    self.check_user_presence()?;
+                    return Err(ffi::FIDO_ERR_CREDENTIAL_EXCLUDED);
+                }
+            }
+        }
+
+        self.check_user_presence()?;
+
+        if rk {
+            self.credentials
+                .retain(|it| !(it.discoverable && it.rp_id == rp_id && it.user_id == user_id));
+
+            let count = self.credentials.iter().filter(|it| it.discoverable).count();
+            if count >= MAX_RESIDENT_KEYS {
+                return Err(ffi::FIDO_ERR_KEY_STORE_FULL);
+            }
+        }
+
+        let credential = StoredCredential {
+            id: random_bytes(CRED_ID_LEN),
+            rp_id: rp_id.to_owned(),
+            rp_name: rp_name.map(str::to_owned),
+            user_id: user_id.to_vec(),
+            user_name: user_name.map(str::to_owned),
+            display_name: display_name.map(str::to_owned),
+            key: generate_key(alg),
+            alg,
+            discoverable: rk,
+            cred_protect,
+            large_blob_key: large_blob_key.then(|| random_bytes(32)),
+            cred_random: hmac_secret.then(|| (random_bytes(32), random_bytes(32))),
+        };
+
+        let mut ext_outputs = vec![];
+        if cred_protect > 1 {
+            ext_outputs.push((Value::from("credProtect"), Value::from(cred_protect)));
+        }
+        if hmac_secret {
+            ext_outputs.push((Value::from("hmac-secret"), Value::from(true)));
+        }
+
+        let mut flags = FLAG_UP | FLAG_AT;
+        if uv {
+            flags |= FLAG_UV;
+        }
+        if !ext_outputs.is_empty() {
+            flags |= FLAG_ED;
+        }
+
+        let counter = self.next_counter();
+        let mut auth_data = auth_data_header(rp_id, flags, counter);
+        auth_data.extend_from_slice(&AAGUID);
+        auth_data.extend_from_slice(&(credential.id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&credential.id);
+        auth_data.extend_from_slice(&super::encode(&cose_public_key(&credential)));
+        if !ext_outputs.is_empty() {
+            auth_data.extend_from_slice(&super::encode(&Value::Map(ext_outputs)));
+        }
+
+        let att_stmt = match &self.attestation {
+            Some(attestation) => Value::Map(vec![
+                (Value::from("alg"), Value::from(ffi::COSE_ES256)),
+                (
+                    Value::from("sig"),
+                    Value::from(sign(&attestation.key, &auth_data, client_data_hash)),
+                ),
+                (
+                    Value::from("x5c"),
+                    Value::Array(vec![Value::from(attestation.certificate.clone())]),
+                ),
+            ]),
+            None => Value::Map(vec![
+                (Value::from("alg"), Value::from(alg)),
+                (
+                    Value::from("sig"),
+                    Value::from(sign(&credential.key, &auth_data, client_data_hash)),
+                ),
+            ]),
+        };
+
+        let mut response = vec![
+            (Value::from(0x01), Value::from("packed")),
+            (Value::from(0x02), Value::from(auth_data)),
+            (Value::from(0x03), att_stmt),
+        ];
+        if ep_att {
+            response.push((Value::from(0x04), Value::from(true)));
+        }
+        if let Some(key) = &credential.large_blob_key {
+            response.push((Value::from(0x05), Value::from(key.clone())));
+        }
+
+        self.credentials.push(credential);
+
+        Ok(Value::Map(response))
+    }
+
+    pub(super) fn get_assertion(&mut self, request: &[(Value, Value)]) -> CtapResult<Value> {
+        let rp_id = map_get(request, 0x01).map(as_text).transpose()?;
+        let client_data_hash = map_get(request, 0x02).map(as_bytes).transpose()?;
+        let (Some(rp_id), Some(client_data_hash)) = (rp_id, client_data_hash) else {
+            return Err(ffi::FIDO_ERR_MISSING_PARAMETER);
+        };
+
+        let pin_auth = map_get(request, 0x06).map(as_bytes).transpose()?;
+        if pin_auth.is_some_and(<[u8]>::is_empty) {
+            return self.touch_probe();
+        }
+
+        let options = map_get(request, 0x05).map(as_map).transpose()?;
+        let up = option(options, "up")?.unwrap_or(true);
+        if option(options, "uv")? == Some(true) {
+            return Err(ffi::FIDO_ERR_INVALID_OPTION);
+        }
+
+        let uv = match pin_auth {
+            Some(pin_auth) => {
+                self.check_pin_uv_auth(
+                    map_get(request, 0x07),
+                    pin_auth,
+                    client_data_hash,
+                    PERMISSION_GA,
+                    Some(rp_id),
+                )?;
+                true
+            }
+            None if self.always_uv && up && self.pin_hash.is_some() => {
+                return Err(ffi::FIDO_ERR_PIN_REQUIRED);
+            }
+            None if self.always_uv && up => return Err(ffi::FIDO_ERR_PIN_NOT_SET),
+            None => false,
+        };
+
+        let allow_list = map_get(request, 0x03)
+            .map(as_array)
+            .transpose()?
+            .unwrap_or_default()
+            .iter()
+            .map(descriptor_id)
+            .collect::<CtapResult<Vec<_>>>()?;
+
+        let mut hmac_secret = None;
+        let mut large_blob_key = false;
+        if let Some(extensions) = map_get(request, 0x04) {
+            for (key, value) in as_map(extensions)? {
+                match key.as_text() {
+                    Some("hmac-secret") => hmac_secret = Some(self.hmac_secret_request(value)?),
+                    Some("largeBlobKey") => {
+                        large_blob_key =
+                            value.as_bool().ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)?;
+                        if !large_blob_key {
+                            return Err(ffi::FIDO_ERR_INVALID_OPTION);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let visible = |it: &&StoredCredential| match it.cred_protect {
+            3 => uv,
+            2 => uv || !allow_list.is_empty(),
+            _ => true,
+        };
+        let mut matching = if allow_list.is_empty() {
+            self.credentials
+                .iter()
+                .rev()
+                .filter(|it| it.discoverable && it.rp_id == rp_id)
+                .filter(visible)
+                .map(|it| it.id.clone())
+                .collect::<Vec<_>>()
+        } else {
+            self.credentials
+                .iter()
+                .filter(|it| it.rp_id == rp_id && allow_list.contains(&it.id))
+                .filter(visible)
+                .map(|it| it.id.clone())
+                .take(1)
+                .collect::<Vec<_>>()
+        };
+
+        if up {
+            self.check_user_presence()?;
+        }
+
+        if matching.is_empty() {
+            return Err(ffi::FIDO_ERR_NO_CREDENTIALS);
+        }
+
+        let count = matching.len();
+        let first = matching.remove(0);
+        let pending = PendingAssertion {
+            credential_id: first,
+            client_data_hash: client_data_hash.to_vec(),
+            up,
+            uv,
+            hmac_secret,
+            large_blob_key,
+        };
+
+        self.next_assertions = matching
+            .into_iter()
+            .map(|id| PendingAssertion {
+                credential_id: id,
+                ..pending.clone()
+            })
+            .collect();
+
+        self.assertion(
+            &pending,
+            (count > 1).then_some(count),
+            allow_list.is_empty(),
+        )
+    }
+
+    pub(super) fn get_next_assertion(&mut self) -> CtapResult<Value> {
+        let pending = self
+            .next_assertions
+            .pop_front()
+            .ok_or(ffi::FIDO_ERR_NOT_ALLOWED)?;
+
+        self.assertion(&pending, None, true)
+    }
+
+    fn assertion(
+        &mut self,
+        pending: &PendingAssertion,
+        count: Option<usize>,
+        with_user: bool,
+    ) -> CtapResult<Value> {
+        let counter = self.next_counter();
+        let credential = self
+            .credentials
+            .iter()
+            .find(|it| it.id == pending.credential_id)
+            .ok_or(ffi::FIDO_ERR_NO_CREDENTIALS)?;
+
+        let mut ext_outputs = vec![];
+        if let (Some(request), Some((without_uv, with_uv))) =
+            (&pending.hmac_secret, &credential.cred_random)
+        {
+            let cred_random = if pending.uv { with_uv } else { without_uv };
+            let mut output = pin::hmac_sha256(cred_random, &request.salt[..32]);
+            if request.salt.len() == 64 {
+                output.extend_from_slice(&pin::hmac_sha256(cred_random, &request.salt[32..]));
+            }
+
+            let output = pin::encrypt(request.protocol, &request.shared, &output)?;
+            ext_outputs.push((Value::from("hmac-secret"), Value::from(output)));
+        }
+
+        let mut flags = 0;
+        if pending.up {
+            flags |= FLAG_UP;
+        }
+        if pending.uv {
+            flags |= FLAG_UV;
+        }
+        if !ext_outputs.is_empty() {
+            flags |= FLAG_ED;
+        }
+
+        let mut auth_data = auth_data_header(&credential.rp_id, flags, counter);
+        if !ext_outputs.is_empty() {
+            auth_data.extend_from_slice(&super::encode(&Value::Map(ext_outputs)));
+        }
+
+        let sig = sign(&credential.key, &auth_data, &pending.client_data_hash);
+
+        let mut response = vec![
+            (
+                Value::from(0x01),
+                Value::Map(vec![
+                    (Value::from("id"), Value::from(credential.id.clone())),
+                    (Value::from("type"), Value::from("public-key")),
+                ]),
+            ),
+            (Value::from(0x02), Value::from(auth_data)),
+            (Value::from(0x03), Value::from(sig)),
+        ];
+
+        if with_user && credential.discoverable {
+            let mut user = vec![(Value::from("id"), Value::from(credential.user_id.clone()))];
+            if pending.uv {
+                if let Some(name) = &credential.user_name {
+                    user.push((Value::from("name"), Value::from(name.as_str())));
+                }
+                if let Some(display_name) = &credential.display_name {
+                    user.push((
+                        Value::from("displayName"),
+                        Value::from(display_name.as_str()),
+                    ));
+                }
+            }
+            response.push((Value::from(0x04), Value::Map(user)));
+        }
+        if let Some(count) = count {
+            response.push((Value::from(0x05), Value::from(count as u64)));
+        }
+        if let Some(key) = &credential.large_blob_key
+            && pending.large_blob_key
+        {
+            response.push((Value::from(0x07), Value::from(key.clone())));
+        }
+
+        Ok(Value::Map(response))
+    }
+
+    fn hmac_secret_request(&self, value: &Value) -> CtapResult<HmacSecretRequest> {
+        let value = as_map(value)?;
+        let key_agreement = map_get(value, 0x01);
+        let salt_enc = map_get(value, 0x02).map(as_bytes).transpose()?;
+        let salt_auth = map_get(value, 0x03).map(as_bytes).transpose()?;
+        let (Some(key_agreement), Some(salt_enc), Some(salt_auth)) =
+            (key_agreement, salt_enc, salt_auth)
+        else {
+            return Err(ffi::FIDO_ERR_MISSING_PARAMETER);
+        };
+
+        let protocol = match map_get(value, 0x04).map(as_uint).transpose()? {
+            None | Some(1) => 1,
+            Some(2) => 2,
+            Some(_) => return Err(ffi::FIDO_ERR_INVALID_PARAMETER),
+        };
+
+        let shared = pin::shared_secret(protocol, &self.key_agreement, key_agreement)?;
+        if !pin::verify(protocol, &shared, salt_enc, salt_auth) {
+            return Err(ffi::FIDO_ERR_INVALID_PARAMETER);
+        }
+
+        let salt = pin::decrypt(protocol, &shared, salt_enc)?;
+        if salt.len() != 32 && salt.len() != 64 {
+            return Err(ffi::FIDO_ERR_INVALID_LENGTH);
+        }
+
+        Ok(HmacSecretRequest {
+            protocol,
+            shared,
+            salt,
+        })
+    }
+}
+
+fn option(options: Option<&[(Value, Value)]>, name: &str) -> CtapResult<Option<bool>> {
+    options
+        .and_then(|it| map_get_text(it, name))
+        .map(|it| it.as_bool().ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE))
+        .transpose()
+}
+
+/// Return the id of a PublicKeyCredentialDescriptor.
+pub(super) fn descriptor_id(descriptor: &Value) -> CtapResult<Vec<u8>> {
+    let descriptor = as_map(descriptor)?;
+    map_get_text(descriptor, "id")
+        .map(as_bytes)
+        .transpose()?
+        .map(<[u8]>::to_vec)
+        .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)
+}
+
+fn auth_data_header(rp_id: &str, flags: u8, counter: u32) -> Vec<u8> {
+    let mut auth_data = sha256(rp_id.as_bytes()).to_vec();
+    auth_data.push(flags);
+    auth_data.extend_from_slice(&counter.to_be_bytes());
+    auth_data
+}
+
+fn generate_key(alg: i32) -> PKey<Private> {
+    if alg == ffi::COSE_EDDSA {
+        PKey::generate_ed25519().expect("generate ed25519 key")
+    } else {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).expect("p256 group");
+        let ec = EcKey::generate(&group).expect("generate p256 key");
+        PKey::from_ec_key(ec).expect("p256 pkey")
+    }
+}
+
+/// Encode the public key of `credential` as a COSE_Key.
+pub(super) fn cose_public_key(credential: &StoredCredential) -> Value {
+    let key = &credential.key;
+    if key.id() == Id::ED25519 {
+        let x = key.raw_public_key().expect("ed25519 public key");
+
+        Value::Map(vec![
+            (Value::from(1), Value::from(1)),
+            (Value::from(3), Value::from(credential.alg)),
+            (Value::from(-1), Value::from(6)),
+            (Value::from(-2), Value::from(x)),
+        ])
+    } else {
+        let ec = key.ec_key().expect("p256 key");
+        let (x, y) = pin::affine_coordinates(ec.group(), ec.public_key());
+
+        Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(credential.alg)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::from(x)),
+            (Value::from(-3), Value::from(y)),
+        ])
+    }
+}
+
+fn sign(key: &PKey<Private>, auth_data: &[u8], client_data_hash: &[u8]) -> Vec<u8> {
+    let mut msg = auth_data.to_vec();
+    msg.extend_from_slice(client_data_hash);
+
+    if key.id() == Id::ED25519 {
+        let mut signer = Signer::new_without_digest(key).expect("ed25519 signer");
+        signer.sign_oneshot_to_vec(&msg).expect("ed25519 sign")
+    } else {
+        let mut signer = Signer::new(MessageDigest::sha256(), key).expect("es256 signer");
+        signer.update(&msg).expect("es256 update");
+        signer.sign_to_vec().expect("es256 sign")
+    }
+}
@@ -0,0 +1,122 @@
+//! authenticatorLargeBlobs.
+use ciborium::Value;
+use openssl::memcmp;
+
+use super::pin::PERMISSION_LBW;
+use super::{
+    CTAP2_ERR_INTEGRITY_FAILURE, CtapResult, MAX_LARGE_BLOB, MAX_MSG_SIZE, State, as_bytes,
+    as_uint, map_get, sha256,
+};
+
+/// Maximum fragment length, as computed by platforms from maxMsgSize.
+const MAX_FRAGMENT_LEN: usize = MAX_MSG_SIZE - 64;
+
+/// The initial serialized largeBlob array: an empty CBOR array followed by its checksum.
+pub(super) fn empty_array() -> Vec<u8> {
+    let mut array = vec![0x80];
+    array.extend_from_slice(&sha256(&array)[..16]);
+    array
+}
+
+impl State {
+    pub(super) fn large_blobs(&mut self, request: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        let get = map_get(request, 0x01).map(as_uint).transpose()?;
+        let set = map_get(request, 0x02).map(as_bytes).transpose()?;
+        let offset = map_get(request, 0x03)
+            .map(as_uint)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)? as usize;
+        let length = map_get(request, 0x04).map(as_uint).transpose()?;
+
+        match (get, set) {
+            (Some(get), None) => {
+                if length.is_some() {
+                    return Err(ffi::FIDO_ERR_INVALID_PARAMETER);
+                }
+                if get as usize > MAX_FRAGMENT_LEN {
+                    return Err(ffi::FIDO_ERR_INVALID_LENGTH);
+                }
+                if offset > self.large_blob.len() {
+                    return Err(ffi::FIDO_ERR_INVALID_PARAMETER);
+                }
+
+                let end = self.large_blob.len().min(offset + get as usize);
+
+                Ok(Some(Value::Map(vec![(
+                    Value::from(0x01),
+                    Value::from(&self.large_blob[offset..end]),
+                )])))
+            }
+            (None, Some(set)) => {
+                if set.len() > MAX_FRAGMENT_LEN {
+                    return Err(ffi::FIDO_ERR_INVALID_LENGTH);
+                }
+
+                if offset == 0 {
+                    let length = length.ok_or(ffi::FIDO_ERR_INVALID_PARAMETER)? as usize;
+                    if length > MAX_LARGE_BLOB {
+                        return Err(ffi::FIDO_ERR_LARGEBLOB_STORAGE_FULL);
+                    }
+                    if length < 17 {
+                        return Err(ffi::FIDO_ERR_INVALID_PARAMETER);
+                    }
+
+                    self.pending_large_blob = Some((Vec::with_capacity(length), length));
+                } else if length.is_some() {
+                    return Err(ffi::FIDO_ERR_INVALID_PARAMETER);
+                }
+
+                match &self.pending_large_blob {
+                    Some((pending, _)) if pending.len() == offset => {}
+                    _ => return Err(ffi::FIDO_ERR_INVALID_SEQ),
+                }
+
+                let pin_auth = map_get(request, 0x05).map(as_bytes).transpose()?;
+                match pin_auth {
+                    Some(pin_auth) => {
+                        // 32 × 0xff || h'0c00' || uint32LittleEndian(offset) || SHA-256(set)
+                        let mut msg = vec![0xff; 32];
+                        msg.extend_from_slice(&[0x0c, 0x00]);
+                        msg.extend_from_slice(&(offset as u32).to_le_bytes());
+                        msg.extend_from_slice(&sha256(set));
+
+                        self.check_pin_uv_auth(
+                            map_get(request, 0x06),
+                            pin_auth,
+                            &msg,
+                            PERMISSION_LBW,
+                            None,
+                        )?;
+                    }
+                    None if self.pin_hash.is_some() => return Err(ffi::FIDO_ERR_PIN_REQUIRED),
+                    None => {}
+                }
+
+                let Some((pending, length)) = &mut self.pending_large_blob else {
+                    return Err(ffi::FIDO_ERR_INVALID_SEQ);
+                };
+                if pending.len() + set.len() > *length {
+                    return Err(ffi::FIDO_ERR_INVALID_PARAMETER);
+                }
+                pending.extend_from_slice(set);
+
+                if pending.len() == *length {
+                    let (array, checksum) = pending.split_at(pending.len() - 16);
+                    if !memcmp::eq(&sha256(array)[..16], checksum) {
+                        self.pending_large_blob = None;
+                        return Err(CTAP2_ERR_INTEGRITY_FAILURE);
+                    }
+
+                    self.large_blob = self
+                        .pending_large_blob
+                        .take()
+                        .map(|(array, _)| array)
+                        .unwrap_or_default();
+                }
+
+                Ok(None)
+            }
+            _ => Err(ffi::FIDO_ERR_INVALID_PARAMETER),
+        }
+    }
+}
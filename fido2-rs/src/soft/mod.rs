@@ -0,0 +1,670 @@
+//! In-process software authenticator.
+//!
+//! [SoftAuthenticator] is a CTAP2 authenticator that keeps all its state in memory and is
+//! attached to a [Device] through a [Transport], so the whole crate can be exercised without
+//! a hardware key.
+//!
+//! It supports makeCredential, getAssertion, clientPIN (PIN/UV auth protocol 1 and 2),
+//! credential management, largeBlobs and authenticatorConfig (enterprise attestation, alwaysUv and
+//! minimum PIN length), with the credProtect, hmac-secret and largeBlobKey extensions. Credentials are always ES256 or EdDSA, with self attestation, or
+//! basic attestation after [SoftAuthenticator::set_attestation].
+//!
+//! Enterprise attestation is supported once enabled with authenticatorConfig. Only
+//! platform-managed enterprise attestation is granted, as no RP ID is pre-configured for
+//! vendor-facilitated enterprise attestation.
+//!
+//! User presence is granted at once by default. [SoftAuthenticator::set_wait_for_touch] makes
+//! requests wait for [SoftAuthenticator::touch] instead, so they can be polled and cancelled like
+//! on a hardware key.
+//!
+//! **This is meant for testing only, no secret is protected in any way.**
+//!
+//! # Example
+//! ```rust,no_run
+//! use fido2_rs::soft::SoftAuthenticator;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let authenticator = SoftAuthenticator::new();
+//!     let dev = authenticator.open()?;
+//!
+//!     dev.set_pin("1234", None)?;
+//!     assert!(dev.has_pin());
+//!
+//!     Ok(())
+//! }
+//! ```
+mod config;
+mod credman;
+mod ctap;
+mod largeblob;
+mod pin;
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use ciborium::Value;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::X509Ref;
+
+use crate::device::Device;
+use crate::error::Result;
+use crate::info::PinProtocol;
+use crate::transport::Transport;
+
+use self::pin::PinToken;
+
+const CTAP_CMD_INIT: u8 = 0x06;
+const CTAP_CMD_CBOR: u8 = 0x10;
+const CTAP_CMD_CANCEL: u8 = 0x11;
+
+const CTAP_CBOR_MAKECRED: u8 = 0x01;
+const CTAP_CBOR_ASSERT: u8 = 0x02;
+const CTAP_CBOR_GETINFO: u8 = 0x04;
+const CTAP_CBOR_CLIENT_PIN: u8 = 0x06;
+const CTAP_CBOR_RESET: u8 = 0x07;
+const CTAP_CBOR_NEXT_ASSERT: u8 = 0x08;
+const CTAP_CBOR_CRED_MGMT: u8 = 0x0a;
+const CTAP_CBOR_LARGEBLOB: u8 = 0x0c;
+const CTAP_CBOR_CONFIG: u8 = 0x0d;
+const CTAP_CBOR_CRED_MGMT_PRE: u8 = 0x41;
+
+/// CTAP2 status codes without a `FIDO_ERR_*` counterpart in libfido2.
+const CTAP2_ERR_INTEGRITY_FAILURE: i32 = 0x3d;
+const CTAP2_ERR_INVALID_SUBCOMMAND: i32 = 0x3e;
+
+/// AAGUID reported by [SoftAuthenticator].
+pub const AAGUID: [u8; 16] = *b"fido2-rs softkey";
+
+const MAX_MSG_SIZE: usize = 2048;
+const MAX_CRED_COUNT_IN_LIST: usize = 8;
+const MAX_CRED_ID_LEN: usize = 64;
+const MAX_RESIDENT_KEYS: usize = 64;
+const MAX_LARGE_BLOB: usize = 4096;
+const MIN_PIN_LEN: usize = 4;
+const PIN_RETRIES: u8 = 8;
+
+/// An in-process CTAP2 authenticator.
+///
+/// Every clone shares the same state, so the same authenticator can be opened as a [Device]
+/// several times, e.g. to simulate re-plugging it.
+pub struct SoftAuthenticator {
+    state: Arc<Mutex<State>>,
+    presence: Arc<Presence>,
+
+    /// Response to the last request of this client.
+    channel: Arc<Channel>,
+}
+
+impl SoftAuthenticator {
+    /// Create a new authenticator with no PIN and no credentials.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> SoftAuthenticator {
+        let presence = Arc::new(Presence::default());
+
+        SoftAuthenticator {
+            state: Arc::new(Mutex::new(State::new(presence.clone()))),
+            presence,
+            channel: Arc::new(Channel::default()),
+        }
+    }
+
+    /// Open this authenticator as a [Device].
+    pub fn open(&self) -> Result<Device> {
+        Device::open_with_transport(self.clone())
+    }
+
+    /// Set whether user presence tests succeed, as if the user touched the authenticator.
+    ///
+    /// Default to `true`. If `false`, operations requiring user presence fail with `FIDO_ERR_OPERATION_DENIED`.
+    pub fn set_user_presence(&self, present: bool) {
+        lock(&self.presence.touch).absent = !present;
+    }
+
+    /// Set whether user presence tests wait for [SoftAuthenticator::touch].
+    ///
+    /// Default to `false`. A request waiting for a touch fails with `FIDO_ERR_KEEPALIVE_CANCEL`
+    /// if it is cancelled, e.g. with [DeviceCancel::cancel](crate::device::DeviceCancel::cancel).
+    pub fn set_wait_for_touch(&self, wait: bool) {
+        lock(&self.presence.touch).wait = wait;
+    }
+
+    /// Touch the authenticator, completing the user presence test of the request waiting for it,
+    /// or else of the next request.
+    pub fn touch(&self) {
+        lock(&self.presence.touch).touched = true;
+        self.presence.changed.notify_all();
+    }
+
+    /// Return true if a request is waiting for [SoftAuthenticator::touch].
+    pub fn is_waiting_for_touch(&self) -> bool {
+        lock(&self.presence.touch).waiting
+    }
+
+    /// Return the number of CTAPHID cancel messages received.
+    pub fn cancel_count(&self) -> usize {
+        lock(&self.presence.touch).cancels
+    }
+
+    /// Attest new credentials with `key` and its X509 `certificate`, i.e. basic attestation,
+    /// instead of self attestation.
+    ///
+    /// `key` must be a P-256 key, the attestation signature is ES256.
+    pub fn set_attestation(&self, key: PKey<Private>, certificate: &X509Ref) -> Result<()> {
+        let certificate = certificate.to_der()?;
+        self.state().attestation = Some(Attestation { key, certificate });
+
+        Ok(())
+    }
+
+    /// Return the number of credentials stored on this authenticator, resident or not.
+    ///
+    /// This waits for a request waiting for a touch to complete.
+    pub fn credential_count(&self) -> usize {
+        self.state().credentials.len()
+    }
+
+    /// Set the PIN/UV auth protocols supported by this authenticator, in order of preference.
+    ///
+    /// Default to protocol two and one.
+    pub fn set_pin_protocols(&self, protocols: &[PinProtocol]) {
+        self.state().pin_protocols = protocols.iter().map(|it| *it as u8).collect();
+    }
+
+    /// Return the PIN/UV auth protocol of the last clientPIN request, if any.
+    pub fn last_pin_protocol(&self) -> Option<PinProtocol> {
+        match self.state().last_pin_protocol? {
+            1 => Some(PinProtocol::V1),
+            _ => Some(PinProtocol::V2),
+        }
+    }
+
+    /// Return the enterpriseAttestation parameter of the last makeCredential request, if any.
+    pub fn last_enterprise_attestation(&self) -> Option<u64> {
+        self.state().last_enterprise_attestation
+    }
+
+    /// Return the serialized largeBlob array currently stored on this authenticator.
+    pub fn large_blob_array(&self) -> Vec<u8> {
+        self.state().large_blob.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl Clone for SoftAuthenticator {
+    fn clone(&self) -> Self {
+        SoftAuthenticator {
+            state: self.state.clone(),
+            presence: self.presence.clone(),
+            channel: Arc::new(Channel::default()),
+        }
+    }
+}
+
+impl Transport for SoftAuthenticator {
+    const MESSAGES: bool = true;
+
+    fn open(&self) -> io::Result<()> {
+        self.state().power_up();
+        Ok(())
+    }
+
+    fn read(&self, _buf: &mut [u8], _timeout: Option<Duration>) -> io::Result<usize> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn write(&self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn tx(&self, cmd: u8, data: &[u8]) -> io::Result<()> {
+        match cmd {
+            CTAP_CMD_INIT => {
+                // nonce, channel id, CTAPHID protocol version, major, minor, build version, capabilities (CBOR | NMSG)
+                let mut reply = data.to_vec();
+                reply.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 2, 0, 5, 0, 0x0c]);
+
+                let request = self.channel.begin();
+                self.channel.reply(request, cmd, reply);
+            }
+            CTAP_CMD_CBOR => {
+                let request = self.channel.begin();
+                lock(&self.presence.touch).cancelled = false;
+
+                // processed on another thread, so that a request waiting for a touch can be
+                // polled with a timeout and cancelled
+                let state = self.state.clone();
+                let channel = self.channel.clone();
+                let data = data.to_vec();
+                std::thread::spawn(move || {
+                    let reply = match data.split_first() {
+                        Some((&command, request)) => lock(&state).process(command, request),
+                        None => vec![ffi::FIDO_ERR_INVALID_LENGTH as u8],
+                    };
+
+                    channel.reply(request, cmd, reply);
+                });
+            }
+            CTAP_CMD_CANCEL => {
+                let mut touch = lock(&self.presence.touch);
+                touch.cancelled = true;
+                touch.cancels += 1;
+                self.presence.changed.notify_all();
+            }
+            _ => return Err(io::ErrorKind::Unsupported.into()),
+        }
+
+        Ok(())
+    }
+
+    fn rx(&self, cmd: u8, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        match self.channel.receive(timeout) {
+            Some((reply_cmd, reply)) if reply_cmd == cmd && reply.len() <= buf.len() => {
+                buf[..reply.len()].copy_from_slice(&reply);
+                Ok(reply.len())
+            }
+            Some(_) => Err(io::ErrorKind::InvalidData.into()),
+            None => Err(io::ErrorKind::TimedOut.into()),
+        }
+    }
+}
+
+/// The response to the last request of a client, returned by the next [Transport::rx].
+#[derive(Default)]
+struct Channel {
+    response: Mutex<Response>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct Response {
+    /// Number of the last request, a late reply to an earlier one is dropped.
+    request: u64,
+    reply: Option<(u8, Vec<u8>)>,
+}
+
+impl Channel {
+    /// Start a new request and return its number.
+    fn begin(&self) -> u64 {
+        let mut response = lock(&self.response);
+        response.request += 1;
+        response.reply = None;
+
+        response.request
+    }
+
+    fn reply(&self, request: u64, cmd: u8, reply: Vec<u8>) {
+        let mut response = lock(&self.response);
+        if response.request == request {
+            response.reply = Some((cmd, reply));
+            self.ready.notify_all();
+        }
+    }
+
+    /// Wait up to `timeout` for the reply to the last request.
+    fn receive(&self, timeout: Option<Duration>) -> Option<(u8, Vec<u8>)> {
+        let response = lock(&self.response);
+        let pending = |it: &mut Response| it.reply.is_none();
+
+        let mut response = match timeout {
+            Some(timeout) => {
+                self.ready
+                    .wait_timeout_while(response, timeout, pending)
+                    .unwrap_or_else(|it| it.into_inner())
+                    .0
+            }
+            None => self
+                .ready
+                .wait_while(response, pending)
+                .unwrap_or_else(|it| it.into_inner()),
+        };
+
+        response.reply.take()
+    }
+}
+
+/// User presence of an authenticator, shared by all its clients.
+#[derive(Default)]
+struct Presence {
+    touch: Mutex<Touch>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Touch {
+    absent: bool,
+    wait: bool,
+    touched: bool,
+    waiting: bool,
+    /// Whether the current request was cancelled.
+    cancelled: bool,
+    cancels: usize,
+}
+
+impl Presence {
+    /// Test user presence, waiting for a touch if needed.
+    fn test(&self) -> CtapResult<()> {
+        let mut touch = lock(&self.touch);
+        if touch.absent {
+            return Err(ffi::FIDO_ERR_OPERATION_DENIED);
+        }
+        if !touch.wait {
+            return Ok(());
+        }
+
+        touch.waiting = true;
+        let mut touch = self
+            .changed
+            .wait_while(touch, |it| !it.touched && !it.cancelled)
+            .unwrap_or_else(|it| it.into_inner());
+        touch.waiting = false;
+
+        if touch.cancelled {
+            return Err(ffi::FIDO_ERR_KEEPALIVE_CANCEL);
+        }
+        touch.touched = false;
+
+        Ok(())
+    }
+}
+
+/// Result of a CTAP2 command, the error is a CTAP2 status code.
+type CtapResult<T> = std::result::Result<T, i32>;
+
+/// A credential stored on the authenticator.
+struct StoredCredential {
+    id: Vec<u8>,
+    rp_id: String,
+    rp_name: Option<String>,
+    user_id: Vec<u8>,
+    user_name: Option<String>,
+    display_name: Option<String>,
+    key: PKey<Private>,
+    alg: i32,
+    discoverable: bool,
+    cred_protect: u8,
+    large_blob_key: Option<Vec<u8>>,
+    /// CredRandomWithoutUV and CredRandomWithUV of hmac-secret.
+    cred_random: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// Basic attestation key and DER-encoded certificate.
+struct Attestation {
+    key: PKey<Private>,
+    certificate: Vec<u8>,
+}
+
+struct State {
+    presence: Arc<Presence>,
+    attestation: Option<Attestation>,
+    /// Whether enterprise attestation is enabled.
+    enterprise_attestation: bool,
+    last_enterprise_attestation: Option<u64>,
+    counter: u32,
+
+    pin_protocols: Vec<u8>,
+    last_pin_protocol: Option<u8>,
+    pin_hash: Option<[u8; 16]>,
+    /// Length of the PIN in code points.
+    pin_len: usize,
+    pin_retries: u8,
+    min_pin_len: usize,
+    force_pin_change: bool,
+    always_uv: bool,
+    key_agreement: PKey<Private>,
+    token: Option<PinToken>,
+
+    credentials: Vec<StoredCredential>,
+    /// Remaining credential indexes for authenticatorGetNextAssertion.
+    next_assertions: VecDeque<ctap::PendingAssertion>,
+    /// Remaining relying parties for enumerateRPsGetNextRP.
+    next_rps: VecDeque<String>,
+    /// Remaining credential indexes for enumerateCredentialsGetNextCredential.
+    next_credentials: VecDeque<usize>,
+
+    large_blob: Vec<u8>,
+    /// Partially written largeBlob array and its expected length.
+    pending_large_blob: Option<(Vec<u8>, usize)>,
+}
+
+impl State {
+    fn new(presence: Arc<Presence>) -> State {
+        State {
+            presence,
+            attestation: None,
+            enterprise_attestation: false,
+            last_enterprise_attestation: None,
+            counter: 0,
+            pin_protocols: vec![2, 1],
+            last_pin_protocol: None,
+            pin_hash: None,
+            pin_len: 0,
+            pin_retries: PIN_RETRIES,
+            min_pin_len: MIN_PIN_LEN,
+            force_pin_change: false,
+            always_uv: false,
+            key_agreement: pin::generate_key_agreement(),
+            token: None,
+            credentials: Vec::new(),
+            next_assertions: VecDeque::new(),
+            next_rps: VecDeque::new(),
+            next_credentials: VecDeque::new(),
+            large_blob: largeblob::empty_array(),
+            pending_large_blob: None,
+        }
+    }
+
+    /// Reset the volatile state, as if the authenticator was plugged in again.
+    fn power_up(&mut self) {
+        self.key_agreement = pin::generate_key_agreement();
+        self.token = None;
+        self.next_assertions.clear();
+        self.next_rps.clear();
+        self.next_credentials.clear();
+        self.pending_large_blob = None;
+    }
+
+    /// Process a CTAP2 command, return the status code followed by the CBOR response.
+    fn process(&mut self, command: u8, request: &[u8]) -> Vec<u8> {
+        let res = if request.is_empty() {
+            self.dispatch(command, &[])
+        } else {
+            match ciborium::de::from_reader::<Value, _>(request) {
+                Ok(Value::Map(request)) => self.dispatch(command, &request),
+                Ok(_) => Err(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE),
+                Err(_) => Err(ffi::FIDO_ERR_INVALID_CBOR),
+            }
+        };
+
+        match res {
+            Ok(Some(response)) => {
+                let mut reply = vec![ffi::FIDO_OK as u8];
+                ciborium::ser::into_writer(&response, &mut reply).expect("encode response");
+                reply
+            }
+            Ok(None) => vec![ffi::FIDO_OK as u8],
+            Err(code) => vec![code as u8],
+        }
+    }
+
+    fn dispatch(&mut self, command: u8, request: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        match command {
+            CTAP_CBOR_MAKECRED => self.make_credential(request).map(Some),
+            CTAP_CBOR_ASSERT => self.get_assertion(request).map(Some),
+            CTAP_CBOR_NEXT_ASSERT => self.get_next_assertion().map(Some),
+            CTAP_CBOR_GETINFO => Ok(Some(self.get_info())),
+            CTAP_CBOR_CLIENT_PIN => self.client_pin(request),
+            CTAP_CBOR_RESET => self.reset().map(|_| None),
+            CTAP_CBOR_CRED_MGMT | CTAP_CBOR_CRED_MGMT_PRE => self.credential_management(request),
+            CTAP_CBOR_LARGEBLOB => self.large_blobs(request),
+            CTAP_CBOR_CONFIG => self.authenticator_config(request),
+            _ => Err(ffi::FIDO_ERR_INVALID_COMMAND),
+        }
+    }
+
+    fn get_info(&self) -> Value {
+        let options = vec![
+            (Value::from("ep"), Value::from(self.enterprise_attestation)),
+            (Value::from("rk"), Value::from(true)),
+            (Value::from("up"), Value::from(true)),
+            (Value::from("plat"), Value::from(false)),
+            (Value::from("alwaysUv"), Value::from(self.always_uv)),
+            (Value::from("credMgmt"), Value::from(true)),
+            (Value::from("authnrCfg"), Value::from(true)),
+            (
+                Value::from("clientPin"),
+                Value::from(self.pin_hash.is_some()),
+            ),
+            (Value::from("largeBlobs"), Value::from(true)),
+            (Value::from("pinUvAuthToken"), Value::from(true)),
+            (Value::from("setMinPINLength"), Value::from(true)),
+            (
+                Value::from("makeCredUvNotRqd"),
+                Value::from(!self.always_uv),
+            ),
+        ];
+
+        let algorithms = [ffi::COSE_ES256, ffi::COSE_EDDSA]
+            .into_iter()
+            .map(|alg| {
+                Value::Map(vec![
+                    (Value::from("alg"), Value::from(alg)),
+                    (Value::from("type"), Value::from("public-key")),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        Value::Map(vec![
+            (
+                Value::from(0x01),
+                Value::from(vec![Value::from("FIDO_2_0"), Value::from("FIDO_2_1")]),
+            ),
+            (
+                Value::from(0x02),
+                Value::from(vec![
+                    Value::from("credProtect"),
+                    Value::from("hmac-secret"),
+                    Value::from("largeBlobKey"),
+                ]),
+            ),
+            (Value::from(0x03), Value::from(&AAGUID[..])),
+            (Value::from(0x04), Value::Map(options)),
+            (Value::from(0x05), Value::from(MAX_MSG_SIZE as u64)),
+            (
+                Value::from(0x06),
+                Value::from(
+                    self.pin_protocols
+                        .iter()
+                        .map(|it| Value::from(*it))
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            (
+                Value::from(0x07),
+                Value::from(MAX_CRED_COUNT_IN_LIST as u64),
+            ),
+            (Value::from(0x08), Value::from(MAX_CRED_ID_LEN as u64)),
+            (Value::from(0x09), Value::from(vec![Value::from("usb")])),
+            (Value::from(0x0a), Value::from(algorithms)),
+            (Value::from(0x0b), Value::from(MAX_LARGE_BLOB as u64)),
+            (Value::from(0x0c), Value::from(self.force_pin_change)),
+            (Value::from(0x0d), Value::from(self.min_pin_len as u64)),
+        ])
+    }
+
+    fn reset(&mut self) -> CtapResult<()> {
+        self.check_user_presence()?;
+
+        let attestation = self.attestation.take();
+        let pin_protocols = std::mem::take(&mut self.pin_protocols);
+        *self = State::new(self.presence.clone());
+        self.attestation = attestation;
+        self.pin_protocols = pin_protocols;
+
+        Ok(())
+    }
+
+    fn check_user_presence(&self) -> CtapResult<()> {
+        self.presence.test()
+    }
+
+    fn next_counter(&mut self) -> u32 {
+        self.counter = self.counter.wrapping_add(1);
+        self.counter
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|it| it.into_inner())
+}
+
+fn map_get(map: &[(Value, Value)], key: i64) -> Option<&Value> {
+    map.iter()
+        .find(|(k, _)| k.as_integer().map(i128::from) == Some(key as i128))
+        .map(|(_, v)| v)
+}
+
+fn map_get_text<'a>(map: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn as_map(value: &Value) -> CtapResult<&[(Value, Value)]> {
+    value
+        .as_map()
+        .map(Vec::as_slice)
+        .ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn as_bytes(value: &Value) -> CtapResult<&[u8]> {
+    value
+        .as_bytes()
+        .map(Vec::as_slice)
+        .ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn as_text(value: &Value) -> CtapResult<&str> {
+    value.as_text().ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn as_uint(value: &Value) -> CtapResult<u64> {
+    value
+        .as_integer()
+        .and_then(|it| u64::try_from(it).ok())
+        .ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn as_int(value: &Value) -> CtapResult<i64> {
+    value
+        .as_integer()
+        .and_then(|it| i64::try_from(it).ok())
+        .ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn as_array(value: &Value) -> CtapResult<&[Value]> {
+    value
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or(ffi::FIDO_ERR_CBOR_UNEXPECTED_TYPE)
+}
+
+fn encode(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(value, &mut buf).expect("encode cbor");
+    buf
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut buf = vec![0; len];
+    openssl::rand::rand_bytes(&mut buf).expect("random bytes");
+    buf
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    openssl::sha::sha256(data)
+}
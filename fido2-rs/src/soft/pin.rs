@@ -0,0 +1,450 @@
+//! authenticatorClientPIN and PIN/UV auth protocols 1 and 2.
+use ciborium::Value;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::derive::Deriver;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private, Public};
+use openssl::sign::Signer;
+use openssl::symm::{Cipher, Crypter, Mode};
+
+use super::{
+    CTAP2_ERR_INVALID_SUBCOMMAND, CtapResult, PIN_RETRIES, State, as_bytes, as_text, as_uint,
+    map_get, random_bytes, sha256,
+};
+
+const CLIENT_PIN_GET_RETRIES: u64 = 0x01;
+const CLIENT_PIN_GET_KEY_AGREEMENT: u64 = 0x02;
+const CLIENT_PIN_SET_PIN: u64 = 0x03;
+const CLIENT_PIN_CHANGE_PIN: u64 = 0x04;
+const CLIENT_PIN_GET_PIN_TOKEN: u64 = 0x05;
+const CLIENT_PIN_GET_PIN_UV_AUTH_TOKEN_USING_PIN: u64 = 0x09;
+
+pub(super) const PERMISSION_MC: u8 = 0x01;
+pub(super) const PERMISSION_GA: u8 = 0x02;
+pub(super) const PERMISSION_CM: u8 = 0x04;
+pub(super) const PERMISSION_LBW: u8 = 0x10;
+pub(super) const PERMISSION_ACFG: u8 = 0x20;
+
+/// A pinUvAuthToken handed out by getPinToken or getPinUvAuthTokenUsingPinWithPermissions.
+pub(super) struct PinToken {
+    protocol: u8,
+    token: Vec<u8>,
+    permissions: u8,
+    rp_id: Option<String>,
+}
+
+pub(super) fn generate_key_agreement() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).expect("p256 group");
+    let ec = EcKey::generate(&group).expect("generate p256 key");
+
+    PKey::from_ec_key(ec).expect("p256 pkey")
+}
+
+/// Encode the public part of `key` as a COSE_Key for ECDH.
+pub(super) fn cose_key_agreement(key: &PKey<Private>) -> Value {
+    let ec = key.ec_key().expect("p256 key");
+    let (x, y) = affine_coordinates(ec.group(), ec.public_key());
+
+    Value::Map(vec![
+        (Value::from(1), Value::from(2)),
+        (Value::from(3), Value::from(-25)),
+        (Value::from(-1), Value::from(1)),
+        (Value::from(-2), Value::from(x)),
+        (Value::from(-3), Value::from(y)),
+    ])
+}
+
+pub(super) fn affine_coordinates(
+    group: &openssl::ec::EcGroupRef,
+    point: &openssl::ec::EcPointRef,
+) -> (Vec<u8>, Vec<u8>) {
+    let mut ctx = BigNumContext::new().expect("bn ctx");
+    let mut x = BigNum::new().expect("bn");
+    let mut y = BigNum::new().expect("bn");
+    point
+        .affine_coordinates(group, &mut x, &mut y, &mut ctx)
+        .expect("affine coordinates");
+
+    (
+        x.to_vec_padded(32).expect("x coordinate"),
+        y.to_vec_padded(32).expect("y coordinate"),
+    )
+}
+
+/// Derive the shared secret of `protocol` from our key agreement key and the platform COSE_Key.
+pub(super) fn shared_secret(
+    protocol: u8,
+    key: &PKey<Private>,
+    peer: &Value,
+) -> CtapResult<Vec<u8>> {
+    let peer = super::as_map(peer)?;
+    let x = map_get(peer, -2).map(as_bytes).transpose()?;
+    let y = map_get(peer, -3).map(as_bytes).transpose()?;
+    let (Some(x), Some(y)) = (x, y) else {
+        return Err(ffi::FIDO_ERR_MISSING_PARAMETER);
+    };
+
+    let peer = peer_key(x, y).ok_or(ffi::FIDO_ERR_INVALID_PARAMETER)?;
+
+    let mut deriver = Deriver::new(key).map_err(|_| ffi::FIDO_ERR_ERR_OTHER)?;
+    deriver
+        .set_peer(&peer)
+        .map_err(|_| ffi::FIDO_ERR_INVALID_PARAMETER)?;
+    let z = deriver
+        .derive_to_vec()
+        .map_err(|_| ffi::FIDO_ERR_INVALID_PARAMETER)?;
+
+    match protocol {
+        1 => Ok(sha256(&z).to_vec()),
+        _ => {
+            let mut shared = hkdf_sha256(&z, b"CTAP2 HMAC key");
+            shared.extend_from_slice(&hkdf_sha256(&z, b"CTAP2 AES key"));
+            Ok(shared)
+        }
+    }
+}
+
+fn peer_key(x: &[u8], y: &[u8]) -> Option<PKey<Public>> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).ok()?;
+    let x = BigNum::from_slice(x).ok()?;
+    let y = BigNum::from_slice(y).ok()?;
+    let ec = EcKey::from_public_key_affine_coordinates(&group, &x, &y).ok()?;
+
+    PKey::from_ec_key(ec).ok()
+}
+
+/// HKDF-SHA-256 with a salt of 32 zero bytes and an output of 32 bytes.
+fn hkdf_sha256(ikm: &[u8], info: &[u8]) -> Vec<u8> {
+    let prk = hmac_sha256(&[0; 32], ikm);
+
+    let mut msg = info.to_vec();
+    msg.push(0x01);
+    hmac_sha256(&prk, &msg)
+}
+
+pub(super) fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let key = PKey::hmac(key).expect("hmac key");
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).expect("hmac signer");
+    signer.update(data).expect("hmac update");
+    signer.sign_to_vec().expect("hmac sign")
+}
+
+fn aes_256_cbc(mode: Mode, key: &[u8], iv: &[u8], data: &[u8]) -> CtapResult<Vec<u8>> {
+    if data.is_empty() || !data.len().is_multiple_of(16) {
+        return Err(ffi::FIDO_ERR_INVALID_LENGTH);
+    }
+
+    let mut crypter = Crypter::new(Cipher::aes_256_cbc(), mode, key, Some(iv))
+        .map_err(|_| ffi::FIDO_ERR_ERR_OTHER)?;
+    crypter.pad(false);
+
+    let mut out = vec![0; data.len() + 16];
+    let mut len = crypter
+        .update(data, &mut out)
+        .map_err(|_| ffi::FIDO_ERR_ERR_OTHER)?;
+    len += crypter
+        .finalize(&mut out[len..])
+        .map_err(|_| ffi::FIDO_ERR_ERR_OTHER)?;
+    out.truncate(len);
+
+    Ok(out)
+}
+
+pub(super) fn encrypt(protocol: u8, shared: &[u8], data: &[u8]) -> CtapResult<Vec<u8>> {
+    match protocol {
+        1 => aes_256_cbc(Mode::Encrypt, shared, &[0; 16], data),
+        _ => {
+            let mut out = random_bytes(16);
+            let ct = aes_256_cbc(Mode::Encrypt, &shared[32..], &out, data)?;
+            out.extend_from_slice(&ct);
+            Ok(out)
+        }
+    }
+}
+
+pub(super) fn decrypt(protocol: u8, shared: &[u8], data: &[u8]) -> CtapResult<Vec<u8>> {
+    match protocol {
+        1 => aes_256_cbc(Mode::Decrypt, shared, &[0; 16], data),
+        _ => {
+            if data.len() < 16 {
+                return Err(ffi::FIDO_ERR_INVALID_LENGTH);
+            }
+            let (iv, ct) = data.split_at(16);
+            aes_256_cbc(Mode::Decrypt, &shared[32..], iv, ct)
+        }
+    }
+}
+
+/// Check `signature` is the pinUvAuthParam of `msg` under `key` for `protocol`.
+pub(super) fn verify(protocol: u8, key: &[u8], msg: &[u8], signature: &[u8]) -> bool {
+    let mac = hmac_sha256(&key[..32], msg);
+    let mac = match protocol {
+        1 => &mac[..16],
+        _ => &mac[..],
+    };
+
+    mac.len() == signature.len() && memcmp::eq(mac, signature)
+}
+
+impl State {
+    pub(super) fn client_pin(&mut self, request: &[(Value, Value)]) -> CtapResult<Option<Value>> {
+        let sub_command = map_get(request, 0x02)
+            .map(as_uint)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+
+        if sub_command == CLIENT_PIN_GET_RETRIES {
+            return Ok(Some(Value::Map(vec![(
+                Value::from(0x03),
+                Value::from(self.pin_retries),
+            )])));
+        }
+
+        let protocol = map_get(request, 0x01)
+            .map(as_uint)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+        let protocol = self.check_protocol(protocol)?;
+        self.last_pin_protocol = Some(protocol);
+
+        match sub_command {
+            CLIENT_PIN_GET_KEY_AGREEMENT => Ok(Some(Value::Map(vec![(
+                Value::from(0x01),
+                cose_key_agreement(&self.key_agreement),
+            )]))),
+            CLIENT_PIN_SET_PIN => self.set_pin(protocol, request).map(|_| None),
+            CLIENT_PIN_CHANGE_PIN => self.change_pin(protocol, request).map(|_| None),
+            CLIENT_PIN_GET_PIN_TOKEN => self
+                .get_pin_token(protocol, request, PERMISSION_MC | PERMISSION_GA, None)
+                .map(Some),
+            CLIENT_PIN_GET_PIN_UV_AUTH_TOKEN_USING_PIN => {
+                let permissions = map_get(request, 0x09)
+                    .map(as_uint)
+                    .transpose()?
+                    .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+                let rp_id = map_get(request, 0x0a)
+                    .map(as_text)
+                    .transpose()?
+                    .map(str::to_owned);
+
+                if permissions == 0 {
+                    return Err(ffi::FIDO_ERR_INVALID_PARAMETER);
+                }
+                let supported = PERMISSION_MC
+                    | PERMISSION_GA
+                    | PERMISSION_CM
+                    | PERMISSION_LBW
+                    | PERMISSION_ACFG;
+                if permissions & !u64::from(supported) != 0 {
+                    return Err(ffi::FIDO_ERR_UNAUTHORIZED_PERM);
+                }
+                if permissions & u64::from(PERMISSION_MC | PERMISSION_GA) != 0 && rp_id.is_none() {
+                    return Err(ffi::FIDO_ERR_MISSING_PARAMETER);
+                }
+
+                self.get_pin_token(protocol, request, permissions as u8, rp_id)
+                    .map(Some)
+            }
+            _ => Err(CTAP2_ERR_INVALID_SUBCOMMAND),
+        }
+    }
+
+    fn check_protocol(&self, protocol: u64) -> CtapResult<u8> {
+        match u8::try_from(protocol) {
+            Ok(protocol) if self.pin_protocols.contains(&protocol) => Ok(protocol),
+            _ => Err(ffi::FIDO_ERR_INVALID_PARAMETER),
+        }
+    }
+
+    /// Derive the shared secret from the platform key agreement key in `request`.
+    fn request_shared_secret(
+        &self,
+        protocol: u8,
+        request: &[(Value, Value)],
+    ) -> CtapResult<Vec<u8>> {
+        let peer = map_get(request, 0x03).ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+        shared_secret(protocol, &self.key_agreement, peer)
+    }
+
+    fn set_pin(&mut self, protocol: u8, request: &[(Value, Value)]) -> CtapResult<()> {
+        let new_pin_enc = required_bytes(request, 0x05)?;
+        let pin_auth = required_bytes(request, 0x04)?;
+
+        if self.pin_hash.is_some() {
+            return Err(ffi::FIDO_ERR_PIN_AUTH_INVALID);
+        }
+
+        let shared = self.request_shared_secret(protocol, request)?;
+        if !verify(protocol, &shared, new_pin_enc, pin_auth) {
+            return Err(ffi::FIDO_ERR_PIN_AUTH_INVALID);
+        }
+
+        self.store_new_pin(protocol, &shared, new_pin_enc)
+    }
+
+    fn change_pin(&mut self, protocol: u8, request: &[(Value, Value)]) -> CtapResult<()> {
+        let new_pin_enc = required_bytes(request, 0x05)?;
+        let pin_hash_enc = required_bytes(request, 0x06)?;
+        let pin_auth = required_bytes(request, 0x04)?;
+
+        let shared = self.request_shared_secret(protocol, request)?;
+        let mut msg = new_pin_enc.to_vec();
+        msg.extend_from_slice(pin_hash_enc);
+        if !verify(protocol, &shared, &msg, pin_auth) {
+            return Err(ffi::FIDO_ERR_PIN_AUTH_INVALID);
+        }
+
+        self.check_pin_hash(protocol, &shared, pin_hash_enc)?;
+        self.store_new_pin(protocol, &shared, new_pin_enc)
+    }
+
+    fn get_pin_token(
+        &mut self,
+        protocol: u8,
+        request: &[(Value, Value)],
+        permissions: u8,
+        rp_id: Option<String>,
+    ) -> CtapResult<Value> {
+        let pin_hash_enc = required_bytes(request, 0x06)?;
+
+        let shared = self.request_shared_secret(protocol, request)?;
+        self.check_pin_hash(protocol, &shared, pin_hash_enc)?;
+        if self.force_pin_change {
+            return Err(ffi::FIDO_ERR_PIN_POLICY_VIOLATION);
+        }
+
+        let token = random_bytes(32);
+        let token_enc = encrypt(protocol, &shared, &token)?;
+        self.token = Some(PinToken {
+            protocol,
+            token,
+            permissions,
+            rp_id,
+        });
+
+        Ok(Value::Map(vec![(
+            Value::from(0x02),
+            Value::from(token_enc),
+        )]))
+    }
+
+    fn check_pin_hash(
+        &mut self,
+        protocol: u8,
+        shared: &[u8],
+        pin_hash_enc: &[u8],
+    ) -> CtapResult<()> {
+        let Some(pin_hash) = self.pin_hash else {
+            return Err(ffi::FIDO_ERR_PIN_NOT_SET);
+        };
+        if self.pin_retries == 0 {
+            return Err(ffi::FIDO_ERR_PIN_BLOCKED);
+        }
+
+        self.pin_retries -= 1;
+
+        let hash = decrypt(protocol, shared, pin_hash_enc)?;
+        if hash.len() != 16 || !memcmp::eq(&hash, &pin_hash) {
+            self.key_agreement = generate_key_agreement();
+
+            return if self.pin_retries == 0 {
+                Err(ffi::FIDO_ERR_PIN_BLOCKED)
+            } else {
+                Err(ffi::FIDO_ERR_PIN_INVALID)
+            };
+        }
+
+        self.pin_retries = PIN_RETRIES;
+
+        Ok(())
+    }
+
+    fn store_new_pin(&mut self, protocol: u8, shared: &[u8], new_pin_enc: &[u8]) -> CtapResult<()> {
+        let padded = decrypt(protocol, shared, new_pin_enc)?;
+        if padded.len() < 64 {
+            return Err(ffi::FIDO_ERR_PIN_POLICY_VIOLATION);
+        }
+
+        let len = padded
+            .iter()
+            .position(|it| *it == 0)
+            .unwrap_or(padded.len());
+        let pin =
+            std::str::from_utf8(&padded[..len]).map_err(|_| ffi::FIDO_ERR_PIN_POLICY_VIOLATION)?;
+        let pin_len = pin.chars().count();
+        if pin_len < self.min_pin_len || pin.len() > 63 {
+            return Err(ffi::FIDO_ERR_PIN_POLICY_VIOLATION);
+        }
+
+        let mut pin_hash = [0; 16];
+        pin_hash.copy_from_slice(&sha256(pin.as_bytes())[..16]);
+
+        self.pin_hash = Some(pin_hash);
+        self.pin_len = pin_len;
+        self.force_pin_change = false;
+        self.pin_retries = PIN_RETRIES;
+        self.token = None;
+
+        Ok(())
+    }
+
+    /// Check `pin_auth` is a valid pinUvAuthParam of `msg` for the current pinUvAuthToken,
+    /// which must have been granted `permission`, bound to `rp_id` if any.
+    pub(super) fn check_pin_uv_auth(
+        &mut self,
+        protocol: Option<&Value>,
+        pin_auth: &[u8],
+        msg: &[u8],
+        permission: u8,
+        rp_id: Option<&str>,
+    ) -> CtapResult<()> {
+        let protocol = protocol
+            .map(as_uint)
+            .transpose()?
+            .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)?;
+        let protocol = self.check_protocol(protocol)?;
+
+        let Some(token) = &mut self.token else {
+            return Err(ffi::FIDO_ERR_PIN_AUTH_INVALID);
+        };
+
+        if token.protocol != protocol || !verify(protocol, &token.token, msg, pin_auth) {
+            return Err(ffi::FIDO_ERR_PIN_AUTH_INVALID);
+        }
+
+        if token.permissions & permission == 0 {
+            return Err(ffi::FIDO_ERR_PIN_AUTH_INVALID);
+        }
+
+        if let Some(rp_id) = rp_id {
+            match &token.rp_id {
+                Some(bound) if bound != rp_id => return Err(ffi::FIDO_ERR_PIN_AUTH_INVALID),
+                Some(_) => {}
+                None => token.rp_id = Some(rp_id.to_owned()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a zero length pinUvAuthParam, sent by platforms to wait for a touch.
+    pub(super) fn touch_probe(&self) -> CtapResult<Value> {
+        self.check_user_presence()?;
+
+        if self.pin_hash.is_some() {
+            Err(ffi::FIDO_ERR_PIN_INVALID)
+        } else {
+            Err(ffi::FIDO_ERR_PIN_NOT_SET)
+        }
+    }
+}
+
+fn required_bytes(request: &[(Value, Value)], key: i64) -> CtapResult<&[u8]> {
+    map_get(request, key)
+        .map(as_bytes)
+        .transpose()?
+        .ok_or(ffi::FIDO_ERR_MISSING_PARAMETER)
+}
@@ -0,0 +1,322 @@
+//! Pluggable I/O transport for [Device](crate::device::Device).
+//!
+//! By default libfido2 talks to devices through its built-in HID, NFC and PC/SC backends.
+//! A [Transport] replaces these backends, which makes it possible to reach an authenticator
+//! over a network socket, a USB/IP bridge or an in-process simulator.
+use std::ffi::CStr;
+use std::io;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use ciborium::Value;
+
+/// Prefix of the device path passed to `fido_dev_open` for a custom transport.
+const PATH_PREFIX: &str = "rust-transport:";
+
+const CTAP_CMD_CBOR: u8 = 0x10;
+const CTAP_CBOR_GETINFO: u8 = 0x04;
+
+/// A custom transport to a FIDO device.
+///
+/// By default, libfido2 frames CTAPHID messages into HID reports itself and exchanges them
+/// through [Transport::read] and [Transport::write].
+///
+/// If [Transport::MESSAGES] is `true`, whole CTAPHID messages are exchanged through
+/// [Transport::tx] and [Transport::rx] instead, and the transport is responsible for framing.
+///
+/// # Concurrency
+///
+/// Requests are sent from the thread using the [Device](crate::device::Device), but a
+/// [DeviceCancel](crate::device::DeviceCancel) may cancel them from any other thread: libfido2
+/// then sends a CTAPHID_CANCEL through [Transport::write] or [Transport::tx] while the device
+/// thread is blocked in [Transport::read] or [Transport::rx]. A transport must allow this, and
+/// a write must not wait for a pending read to return.
+///
+/// [Transport::open] is called before any other method, and [Transport::close] after all others
+/// returned.
+pub trait Transport: Send + Sync + 'static {
+    /// Whether this transport exchanges whole CTAPHID messages through [Transport::tx] and
+    /// [Transport::rx].
+    const MESSAGES: bool = false;
+
+    /// Called when the device is opened.
+    fn open(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called when the device is closed.
+    fn close(&self) {}
+
+    /// Read a single HID report into `buf`, waiting up to `timeout`.
+    ///
+    /// A `timeout` of [None] means wait forever. If nothing was read in time, return an error of
+    /// kind [io::ErrorKind::TimedOut], the request then fails with
+    /// [Error::Timeout](crate::error::Error::Timeout).
+    ///
+    /// Return the number of bytes read.
+    fn read(&self, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize>;
+
+    /// Write a single HID report.
+    ///
+    /// Return the number of bytes written.
+    fn write(&self, buf: &[u8]) -> io::Result<usize>;
+
+    /// Send a CTAPHID message with command `cmd` and payload `data`.
+    ///
+    /// Only used if [Transport::MESSAGES] is `true`.
+    fn tx(&self, cmd: u8, data: &[u8]) -> io::Result<()> {
+        let _ = (cmd, data);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Receive the payload of a CTAPHID message with command `cmd` into `buf`, waiting up to `timeout`.
+    ///
+    /// Only used if [Transport::MESSAGES] is `true`. Timeouts are reported as in [Transport::read].
+    ///
+    /// Return the number of bytes received.
+    fn rx(&self, cmd: u8, buf: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        let _ = (cmd, buf, timeout);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// A [Transport] and whether it reported a timeout, the I/O handle of the callbacks.
+struct Io<T> {
+    transport: T,
+    timed_out: Arc<AtomicBool>,
+    /// The only PIN/UV auth protocol reported to libfido2, if restricted.
+    pin_protocol: Option<u8>,
+    /// Whether the last message sent is a getInfo request.
+    get_info: AtomicBool,
+}
+
+impl<T> Io<T> {
+    /// Return the length of `res`, or -1 on error after remembering a timeout.
+    fn len_or_error(&self, res: std::thread::Result<io::Result<usize>>) -> c_int {
+        match res {
+            Ok(Ok(len)) => c_int::try_from(len).unwrap_or(-1),
+            Ok(Err(e)) => {
+                if e.kind() == io::ErrorKind::TimedOut {
+                    self.timed_out.store(true, Ordering::Relaxed);
+                }
+                -1
+            }
+            Err(_) => -1,
+        }
+    }
+}
+
+/// Owned pointer to a boxed [Transport], released when the owning device is dropped.
+pub(crate) struct TransportHandle {
+    ptr: *mut c_void,
+    drop: unsafe fn(*mut c_void),
+    timed_out: Arc<AtomicBool>,
+}
+
+impl TransportHandle {
+    /// Return a handle to `transport`, reporting only `pin_protocol` in the getInfo responses of
+    /// a [Transport::MESSAGES] transport if any.
+    pub(crate) fn new<T: Transport>(transport: T, pin_protocol: Option<u8>) -> TransportHandle {
+        unsafe fn drop_transport<T>(ptr: *mut c_void) {
+            unsafe {
+                drop(Box::from_raw(ptr as *mut Io<T>));
+            }
+        }
+
+        let timed_out = Arc::new(AtomicBool::new(false));
+        let io = Io {
+            transport,
+            timed_out: timed_out.clone(),
+            pin_protocol,
+            get_info: AtomicBool::new(false),
+        };
+
+        TransportHandle {
+            ptr: Box::into_raw(Box::new(io)) as *mut c_void,
+            drop: drop_transport::<T>,
+            timed_out,
+        }
+    }
+
+    /// The path to pass to `fido_dev_open`, from which [io_open] recovers the transport.
+    pub(crate) fn path(&self) -> String {
+        format!("{}{:x}", PATH_PREFIX, self.ptr as usize)
+    }
+
+    /// Return true if a read timed out since the last call.
+    pub(crate) fn take_timeout(&self) -> bool {
+        self.timed_out.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Drop for TransportHandle {
+    fn drop(&mut self) {
+        unsafe {
+            (self.drop)(self.ptr);
+        }
+    }
+}
+
+pub(crate) fn io_functions<T: Transport>() -> ffi::fido_dev_io_t {
+    ffi::fido_dev_io_t {
+        open: Some(io_open::<T>),
+        close: Some(io_close::<T>),
+        read: Some(io_read::<T>),
+        write: Some(io_write::<T>),
+    }
+}
+
+pub(crate) fn transport_functions<T: Transport>() -> ffi::fido_dev_transport_t {
+    ffi::fido_dev_transport_t {
+        rx: Some(rx::<T>),
+        tx: Some(tx::<T>),
+    }
+}
+
+fn timeout(ms: c_int) -> Option<Duration> {
+    u64::try_from(ms).ok().map(Duration::from_millis)
+}
+
+unsafe extern "C" fn io_open<T: Transport>(path: *const c_char) -> *mut c_void {
+    let path = unsafe { CStr::from_ptr(path) };
+    let Some(addr) = path
+        .to_str()
+        .ok()
+        .and_then(|it| it.strip_prefix(PATH_PREFIX))
+        .and_then(|it| usize::from_str_radix(it, 16).ok())
+    else {
+        return std::ptr::null_mut();
+    };
+
+    let io = addr as *const Io<T>;
+    let res = catch_unwind(AssertUnwindSafe(|| unsafe { (*io).transport.open() }));
+
+    match res {
+        Ok(Ok(())) => io as *mut c_void,
+        _ => std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn io_close<T: Transport>(handle: *mut c_void) {
+    let io = handle as *const Io<T>;
+    let _ = catch_unwind(AssertUnwindSafe(|| unsafe { (*io).transport.close() }));
+}
+
+unsafe extern "C" fn io_read<T: Transport>(
+    handle: *mut c_void,
+    buf: *mut u8,
+    len: usize,
+    ms: c_int,
+) -> c_int {
+    let io = unsafe { &*(handle as *const Io<T>) };
+    let res = catch_unwind(AssertUnwindSafe(|| unsafe {
+        let buf = std::slice::from_raw_parts_mut(buf, len);
+        io.transport.read(buf, timeout(ms))
+    }));
+
+    io.len_or_error(res)
+}
+
+unsafe extern "C" fn io_write<T: Transport>(
+    handle: *mut c_void,
+    buf: *const u8,
+    len: usize,
+) -> c_int {
+    let io = unsafe { &*(handle as *const Io<T>) };
+    let res = catch_unwind(AssertUnwindSafe(|| unsafe {
+        let buf = std::slice::from_raw_parts(buf, len);
+        io.transport.write(buf)
+    }));
+
+    io.len_or_error(res)
+}
+
+unsafe extern "C" fn tx<T: Transport>(
+    dev: *mut ffi::fido_dev_t,
+    cmd: u8,
+    buf: *const u8,
+    len: usize,
+) -> c_int {
+    let io = unsafe { &*(ffi::fido_dev_io_handle(dev) as *const Io<T>) };
+    let res = catch_unwind(AssertUnwindSafe(|| unsafe {
+        let buf = if len == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(buf, len)
+        };
+        let get_info = cmd == CTAP_CMD_CBOR && buf.first() == Some(&CTAP_CBOR_GETINFO);
+        io.get_info.store(get_info, Ordering::Relaxed);
+        io.transport.tx(cmd, buf)
+    }));
+
+    match res {
+        Ok(Ok(())) => 0,
+        _ => -1,
+    }
+}
+
+unsafe extern "C" fn rx<T: Transport>(
+    dev: *mut ffi::fido_dev_t,
+    cmd: u8,
+    buf: *mut u8,
+    len: usize,
+    ms: c_int,
+) -> c_int {
+    let io = unsafe { &*(ffi::fido_dev_io_handle(dev) as *const Io<T>) };
+    let res = catch_unwind(AssertUnwindSafe(|| unsafe {
+        let buf = if len == 0 {
+            &mut []
+        } else {
+            std::slice::from_raw_parts_mut(buf, len)
+        };
+        let len = io.transport.rx(cmd, buf, timeout(ms))?;
+
+        match io.pin_protocol {
+            Some(protocol)
+                if cmd == CTAP_CMD_CBOR && io.get_info.swap(false, Ordering::Relaxed) =>
+            {
+                Ok(restrict_pin_protocols(&mut buf[..len], protocol).unwrap_or(len))
+            }
+            _ => Ok(len),
+        }
+    }));
+
+    io.len_or_error(res)
+}
+
+/// Keep only `protocol` in the pinUvAuthProtocols of `reply`, a getInfo response made of a status
+/// byte and a CBOR map, and return its new length.
+///
+/// libfido2 selects the PIN/UV auth protocol among the ones reported when the device is opened.
+fn restrict_pin_protocols(reply: &mut [u8], protocol: u8) -> Option<usize> {
+    let (&status, data) = reply.split_first()?;
+    if status != 0 {
+        return None;
+    }
+
+    let mut info: Value = ciborium::de::from_reader(data).ok()?;
+    let protocols = info
+        .as_map_mut()?
+        .iter_mut()
+        .find(|(k, _)| k.as_integer() == Some(0x06.into()))
+        .map(|(_, v)| v)?;
+    let supported = protocols
+        .as_array()?
+        .iter()
+        .any(|it| it.as_integer() == Some(protocol.into()));
+    *protocols = if supported {
+        Value::Array(vec![Value::from(protocol)])
+    } else {
+        Value::Array(vec![])
+    };
+
+    // removing protocols only shortens the response
+    let mut data = Vec::new();
+    ciborium::ser::into_writer(&info, &mut data).ok()?;
+    reply[1..=data.len()].copy_from_slice(&data);
+
+    Some(data.len() + 1)
+}
@@ -7,6 +7,18 @@ pub(crate) const fn check(code: i32) -> Result<(), FidoError> {
     }
 }
 
+/// Return the `len` elements at `ptr`, libfido2 returns a null pointer for unset fields.
+///
+/// # Safety
+/// `ptr` must be null, or valid for reads of `len` elements during `'a`.
+pub(crate) unsafe fn slice_or_empty<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    if ptr.is_null() {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(ptr, len) }
+    }
+}
+
 macro_rules! str_or_none {
     ($ptr:ident) => {
         if $ptr.is_null() {
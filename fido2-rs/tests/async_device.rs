@@ -0,0 +1,128 @@
+//! Tests of [AsyncDevice] against the in-process [SoftAuthenticator].
+#![cfg(all(feature = "tokio", feature = "soft-authenticator"))]
+
+use std::thread;
+use std::time::Duration;
+
+use fido2_rs::async_device::AsyncDevice;
+use fido2_rs::credentials::{CoseType, Credential, Opt};
+use fido2_rs::error::{Error, FidoErrorKind, Result};
+use fido2_rs::soft::SoftAuthenticator;
+
+const PIN: &str = "1234";
+const RP_ID: &str = "fido2-rs.example";
+
+/// Return a new authenticator with [PIN] set, and an async device opened on it.
+fn setup() -> Result<(SoftAuthenticator, AsyncDevice)> {
+    let authenticator = SoftAuthenticator::new();
+    let dev = authenticator.open()?;
+    dev.set_pin(PIN, None)?;
+
+    Ok((authenticator, AsyncDevice::new(dev)))
+}
+
+/// Return a request for a resident credential of the user `name`.
+fn credential(user_id: &[u8], name: &str) -> Result<Credential> {
+    let mut cred = Credential::new();
+    cred.set_client_data(b"make credential")?;
+    cred.set_rp(RP_ID, "async device tests")?;
+    cred.set_user(user_id, name, Some(name), None)?;
+    cred.set_cose_type(CoseType::ES256)?;
+    cred.set_rk(Opt::True)?;
+
+    Ok(cred)
+}
+
+/// Wait until a request waits for a touch of `authenticator`.
+fn wait_for_request(authenticator: &SoftAuthenticator) {
+    while !authenticator.is_waiting_for_touch() {
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[tokio::test]
+async fn completed() -> Result<()> {
+    let (authenticator, dev) = setup()?;
+
+    let cred = dev
+        .make_credential(credential(&[1], "alice")?, Some(PIN))
+        .await?;
+    cred.verify_self()?;
+
+    assert_eq!(authenticator.credential_count(), 1);
+    assert_eq!(authenticator.cancel_count(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn dropped() -> Result<()> {
+    let (authenticator, dev) = setup()?;
+    authenticator.set_wait_for_touch(true);
+
+    // a running request is cancelled on the device
+    let running = dev.make_credential(credential(&[1], "alice")?, Some(PIN));
+    wait_for_request(&authenticator);
+    drop(running);
+    assert_eq!(authenticator.cancel_count(), 1);
+
+    // a queued one is skipped
+    let running = dev.make_credential(credential(&[2], "bob")?, Some(PIN));
+    let queued = dev.make_credential(credential(&[3], "carol")?, Some(PIN));
+    wait_for_request(&authenticator);
+    drop(queued);
+    authenticator.touch();
+
+    let bob = running.await?;
+    assert_eq!(bob.user_name(), Some("bob"));
+    assert_eq!(authenticator.cancel_count(), 1);
+
+    authenticator.set_wait_for_touch(false);
+    dev.largeblob_get_array().await?;
+    assert_eq!(authenticator.credential_count(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn queued_after_cancel() -> Result<()> {
+    let (authenticator, dev) = setup()?;
+    authenticator.set_wait_for_touch(true);
+
+    let cancelled = dev.make_credential(credential(&[1], "alice")?, Some(PIN));
+    let queued = dev.make_credential(credential(&[2], "bob")?, Some(PIN));
+    wait_for_request(&authenticator);
+
+    // the first request may complete with this touch while its future is dropped, the cancel
+    // must then not reach the second one
+    let touch = {
+        let authenticator = authenticator.clone();
+        thread::spawn(move || authenticator.touch())
+    };
+    drop(cancelled);
+    touch.join().unwrap();
+    authenticator.touch();
+
+    let bob = queued.await?;
+    assert_eq!(bob.user_name(), Some("bob"));
+    assert_eq!(authenticator.cancel_count(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_handle() -> Result<()> {
+    let (authenticator, dev) = setup()?;
+    authenticator.set_wait_for_touch(true);
+
+    let pending = dev.make_credential(credential(&[1], "alice")?, Some(PIN));
+    wait_for_request(&authenticator);
+    dev.cancel_handle().cancel();
+
+    match pending.await {
+        Err(Error::Fido(e)) => assert_eq!(e.kind(), FidoErrorKind::KeepaliveCancel),
+        _ => panic!("request not cancelled"),
+    }
+
+    Ok(())
+}
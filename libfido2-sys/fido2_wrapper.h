@@ -1,4 +1,6 @@
 #include <fido.h>
+#include <fido/bio.h>
+#include <fido/config.h>
 #include <fido/credman.h>
 #include <fido/rs256.h>
 #include <fido/es256.h>